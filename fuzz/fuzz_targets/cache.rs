@@ -0,0 +1,14 @@
+// Copyright 2025 Amon Rayfa.
+// SPDX-License-Identifier: Apache-2.0.
+
+//! The fuzz target of the cache, which feeds arbitrary content to its parsing and migration layers. It can be run with
+//! `cargo fuzz run cache tests/fixtures/cache` from the root of the repository, and the inputs that make it crash must be
+//! added to the `tests/fixtures/cache` corpus once fixed, so that they are replayed by the tests of the program.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    wapi::api::cache::fuzz_parse(data);
+});
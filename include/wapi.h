@@ -0,0 +1,199 @@
+/* Copyright 2025 Amon Rayfa.
+ * SPDX-License-Identifier: Apache-2.0. */
+
+#ifndef WAPI_H
+#define WAPI_H
+
+/* This file is generated by cbindgen from `src/ffi.rs`, do not edit it by hand. */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+// The flag of `wapi_update` updating the records even if the detected IP addresses are the ones stored in the cache (see
+// [`UpdateOptions::force`](wapi::api::update::UpdateOptions)).
+#define WAPI_UPDATE_FORCE (1 << 0)
+
+// The flag of `wapi_update` skipping the records whose DNS provider reports safety warnings (see
+// [`UpdateOptions::block_on_warnings`](wapi::api::update::UpdateOptions)).
+#define WAPI_UPDATE_BLOCK_ON_WARNINGS (1 << 1)
+
+// The status of the last call made to the C API on the current thread.
+typedef enum WapiStatus {
+  // The call succeeded.
+  WAPI_STATUS_OK = 0,
+  // The operation failed, and its stable code is returned by `wapi_last_error_code` (see
+  // [`Error::code`](wapi::Error)).
+  WAPI_STATUS_ERROR = 1,
+  // An argument is invalid (e.g. a `NULL` pointer or a string that is not valid UTF-8).
+  WAPI_STATUS_INVALID_ARGUMENT = 2,
+  // The library panicked, which was caught at the boundary of the C API.
+  WAPI_STATUS_PANIC = 3,
+} WapiStatus;
+
+// The outcome of the update of a record, as returned by `wapi_report_record_outcome` (see
+// [`RecordOutcome`](wapi::api::update::RecordOutcome)).
+typedef enum WapiOutcome {
+  // The record was changed to point to the new address.
+  WAPI_OUTCOME_UPDATED = 0,
+  // The record already pointed to the address.
+  WAPI_OUTCOME_UNCHANGED = 1,
+  // The record was not updated, for the reason returned by `wapi_report_record_detail`.
+  WAPI_OUTCOME_SKIPPED = 2,
+  // The record could not be updated, because of the error returned by `wapi_report_record_detail`.
+  WAPI_OUTCOME_FAILED = 3,
+  // The record was not updated because its DNS provider is disabled.
+  WAPI_OUTCOME_DISABLED = 4,
+} WapiOutcome;
+
+// The client driven through the C API, created with `wapi_client_new` and released with `wapi_client_free`.
+typedef struct WapiClient WapiClient;
+
+// The report of an update run, returned by `wapi_update` and released with `wapi_report_free`. Its strings are kept alive
+// until it is released.
+typedef struct WapiReport WapiReport;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Creates a client keeping its cache in the cache file at the given path, or in the cache file of the program if the path is
+// `NULL`. The client uses the default configurations, and must be released with `wapi_client_free`. `NULL` is returned if the
+// path is not valid UTF-8 or if the client cannot be built.
+//
+// # Safety
+//
+// The path must be `NULL` or a valid NUL-terminated string.
+WapiClient *wapi_client_new(const char *cache_path);
+
+// Releases a client created with `wapi_client_new`. Nothing happens if the client is `NULL`.
+//
+// # Safety
+//
+// The client must be `NULL` or a pointer returned by `wapi_client_new` that was not released yet.
+void wapi_client_free(WapiClient *client);
+
+// Runs an update (see [`Client::update`](wapi::Client)) with the flags (`WAPI_UPDATE_*`, combined with `|`), and returns its
+// report, which must be released with `wapi_report_free`. `NULL` is returned if the client is `NULL` or if the update fails.
+//
+// # Safety
+//
+// The client must be `NULL` or a valid pointer returned by `wapi_client_new`.
+WapiReport *wapi_update(const WapiClient *client, uint32_t flags);
+
+// Releases a report returned by `wapi_update`, along with its strings. Nothing happens if the report is `NULL`.
+//
+// # Safety
+//
+// The report must be `NULL` or a pointer returned by `wapi_update` that was not released yet.
+void wapi_report_free(WapiReport *report);
+
+// Returns the IPv4 address the A records were updated to, or `NULL` if none was detected (or if the report is `NULL`). The
+// string belongs to the report.
+//
+// # Safety
+//
+// The report must be `NULL` or a valid pointer returned by `wapi_update`.
+const char *wapi_report_ipv4(const WapiReport *report);
+
+// Returns the IPv6 address the AAAA records were updated to, or `NULL` if none was detected (or if the report is `NULL`).
+// The string belongs to the report.
+//
+// # Safety
+//
+// The report must be `NULL` or a valid pointer returned by `wapi_update`.
+const char *wapi_report_ipv6(const WapiReport *report);
+
+// Returns the number of records that were updated (`0` if the report is `NULL`).
+//
+// # Safety
+//
+// The report must be `NULL` or a valid pointer returned by `wapi_update`.
+size_t wapi_report_updated(const WapiReport *report);
+
+// Returns the number of records that were already up to date (`0` if the report is `NULL`).
+//
+// # Safety
+//
+// The report must be `NULL` or a valid pointer returned by `wapi_update`.
+size_t wapi_report_unchanged(const WapiReport *report);
+
+// Returns the number of records that were skipped (`0` if the report is `NULL`).
+//
+// # Safety
+//
+// The report must be `NULL` or a valid pointer returned by `wapi_update`.
+size_t wapi_report_skipped(const WapiReport *report);
+
+// Returns the number of records that could not be updated (`0` if the report is `NULL`).
+//
+// # Safety
+//
+// The report must be `NULL` or a valid pointer returned by `wapi_update`.
+size_t wapi_report_failed(const WapiReport *report);
+
+// Returns the number of records that were not updated because their DNS provider is disabled (`0` if the report is
+// `NULL`).
+//
+// # Safety
+//
+// The report must be `NULL` or a valid pointer returned by `wapi_update`.
+size_t wapi_report_disabled(const WapiReport *report);
+
+// Returns the number of records of the report, which are indexed from `0` by the `wapi_report_record_*` functions (`0` if
+// the report is `NULL`).
+//
+// # Safety
+//
+// The report must be `NULL` or a valid pointer returned by `wapi_update`.
+size_t wapi_report_record_count(const WapiReport *report);
+
+// Returns the DNS provider of a record of the report (followed by its alias if it has one), or `NULL` if the index is out of
+// bounds (or if the report is `NULL`). The string belongs to the report.
+//
+// # Safety
+//
+// The report must be `NULL` or a valid pointer returned by `wapi_update`.
+const char *wapi_report_record_provider(const WapiReport *report, size_t index);
+
+// Returns the fully qualified domain name of a record of the report, or `NULL` if the index is out of bounds (or if the
+// report is `NULL`). The string belongs to the report.
+//
+// # Safety
+//
+// The report must be `NULL` or a valid pointer returned by `wapi_update`.
+const char *wapi_report_record_fqdn(const WapiReport *report, size_t index);
+
+// Returns the outcome of a record of the report. `WAPI_OUTCOME_FAILED` is returned if the index is out of bounds (or if the
+// report is `NULL`).
+//
+// # Safety
+//
+// The report must be `NULL` or a valid pointer returned by `wapi_update`.
+WapiOutcome wapi_report_record_outcome(const WapiReport *report, size_t index);
+
+// Returns the reason a record of the report was skipped or the error that prevented its update, or `NULL` if it has none
+// (or if the index is out of bounds, or if the report is `NULL`). The string belongs to the report.
+//
+// # Safety
+//
+// The report must be `NULL` or a valid pointer returned by `wapi_update`.
+const char *wapi_report_record_detail(const WapiReport *report, size_t index);
+
+// Returns the status of the last call made to the C API on the current thread.
+WapiStatus wapi_last_error_status(void);
+
+// Returns the stable code of the last error of the current thread (one of [`ERROR_CODES`](wapi::ERROR_CODES)), or `NULL` if
+// the last call succeeded or did not fail with `WAPI_STATUS_ERROR`. The string is valid until the next call made to the C
+// API on the current thread.
+const char *wapi_last_error_code(void);
+
+// Returns the message of the last error of the current thread, or `NULL` if the last call succeeded. The string is valid
+// until the next call made to the C API on the current thread.
+const char *wapi_last_error_message(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* WAPI_H */
@@ -3,16 +3,24 @@
 
 //! This module contains the struct and methods used to manipulate the program's cache.
 
-use crate::error::api::{Error, Result};
-use chrono::Local;
+use crate::api::format::{CacheFormat, JsonFormat};
+use crate::api::provider;
+use crate::api::request::{is_valid_header_name, is_valid_header_value};
+use crate::error::api::{Error, Result, ResultExt};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local, NaiveDateTime, SecondsFormat, TimeZone, Utc};
 use directories::BaseDirs;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
-use std::net::{Ipv4Addr, Ipv6Addr};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::path::{Path, PathBuf};
+use std::time::Instant;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct Metadata {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     warning: String,
     name: String,
     version: String,
@@ -21,18 +29,485 @@ struct Metadata {
     timestamp: String,
 }
 
+/// The type of a DNS record managed by the program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum RecordType {
+    /// A record pointing a domain name to an IPv4 address.
+    A,
+    /// A record pointing a domain name to an IPv6 address.
+    #[serde(rename = "AAAA")]
+    Aaaa,
+    /// A record holding a text value computed from the detected IP addresses (see
+    /// [`RecordConfig::value_template`](wapi::RecordConfig)).
+    #[serde(rename = "TXT")]
+    Txt,
+    /// A record aliasing a domain name to a hostname computed from the detected IP addresses (see
+    /// [`RecordConfig::value_template`](wapi::RecordConfig)).
+    #[serde(rename = "CNAME")]
+    Cname,
+}
+
+impl std::fmt::Display for RecordType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecordType::A => write!(f, "A"),
+            RecordType::Aaaa => write!(f, "AAAA"),
+            RecordType::Txt => write!(f, "TXT"),
+            RecordType::Cname => write!(f, "CNAME"),
+        }
+    }
+}
+
+/// The struct describing a DNS record managed through a DNS provider. The records of a single DNS provider can belong to
+/// different domains, so that one set of credentials can be used for every zone of the same account.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RecordConfig {
+    /// The domain (zone) the record belongs to, e.g. `example.com`.
+    pub domain: String,
+    /// The subdomain of the record, e.g. `home` for `home.example.com`. An empty subdomain targets the domain itself.
+    #[serde(default)]
+    pub subdomain: String,
+    /// The type of the record.
+    pub record_type: RecordType,
+    /// The time to live of the record, in seconds.
+    pub ttl: u32,
+    /// The ID of the zone the record belongs to, for the DNS providers whose APIs identify zones by ID rather than by domain
+    /// name (e.g. Cloudflare). It is filled in the first time the zone is resolved and reused afterwards, so that the lookup
+    /// does not have to be made on every update.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zone_id: Option<String>,
+    /// The template of the value pushed to the record, in which the `{ipv4}` and `{ipv6}` placeholders are replaced with the
+    /// detected IP addresses (e.g. `v=spf1 ip4:{ipv4} -all`). It is required by the TXT and CNAME records, while the A and
+    /// AAAA records can only use the placeholder of their own family (which is the same as using no template).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value_template: Option<String>,
+    /// The static addresses held by the record along with the detected one (e.g. for a round-robin between several hosts).
+    /// They must belong to the family of the record (so only the A and AAAA records can have some), and are only supported by
+    /// the DNS providers whose API can set several values for a record.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub static_values: Vec<IpAddr>,
+}
+
+/// The placeholder replaced with the detected IPv4 address in the value templates of the records.
+pub const IPV4_PLACEHOLDER: &str = "{ipv4}";
+
+/// The placeholder replaced with the detected IPv6 address in the value templates of the records.
+pub const IPV6_PLACEHOLDER: &str = "{ipv6}";
+
+impl RecordConfig {
+    /// Creates a new record configuration with no cached zone ID and no value template.
+    pub fn new(domain: &str, subdomain: &str, record_type: RecordType, ttl: u32) -> RecordConfig {
+        RecordConfig {
+            domain: domain.to_string(),
+            subdomain: subdomain.to_string(),
+            record_type,
+            ttl,
+            zone_id: None,
+            value_template: None,
+            static_values: Vec::new(),
+        }
+    }
+
+    /// Sets the template of the value pushed to the record (see
+    /// [`RecordConfig::value_template`](wapi::RecordConfig)).
+    pub fn with_value_template(mut self, template: &str) -> RecordConfig {
+        self.value_template = Some(template.to_string());
+        self
+    }
+
+    /// Sets the static addresses held by the record along with the detected one (see
+    /// [`RecordConfig::static_values`](wapi::RecordConfig)).
+    pub fn with_static_values(mut self, values: &[IpAddr]) -> RecordConfig {
+        self.static_values = values.to_vec();
+        self
+    }
+
+    /// Returns the full set of addresses the record must hold: the detected address, followed by the static ones that differ
+    /// from it.
+    pub fn addresses(&self, detected: IpAddr) -> Vec<IpAddr> {
+        let mut addresses = vec![detected];
+        for value in self.static_values.iter() {
+            if !addresses.contains(value) {
+                addresses.push(*value);
+            }
+        }
+
+        addresses
+    }
+
+    /// Returns `true` if the value of the record depends on the detected IPv4 address.
+    pub fn uses_ipv4(&self) -> bool {
+        match self.record_type {
+            RecordType::A => true,
+            RecordType::Aaaa => false,
+            RecordType::Txt | RecordType::Cname => self.value_template.as_deref().is_some_and(|t| t.contains(IPV4_PLACEHOLDER)),
+        }
+    }
+
+    /// Returns `true` if the value of the record depends on the detected IPv6 address.
+    pub fn uses_ipv6(&self) -> bool {
+        match self.record_type {
+            RecordType::A => false,
+            RecordType::Aaaa => true,
+            RecordType::Txt | RecordType::Cname => self.value_template.as_deref().is_some_and(|t| t.contains(IPV6_PLACEHOLDER)),
+        }
+    }
+
+    /// Returns the value of a TXT or CNAME record, computed by replacing the placeholders of its template with the detected
+    /// IP addresses. `None` is returned if the record has no template, or if an address used by the template was not
+    /// detected.
+    pub fn render_value(&self, ipv4: Option<Ipv4Addr>, ipv6: Option<Ipv6Addr>) -> Option<String> {
+        let mut value = self.value_template.clone()?;
+        if self.uses_ipv4() {
+            value = value.replace(IPV4_PLACEHOLDER, &ipv4?.to_string());
+        }
+        if self.uses_ipv6() {
+            value = value.replace(IPV6_PLACEHOLDER, &ipv6?.to_string());
+        }
+
+        Some(value)
+    }
+
+    /// Checks that the value template of the record is compatible with its type, and returns the reason why it is not
+    /// otherwise.
+    fn check_template(&self) -> std::result::Result<(), String> {
+        match (self.record_type, self.value_template.as_deref()) {
+            (RecordType::A, None | Some(IPV4_PLACEHOLDER)) | (RecordType::Aaaa, None | Some(IPV6_PLACEHOLDER)) => Ok(()),
+            (RecordType::A | RecordType::Aaaa, Some(template)) => Err(format!(
+                "The template \"{}\" cannot be used by an {} record, which can only point to the address of its family.",
+                template, self.record_type
+            )),
+            (RecordType::Txt | RecordType::Cname, None) => {
+                Err(format!("The {} records require a value template.", self.record_type))
+            }
+            (RecordType::Txt | RecordType::Cname, Some(template)) if !self.uses_ipv4() && !self.uses_ipv6() => Err(format!(
+                "The template \"{}\" contains neither the {} nor the {} placeholder.",
+                template, IPV4_PLACEHOLDER, IPV6_PLACEHOLDER
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that the static addresses of the record belong to its family, and returns the reason why they do not otherwise.
+    fn check_static_values(&self) -> std::result::Result<(), String> {
+        let family_matches = |value: &IpAddr| match self.record_type {
+            RecordType::A => value.is_ipv4(),
+            RecordType::Aaaa => value.is_ipv6(),
+            RecordType::Txt | RecordType::Cname => false,
+        };
+
+        match self.static_values.iter().find(|v| !family_matches(v)) {
+            Some(value) => Err(format!("The static address {} cannot be held by an {} record.", value, self.record_type)),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the fully qualified domain name of the record (without the trailing dot).
+    pub fn fqdn(&self) -> String {
+        match self.subdomain.is_empty() {
+            true => self.domain.clone(),
+            false => format!("{}.{}", self.subdomain, self.domain),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct DNSProvider {
     id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    alias: Option<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    api_key: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    secret_api_key: String,
+    #[serde(default)]
+    records: Vec<RecordConfig>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    params: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_success: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    labels: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_error: Option<(String, String)>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    extra_headers: Vec<(String, String)>,
+    #[serde(default = "enabled_by_default", skip_serializing_if = "is_enabled")]
+    enabled: bool,
+}
+
+impl DNSProvider {
+    /// Returns `true` if the DNS provider is the one designated by the reference (see
+    /// [`split_reference`](wapi::api::cache::split_reference)).
+    fn is(&self, reference: &str) -> bool {
+        split_reference(reference) == (self.id.as_str(), self.alias.as_deref())
+    }
+
+    /// Returns the reference designating the DNS provider (see [`split_reference`](wapi::api::cache::split_reference)).
+    fn reference(&self) -> String {
+        match &self.alias {
+            Some(alias) => format!("{}{}{}", self.id, ALIAS_SEPARATOR, alias),
+            None => self.id.clone(),
+        }
+    }
+}
+
+/// The character separating the ID of a DNS provider from its alias in the references to the DNS providers of the cache (e.g.
+/// `desec/work`).
+pub const ALIAS_SEPARATOR: char = '/';
+
+/// Splits a reference to a DNS provider of the cache into the ID of the DNS provider and its alias (if any). The DNS providers
+/// of the cache are referred to by their ID, or by their ID and their alias separated by
+/// [`ALIAS_SEPARATOR`](wapi::api::cache::ALIAS_SEPARATOR) if they have one (e.g. `desec/personal` and `desec/work`), which
+/// lets several accounts of the same DNS provider be configured side by side. The methods of the cache taking the ID of a DNS
+/// provider accept both forms.
+pub fn split_reference(reference: &str) -> (&str, Option<&str>) {
+    match reference.split_once(ALIAS_SEPARATOR) {
+        Some((id, alias)) => (id, Some(alias)),
+        None => (reference, None),
+    }
+}
+
+/// Returns the default value of the `enabled` field of a DNS provider (which is missing from the older cache files).
+fn enabled_by_default() -> bool {
+    true
+}
+
+/// Returns `true` if the DNS provider is enabled, in which case the field is not written to the cache file.
+fn is_enabled(enabled: &bool) -> bool {
+    *enabled
+}
+
+/// The credentials of a DNS provider, as stored in the secrets file of the split storage mode.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Secrets {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     api_key: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     secret_api_key: String,
 }
 
+/// A read-only view of a DNS provider of the cache, in which the credentials are masked so that it can be displayed safely.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProviderView<'a> {
+    /// The ID of the DNS provider.
+    pub id: &'a str,
+    /// The alias distinguishing the DNS provider from the other accounts of the same DNS provider, if it has one.
+    pub alias: Option<&'a str>,
+    /// The masked `api_key` of the DNS provider (only its last 4 characters are shown, if it is long enough).
+    pub api_key: String,
+    /// The masked `secret_api_key` of the DNS provider (only its last 4 characters are shown, if it is long enough).
+    pub secret_api_key: String,
+    /// The number of records managed through the DNS provider.
+    pub record_count: usize,
+    /// The time at which a record was last updated successfully through the DNS provider, if any.
+    pub last_success: Option<&'a str>,
+    /// The labels of the DNS provider.
+    pub labels: &'a [String],
+    /// Whether the DNS provider is enabled (the update runs skip the disabled ones).
+    pub enabled: bool,
+}
+
+impl<'a> ProviderView<'a> {
+    fn new(provider: &'a DNSProvider) -> ProviderView<'a> {
+        ProviderView {
+            id: &provider.id,
+            alias: provider.alias.as_deref(),
+            api_key: mask(&provider.api_key),
+            secret_api_key: mask(&provider.secret_api_key),
+            record_count: provider.records.len(),
+            last_success: provider.last_success.as_deref(),
+            labels: &provider.labels,
+            enabled: provider.enabled,
+        }
+    }
+
+    /// Returns the reference designating the DNS provider in the methods of the cache (see
+    /// [`split_reference`](wapi::api::cache::split_reference)), which is its ID followed by its alias if it has one.
+    pub fn reference(&self) -> String {
+        match self.alias {
+            Some(alias) => format!("{}{}{}", self.id, ALIAS_SEPARATOR, alias),
+            None => self.id.to_string(),
+        }
+    }
+}
+
+/// Masks a secret, only keeping its last 4 characters when it has at least 12 of them (so that short secrets are not
+/// partially revealed).
+fn mask(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    match chars.len() {
+        0 => String::new(),
+        n if n >= 12 => format!("****{}", chars[n - 4..].iter().collect::<String>()),
+        _ => String::from("****"),
+    }
+}
+
+/// The policy applied when several records of a DNS provider target the same fully qualified domain name with the same type
+/// (but different settings).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// The conflicting records are kept as they are, and [`Cache::validate`](wapi::Cache) returns an error naming them.
+    #[default]
+    Error,
+    /// Only the first of the conflicting records is kept.
+    KeepFirst,
+    /// Only the last of the conflicting records is kept.
+    KeepLast,
+}
+
+/// The time zone of the timestamps stored in the cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampZone {
+    /// The local time of the machine.
+    #[default]
+    Local,
+    /// The coordinated universal time.
+    Utc,
+}
+
+/// The format of the timestamps stored in the cache.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampFormat {
+    /// The date and the time with milliseconds, without offset (e.g. `2025-01-01 12:00:00.000`).
+    #[default]
+    Plain,
+    /// The RFC 3339 format with milliseconds and offset (e.g. `2025-01-01T12:00:00.000+01:00`, or with a `Z` in UTC).
+    Rfc3339,
+    /// A custom [chrono format string](https://docs.rs/chrono/latest/chrono/format/strftime/index.html).
+    Custom(String),
+}
+
+/// The representation of the timestamps stored in the cache (the time of the last change of the cache, of the runs, and of
+/// the last success and error of the DNS providers).
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct TimestampConfig {
+    /// The time zone of the timestamps. Defaults to [`TimestampZone::Local`].
+    pub zone: TimestampZone,
+    /// The format of the timestamps. Defaults to [`TimestampFormat::Plain`].
+    pub format: TimestampFormat,
+}
+
+/// The format string of [`TimestampFormat::Plain`].
+const PLAIN_TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.3f";
+
+impl TimestampConfig {
+    /// Returns the current time formatted according to the configuration. The plain format is used if the custom format
+    /// string is invalid.
+    pub fn now(&self) -> String {
+        match self.zone {
+            TimestampZone::Local => self.format_time(Local::now()),
+            TimestampZone::Utc => self.format_time(Utc::now()),
+        }
+    }
+
+    /// Parses a timestamp of the cache, whichever configuration it was written with: the RFC 3339 format, the plain format, and
+    /// the custom format string of the configuration are accepted. The timestamps without offset are interpreted in the time
+    /// zone of the configuration. `None` is returned if the timestamp cannot be parsed.
+    pub fn parse(&self, timestamp: &str) -> Option<DateTime<Utc>> {
+        let timestamp = timestamp.trim();
+        if let Ok(time) = DateTime::parse_from_rfc3339(timestamp) {
+            return Some(time.with_timezone(&Utc));
+        }
+        let custom = match &self.format {
+            TimestampFormat::Custom(pattern) if is_valid_timestamp_format(pattern) => Some(pattern.as_str()),
+            _ => None,
+        };
+        if let Some(time) = custom.and_then(|pattern| DateTime::parse_from_str(timestamp, pattern).ok()) {
+            return Some(time.with_timezone(&Utc));
+        }
+
+        let time = NaiveDateTime::parse_from_str(timestamp, PLAIN_TIMESTAMP_FORMAT)
+            .ok()
+            .or_else(|| custom.and_then(|pattern| NaiveDateTime::parse_from_str(timestamp, pattern).ok()))?;
+        match self.zone {
+            TimestampZone::Local => time.and_local_timezone(Local).earliest().map(|t| t.with_timezone(&Utc)),
+            TimestampZone::Utc => Some(time.and_utc()),
+        }
+    }
+
+    /// Formats the time according to the format of the configuration.
+    fn format_time<Tz: TimeZone>(&self, time: DateTime<Tz>) -> String
+    where
+        Tz::Offset: std::fmt::Display,
+    {
+        match &self.format {
+            TimestampFormat::Rfc3339 => time.to_rfc3339_opts(SecondsFormat::Millis, true),
+            TimestampFormat::Custom(pattern) if is_valid_timestamp_format(pattern) => time.format(pattern).to_string(),
+            _ => time.format(PLAIN_TIMESTAMP_FORMAT).to_string(),
+        }
+    }
+}
+
+/// Overwrites the timestamp of the metadata at the start of the JSON cache file, without touching the rest of the file. `false`
+/// is returned (and nothing is written) if the file does not start with the previous timestamp, or if the new timestamp does
+/// not have the same length, since the file would have to be rewritten.
+fn patch_timestamp(cache_path: &Path, previous: &str, timestamp: &str) -> std::io::Result<bool> {
+    let (previous, timestamp) = (serde_json::to_string(previous)?, serde_json::to_string(timestamp)?);
+    if previous.len() != timestamp.len() {
+        return Ok(false);
+    }
+
+    // Finds the value of the first `timestamp` key, which belongs to the metadata since it is serialized first.
+    let mut file = std::fs::OpenOptions::new().read(true).write(true).open(cache_path)?;
+    let mut head = Vec::new();
+    (&mut file).take(METADATA_HEAD_SIZE).read_to_end(&mut head)?;
+    let offset = match head.windows(11).position(|window| window == b"\"timestamp\"") {
+        Some(key) => key + 11 + head[key + 11..].iter().take_while(|b| b.is_ascii_whitespace() || **b == b':').count(),
+        None => return Ok(false),
+    };
+    if !head[offset..].starts_with(previous.as_bytes()) {
+        return Ok(false);
+    }
+
+    file.seek(SeekFrom::Start(offset as u64))?;
+    file.write_all(timestamp.as_bytes())?;
+    Ok(true)
+}
+
+/// Returns `true` if the chrono format string is valid, since formatting a time with an invalid one panics.
+fn is_valid_timestamp_format(pattern: &str) -> bool {
+    !pattern.is_empty() && StrftimeItems::new(pattern).all(|item| !matches!(item, Item::Error))
+}
+
+/// The outcome of [`Cache::ensure_provider`](wapi::Cache).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpsertOutcome {
+    /// The DNS provider did not exist in the cache and was added.
+    Created,
+    /// The DNS provider already existed in the cache and its credentials were replaced.
+    Updated,
+    /// The DNS provider already existed in the cache with the same credentials, so nothing was changed.
+    Unchanged,
+}
+
+/// The struct summarizing the outcome of an update run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunRecord {
+    /// The time at which the run was recorded.
+    pub timestamp: String,
+    /// The number of records that were updated.
+    pub updated: usize,
+    /// The number of records that were already up to date.
+    pub unchanged: usize,
+    /// The number of records that could not be updated.
+    pub failed: usize,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct Data {
     ipv4_address: String,
     ipv6_address: String,
     dns_providers: Vec<DNSProvider>,
+    #[serde(default)]
+    run_history: Vec<RunRecord>,
+    #[serde(default)]
+    conflict_policy: ConflictPolicy,
+    #[serde(default)]
+    timestamps: TimestampConfig,
 }
 
 /// The struct used to manipulate the program's cache file.
@@ -42,6 +517,29 @@ pub struct Cache {
     metadata: Metadata,
     #[serde(rename = "DATA")]
     data: Data,
+    #[serde(skip)]
+    read_only: bool,
+    /// The text cache file the cache was last loaded from or written to, if it has not been changed since (see
+    /// [`Cache::touch_and_save`](wapi::Cache)).
+    #[serde(skip)]
+    synced: Option<PathBuf>,
+}
+
+/// The maximum number of runs kept in the run history of the cache.
+const MAX_RUN_HISTORY: usize = 100;
+
+/// The number of bytes read at the start of the cache file to find the timestamp of its metadata, which is serialized first.
+const METADATA_HEAD_SIZE: u64 = 4096;
+
+/// Parses the major, minor, and patch numbers of a version of the program (the pre-release and build suffixes are ignored).
+/// `None` is returned if the version is not made of three numbers.
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let core = version.trim().split(['-', '+']).next()?;
+    let mut numbers = core.split('.').map(|n| n.parse::<u64>().ok());
+    match (numbers.next()??, numbers.next()??, numbers.next()??, numbers.next()) {
+        (major, minor, patch, None) => Some((major, minor, patch)),
+        _ => None,
+    }
 }
 
 impl Default for Cache {
@@ -62,7 +560,16 @@ impl Cache {
                 homepage: String::new(),
                 timestamp: String::new(),
             },
-            data: Data { ipv4_address: String::new(), ipv6_address: String::new(), dns_providers: Vec::new() },
+            data: Data {
+                ipv4_address: String::new(),
+                ipv6_address: String::new(),
+                dns_providers: Vec::new(),
+                run_history: Vec::new(),
+                conflict_policy: ConflictPolicy::default(),
+                timestamps: TimestampConfig::default(),
+            },
+            read_only: false,
+            synced: None,
         };
 
         cache.fmt();
@@ -73,9 +580,16 @@ impl Cache {
     /// content). The is done by ensuring that the metadata is correct, the IP addresses are valid, and the DNS providers are in
     /// the correct format. If the IP addresses are not valid, they are replaced with default values (`0.0.0.0` and
     /// `0:0:0:0:0:0:0:0` for IPv4 and IPv6 respectively). If the ID of a DNS provider is not recognized, the DNS provider is
-    /// removed from the cache. And if the ID of a DNS provider appears more than once, only the most recent one is kept. For a
-    /// list of the supported DNS providers and their ID, see the [GitHub repository](https://github.com/AmonRayfa/wapi).
+    /// removed from the cache. And if a DNS provider appears more than once (with the same ID and alias), only the most recent
+    /// one is kept. The domains of the records are normalized (lowercased and stripped of their trailing dot), the records
+    /// without a domain are removed, and only the first occurrence of a duplicate record is kept. Records that target the same
+    /// fully qualified domain name with the same type but different settings are resolved according to the
+    /// [`ConflictPolicy`](wapi::api::cache::ConflictPolicy) of the cache. For a list of the supported DNS providers and their
+    /// ID, see the [GitHub repository](https://github.com/AmonRayfa/wapi).
     pub fn fmt(&mut self) {
+        // Forgets the cache file the cache was in sync with, since it is about to change.
+        self.synced = None;
+
         // Ensures the metadata is correct.
         self.metadata.warning = String::from("THIS FILE IS AUTO-GENERATED. DO NOT EDIT MANUALLY. IF THE FILE IS TAMPERED WITH, IT WILL BE OVERWRITTEN WITH DEFAULT DATA, AND ALL PREVIOUS DATA WILL BE LOST.");
         self.metadata.name = String::from("wapi-cache");
@@ -95,34 +609,62 @@ impl Cache {
             Err(_) => self.data.ipv6_address = String::from("0:0:0:0:0:0:0:0"),
         }
 
-        // Removes duplicate DNS providers and ensures that only the most recent one is kept.
+        // Removes the empty aliases of the DNS providers.
+        for p in self.data.dns_providers.iter_mut() {
+            p.alias = p.alias.take().map(|a| a.trim().to_string()).filter(|a| !a.is_empty());
+        }
+
+        // Removes duplicate DNS providers (sharing the same ID and alias) and ensures that only the most recent one is kept.
         let mut filtered_providers = HashSet::new();
         self.data.dns_providers.reverse();
-        self.data.dns_providers.retain(|p| match p.id.as_str() {
-            "alibabacloud" => filtered_providers.insert(p.id.clone()),
-            "bluehost" => filtered_providers.insert(p.id.clone()),
-            "cloudflare" => filtered_providers.insert(p.id.clone()),
-            "dnspod" => filtered_providers.insert(p.id.clone()),
-            "dreamhost" => filtered_providers.insert(p.id.clone()),
-            "dynadot" => filtered_providers.insert(p.id.clone()),
-            "enom" => filtered_providers.insert(p.id.clone()),
-            "epik" => filtered_providers.insert(p.id.clone()),
-            "gandi" => filtered_providers.insert(p.id.clone()),
-            "godaddy" => filtered_providers.insert(p.id.clone()),
-            "hover" => filtered_providers.insert(p.id.clone()),
-            "ionos" => filtered_providers.insert(p.id.clone()),
-            "namecheap" => filtered_providers.insert(p.id.clone()),
-            "namesilo" => filtered_providers.insert(p.id.clone()),
-            "opensrs" => filtered_providers.insert(p.id.clone()),
-            "ovh" => filtered_providers.insert(p.id.clone()),
-            "porkbun" => filtered_providers.insert(p.id.clone()),
-            "resellerclub" => filtered_providers.insert(p.id.clone()),
-            _ => false,
-        });
+        self.data
+            .dns_providers
+            .retain(|p| provider::is_known(&p.id) && filtered_providers.insert((p.id.clone(), p.alias.clone())));
         self.data.dns_providers.reverse();
 
+        // Normalizes the records of each DNS provider (which may span several domains) and removes invalid and duplicate ones.
+        for p in self.data.dns_providers.iter_mut() {
+            // Normalizes the labels of the DNS provider and removes empty and duplicate ones.
+            let mut filtered_labels = HashSet::new();
+            for label in p.labels.iter_mut() {
+                *label = label.trim().to_lowercase();
+            }
+            p.labels.retain(|l| !l.is_empty() && filtered_labels.insert(l.clone()));
+
+            // Removes the malformed extra headers of the DNS provider.
+            for (name, value) in p.extra_headers.iter_mut() {
+                *name = name.trim().to_string();
+                *value = value.trim().to_string();
+            }
+            p.extra_headers.retain(|(name, value)| is_valid_header_name(name) && is_valid_header_value(value));
+
+            let mut filtered_records = HashSet::new();
+            for record in p.records.iter_mut() {
+                record.domain = record.domain.trim().trim_end_matches('.').to_lowercase();
+                record.subdomain = record.subdomain.trim().trim_end_matches('.').to_lowercase();
+            }
+            p.records.retain(|r| !r.domain.is_empty() && filtered_records.insert(r.clone()));
+
+            // Resolves the records targeting the same name and type according to the conflict policy.
+            let mut filtered_targets = HashSet::new();
+            match self.data.conflict_policy {
+                ConflictPolicy::Error => {}
+                ConflictPolicy::KeepFirst => p.records.retain(|r| filtered_targets.insert((r.fqdn(), r.record_type))),
+                ConflictPolicy::KeepLast => {
+                    p.records.reverse();
+                    p.records.retain(|r| filtered_targets.insert((r.fqdn(), r.record_type)));
+                    p.records.reverse();
+                }
+            }
+        }
+
+        // Keeps only the most recent runs in the run history.
+        if self.data.run_history.len() > MAX_RUN_HISTORY {
+            self.data.run_history.drain(..self.data.run_history.len() - MAX_RUN_HISTORY);
+        }
+
         // Timestamps the cache.
-        self.metadata.timestamp = Local::now().format("%Y-%m-%d %H:%M:%S%.3f").to_string();
+        self.metadata.timestamp = self.data.timestamps.now();
     }
 
     /// Retrieves the cache file's path. A `None` value is returned if the user's home directory path cannot be retrieved from
@@ -135,93 +677,843 @@ impl Cache {
     /// instance. An error is returned if the cache file: does not exist, cannot be read to a string, or is corrupted and cannot
     /// be deserialized.
     pub fn load() -> Result<Cache> {
+        Cache::load_with(&JsonFormat::default())
+    }
+
+    /// Loads the cache file like [`Cache::load`](wapi::Cache), but deserializes it with the given
+    /// [`CacheFormat`](wapi::api::format::CacheFormat) instead of JSON.
+    pub fn load_with(format: &dyn CacheFormat) -> Result<Cache> {
         // Retrieves the cache file's path and returns an error if it fails.
         let cache_path = match Cache::get_path() {
             Some(p) => p,
             None => {
-                return Err(Error::Cache(
-                    String::from("locate"),
-                    String::from("No valid user home directory path could be retrieved from the operating system."),
-                ))
+                return Err(Error::CacheLocate {
+                    reason: "No valid user home directory path could be retrieved from the operating system.".into(),
+                })
             }
         };
 
-        // Reads the cache file to a string and returns an error if it fails.
-        let cache_file =
-            std::fs::read_to_string(&cache_path).map_err(|err| Error::Cache(String::from("load"), err.to_string()))?;
+        Cache::load_from(&cache_path, format)
+    }
+
+    /// Loads the cache file at the given path like [`Cache::load_with`](wapi::Cache), instead of the one whose location
+    /// depends on the operating system.
+    pub fn load_from(cache_path: &Path, format: &dyn CacheFormat) -> Result<Cache> {
+        // Reads the cache file and returns an error if it fails.
+        let cache_file = std::fs::read(cache_path).map_err(|err| Error::cache_read(cache_path, err))?;
 
         // Deserializes the cache file and returns an error if it fails.
-        let cache = match serde_json::from_str(&cache_file) {
-            Ok(c) => c,
-            Err(e) => return Err(Error::Cache(String::from("load"), e.to_string())),
-        };
+        let mut cache = format.deserialize(&cache_file).map_err(|err| err.with_path(cache_path))?;
+        if !format.is_binary() {
+            cache.synced = Some(cache_path.to_path_buf());
+        }
+
+        // Warns if the cache file was written by a newer version of the program, whose changes may not be understood.
+        if let (Some(version), Some(current)) = (parse_version(cache.version()), parse_version(env!("CARGO_PKG_VERSION"))) {
+            if version > current {
+                tracing::warn!(
+                    path = %cache_path.display(),
+                    version = %cache.version(),
+                    "The cache file was written by a newer version of the program."
+                );
+            }
+        }
 
         Ok(cache)
     }
 
     /// Saves the [`Cache`](wapi::Cache) instance to a JSON file (the location of the file depends on the operating system). An
     /// error is returned if the cache file's path is invalid, or if the [`Cache`](wapi::Cache) instance cannot be serialized.
-    /// If a cache file already exists, it is overwritten with the new cache.
+    /// If a cache file already exists, it is overwritten with the new cache. If the cache is in read-only mode, nothing is
+    /// written and an error is returned instead.
     pub fn save(&mut self) -> Result<()> {
+        self.save_with(&JsonFormat::default())
+    }
+
+    /// Updates the timestamp of the [`Cache`](wapi::Cache) instance and persists it, without formatting it (see
+    /// [`Cache::fmt`](wapi::Cache)). It is meant for the update runs that changed nothing (e.g. because the IP addresses are
+    /// unchanged), which only need to record that the cache was checked. If the cache has not been changed since it was loaded
+    /// from or written to the cache file, only the timestamp of the metadata is overwritten in place, without serializing the
+    /// cache nor rewriting the rest of the file. Otherwise (or if the new timestamp does not have the length of the previous
+    /// one), the cache is saved with [`Cache::save`](wapi::Cache). The same errors as [`Cache::save`](wapi::Cache) can be
+    /// returned.
+    pub fn touch_and_save(&mut self) -> Result<()> {
+        let cache_path = match Cache::get_path() {
+            Some(p) => p,
+            None => {
+                return Err(Error::CacheLocate {
+                    reason: "No valid user home directory path could be retrieved from the operating system.".into(),
+                })
+            }
+        };
+
+        self.touch_and_save_to(&cache_path)
+    }
+
+    /// Updates the timestamp of the [`Cache`](wapi::Cache) instance and persists it like
+    /// [`Cache::touch_and_save`](wapi::Cache), but to the JSON cache file at the given path instead of the one whose location
+    /// depends on the operating system.
+    pub fn touch_and_save_to(&mut self, cache_path: &Path) -> Result<()> {
+        self.ensure_writable(cache_path)?;
+        let previous = std::mem::replace(&mut self.metadata.timestamp, self.data.timestamps.now());
+
+        // Overwrites the timestamp in place if the cache file holds the rest of the cache, or saves the whole cache otherwise.
+        if self.synced.as_deref() == Some(cache_path) {
+            match patch_timestamp(cache_path, &previous, &self.metadata.timestamp) {
+                Ok(true) => return Ok(()),
+                Ok(false) => {}
+                Err(err) => return Err(Error::cache_write(cache_path, err)),
+            }
+        }
+        self.save_to(cache_path, &JsonFormat::default())
+    }
+
+    /// Saves the [`Cache`](wapi::Cache) instance like [`Cache::save`](wapi::Cache), but serializes it with the given
+    /// [`CacheFormat`](wapi::api::format::CacheFormat) instead of JSON.
+    pub fn save_with(&mut self, format: &dyn CacheFormat) -> Result<()> {
+        self.write(format).map(|_| ())
+    }
+
+    /// Saves the [`Cache`](wapi::Cache) instance like [`Cache::save_with`](wapi::Cache), but to the cache file at the given
+    /// path instead of the one whose location depends on the operating system.
+    pub fn save_to(&mut self, cache_path: &Path, format: &dyn CacheFormat) -> Result<()> {
+        self.write_to(cache_path, format).map(|_| ())
+    }
+
+    /// Compacts the [`Cache`](wapi::Cache) instance and rewrites the cache file without indentation. The cache is formatted
+    /// first (see [`Cache::fmt`](wapi::Cache)), which trims the run history to its limit and removes the invalid and
+    /// duplicate entries. The number of bytes reclaimed in the cache file is returned (it is negative if the file grew, e.g.
+    /// if it did not exist). The same errors as [`Cache::save`](wapi::Cache) can be returned. The next call to
+    /// [`Cache::save`](wapi::Cache) writes the cache file with indentation again.
+    pub fn compact(&mut self) -> Result<i64> {
+        self.fmt();
+        let previous_size = Cache::get_path().and_then(|p| std::fs::metadata(p).ok()).map_or(0, |m| m.len());
+        let size = self.write(&JsonFormat { pretty: false })?;
+        Ok(i64::try_from(previous_size).unwrap_or(i64::MAX).saturating_sub(i64::try_from(size).unwrap_or(i64::MAX)))
+    }
+
+    /// Upgrades the cache file at the given path to the current version of the program. The cache file is loaded (which
+    /// migrates its content, e.g. by giving the fields added since it was written their default value), and if it was written
+    /// by an older version of the program (or if its version is invalid), it is formatted (see [`Cache::fmt`](wapi::Cache))
+    /// and written back, so that the other tools reading it see the current schema. Returns `true` if the cache file was
+    /// rewritten, and `false` if it was left untouched because it is already current or was written by a newer version, so
+    /// that running it again is a no-op. The same errors as [`Cache::load`](wapi::Cache) and [`Cache::save`](wapi::Cache)
+    /// can be returned.
+    pub fn upgrade_in_place(path: &Path) -> Result<bool> {
+        let cache_file = std::fs::read(path).map_err(|err| Error::cache_read(path, err))?;
+        match Cache::upgrade(&cache_file).map_err(|err| err.with_path(path))? {
+            Some(cache_file) => {
+                std::fs::write(path, cache_file).map_err(|err| Error::cache_write(path, err))?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Upgrades the content of a cache file like [`Cache::upgrade_in_place`](wapi::Cache), and returns the upgraded content,
+    /// or `None` if the content is already current.
+    pub fn upgrade(cache_file: &[u8]) -> Result<Option<Vec<u8>>> {
+        let format = JsonFormat::default();
+        let mut cache = format.deserialize(cache_file)?;
+
+        // Leaves the content untouched unless it was written by an older version of the program.
+        match (parse_version(&cache.metadata.version), parse_version(env!("CARGO_PKG_VERSION"))) {
+            (Some(version), Some(current)) if version >= current => return Ok(None),
+            _ => {}
+        }
+
+        cache.fmt();
+        format.serialize(&cache).map(Some)
+    }
+
+    /// Returns the version of the program that wrote the [`Cache`](wapi::Cache) instance, which is the version of its schema.
+    /// It is the current version of the program for the caches created or formatted by it.
+    pub fn version(&self) -> &str {
+        &self.metadata.version
+    }
+
+    /// Returns `true` if the [`Cache`](wapi::Cache) instance can be used by the given version of the program. The schema of
+    /// the cache only changes in a breaking way with the major version (or with the minor version before 1.0.0, following
+    /// the semantic versioning), so the versions are compatible if they share it. `false` is returned if either version is
+    /// invalid.
+    pub fn is_compatible_with(&self, current: &str) -> bool {
+        match (parse_version(self.version()), parse_version(current)) {
+            (Some((0, minor, _)), Some((0, current_minor, _))) => minor == current_minor,
+            (Some((major, _, _)), Some((current_major, _, _))) => major == current_major,
+            _ => false,
+        }
+    }
+
+    /// Writes the [`Cache`](wapi::Cache) instance to the cache file in the given format, and returns the size of the written
+    /// file in bytes.
+    fn write(&mut self, format: &dyn CacheFormat) -> Result<u64> {
         // Retrieves the cache file's path and returns an error if it fails.
         let cache_path = match Cache::get_path() {
             Some(p) => p,
             None => {
-                return Err(Error::Cache(
-                    String::from("locate"),
-                    String::from("No valid user home directory path could be retrieved from the operating system."),
-                ))
+                return Err(Error::CacheLocate {
+                    reason: "No valid user home directory path could be retrieved from the operating system.".into(),
+                })
             }
         };
 
+        self.write_to(&cache_path, format)
+    }
+
+    /// Writes the [`Cache`](wapi::Cache) instance to the cache file at the given path in the given format, and returns the
+    /// size of the written file in bytes. The write is traced in a `cache.save` span, which records the size of the cache file,
+    /// the duration and the outcome.
+    fn write_to(&mut self, cache_path: &Path, format: &dyn CacheFormat) -> Result<u64> {
+        let span = tracing::info_span!(
+            "cache.save",
+            path = %cache_path.display(),
+            bytes = tracing::field::Empty,
+            duration_ms = tracing::field::Empty,
+            outcome = tracing::field::Empty,
+        )
+        .entered();
+        let start = Instant::now();
+        let result = self.write_file(cache_path, format);
+        self.synced = match (&result, format.is_binary()) {
+            (Ok(_), false) => Some(cache_path.to_path_buf()),
+            _ => None,
+        };
+
+        span.record("duration_ms", u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX));
+        match &result {
+            Ok(size) => span.record("bytes", *size).record("outcome", "saved"),
+            Err(_) => span.record("outcome", "failed"),
+        };
+        result
+    }
+
+    /// Runs the write of [`Cache::write_to`](wapi::Cache), without tracing it.
+    fn write_file(&self, cache_path: &Path, format: &dyn CacheFormat) -> Result<u64> {
+        // Refuses to write the cache file if the cache is in read-only mode.
+        self.ensure_writable(cache_path)?;
+
         // Ensures that the parent directories of the cache file exist, and creates them if they don't.
         if let Some(parent_dir) = cache_path.parent() {
-            std::fs::create_dir_all(parent_dir).map_err(|err| Error::Cache(String::from("locate"), err.to_string()))?;
+            std::fs::create_dir_all(parent_dir).map_err(|err| Error::cache_write(parent_dir, err))?;
         } else {
-            return Err(Error::Cache(
-                String::from("locate"),
-                String::from("No valid parent directory path could be retrieved from the cache file path."),
-            ));
+            return Err(Error::CacheLocate {
+                reason: "No valid parent directory path could be retrieved from the cache file path.".into(),
+            });
+        }
+
+        // Serializes the cache instance (without the warning if the format is binary) and returns an error if it fails.
+        let cache = match format.is_binary() {
+            true => {
+                let mut cache = self.clone();
+                cache.metadata.warning.clear();
+                format.serialize(&cache)?
+            }
+            false => format.serialize(self)?,
+        };
+        std::fs::write(cache_path, &cache).map_err(|err| Error::cache_write(cache_path, err))?;
+
+        Ok(cache.len() as u64)
+    }
+
+    /// Retrieves the paths of the configuration file and the secrets file used by the split storage mode (see
+    /// [`Cache::load_split`](wapi::Cache)), which are stored next to the cache file. A `None` value is returned if the user's
+    /// home directory path cannot be retrieved from the operating system.
+    pub fn get_split_paths() -> Option<(PathBuf, PathBuf)> {
+        BaseDirs::new().map(|base_dirs| {
+            let dir = base_dirs.home_dir().join(Path::new(".wapi"));
+            (dir.join(Path::new("config.json")), dir.join(Path::new("secrets.json")))
+        })
+    }
+
+    /// Loads the cache from the configuration file and the secrets file of the split storage mode (see
+    /// [`Cache::get_split_paths`](wapi::Cache)). In this mode, the configuration file holds everything but the credentials of
+    /// the DNS providers (so it can be committed to a version control system), and the secrets file only holds the credentials,
+    /// keyed by DNS provider ID. If the secrets file does not exist, the DNS providers are loaded without their credentials
+    /// (see [`Cache::incomplete_providers`](wapi::Cache)). The same errors as [`Cache::load`](wapi::Cache) can be returned.
+    pub fn load_split() -> Result<Cache> {
+        match Cache::get_split_paths() {
+            Some((config_path, secrets_path)) => Cache::load_split_from(&config_path, &secrets_path),
+            None => Err(Error::CacheLocate {
+                reason: "No valid user home directory path could be retrieved from the operating system.".into(),
+            }),
+        }
+    }
+
+    /// Loads the cache like [`Cache::load_split`](wapi::Cache), but from the given configuration and secrets files.
+    pub fn load_split_from(config_path: &Path, secrets_path: &Path) -> Result<Cache> {
+        // Reads and deserializes the configuration file, and returns an error if it fails.
+        let config_file = std::fs::read(config_path).map_err(|err| Error::cache_read(config_path, err))?;
+        let mut cache = JsonFormat::default().deserialize(&config_file).map_err(|err| err.with_path(config_path))?;
+
+        // Reads and deserializes the secrets file (if it exists), and returns an error if it fails.
+        let secrets: BTreeMap<String, Secrets> = match std::fs::read(secrets_path) {
+            Ok(secrets_file) => serde_json::from_slice(&secrets_file)
+                .map_err(|source| Error::CacheParse { path: secrets_path.to_path_buf(), source })
+                .with_context(|| "Could not load the credentials of the DNS providers.")?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => {
+                return Err(Error::cache_read(secrets_path, err).context("Could not load the credentials of the DNS providers."))
+            }
+        };
+
+        // Merges the credentials into the DNS providers.
+        for p in cache.data.dns_providers.iter_mut() {
+            if let Some(secrets) = secrets.get(&p.reference()) {
+                p.api_key = secrets.api_key.clone();
+                p.secret_api_key = secrets.secret_api_key.clone();
+            }
+        }
+
+        Ok(cache)
+    }
+
+    /// Saves the [`Cache`](wapi::Cache) instance to the configuration file and the secrets file of the split storage mode (see
+    /// [`Cache::load_split`](wapi::Cache)). Both files are overwritten if they already exist, and on Unix systems the secrets
+    /// file can only be read and written by its owner. The same errors as [`Cache::save`](wapi::Cache) can be returned.
+    pub fn save_split(&mut self) -> Result<()> {
+        match Cache::get_split_paths() {
+            Some((config_path, secrets_path)) => self.save_split_to(&config_path, &secrets_path),
+            None => Err(Error::CacheLocate {
+                reason: "No valid user home directory path could be retrieved from the operating system.".into(),
+            }),
+        }
+    }
+
+    /// Saves the [`Cache`](wapi::Cache) instance like [`Cache::save_split`](wapi::Cache), but to the given configuration and
+    /// secrets files.
+    pub fn save_split_to(&mut self, config_path: &Path, secrets_path: &Path) -> Result<()> {
+        // Refuses to write the files if the cache is in read-only mode.
+        self.ensure_writable(config_path)?;
+
+        // Moves the credentials of the DNS providers out of the configuration.
+        let mut config = self.clone();
+        let mut secrets = BTreeMap::new();
+        for p in config.data.dns_providers.iter_mut() {
+            let provider_secrets =
+                Secrets { api_key: std::mem::take(&mut p.api_key), secret_api_key: std::mem::take(&mut p.secret_api_key) };
+            if !provider_secrets.api_key.is_empty() || !provider_secrets.secret_api_key.is_empty() {
+                secrets.insert(p.reference(), provider_secrets);
+            }
+        }
+
+        // Serializes both files and returns an error if it fails.
+        let config_file = JsonFormat::default().serialize(&config)?;
+        let secrets_file = serde_json::to_vec_pretty(&secrets).map_err(|source| Error::CacheSerialize { source })?;
+
+        // Writes both files, creating their parent directories if needed, and returns an error if it fails.
+        for (path, content) in [(config_path, &config_file), (secrets_path, &secrets_file)] {
+            if let Some(parent_dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                std::fs::create_dir_all(parent_dir).map_err(|err| Error::cache_write(parent_dir, err))?;
+            }
+            std::fs::write(path, content).map_err(|err| Error::cache_write(path, err))?;
         }
 
-        // Serializes the cache instance and returns an error if it fails.
-        let cache = serde_json::to_string_pretty(self).map_err(|err| Error::Cache(String::from("save"), err.to_string()))?;
-        std::fs::write(cache_path, cache).map_err(|err| Error::Cache(String::from("save"), err.to_string()))?;
+        // Restricts the access to the secrets file to its owner.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(secrets_path, std::fs::Permissions::from_mode(0o600))
+                .map_err(|err| Error::cache_write(secrets_path, err))
+                .with_context(|| "Could not restrict the access to the credentials of the DNS providers.")?;
+        }
 
         Ok(())
     }
 
-    /// Adds a DNS provider to the cache. If the DNS provider already exists in the cache, it is replaced with the new one.
+    /// Returns the references (see [`split_reference`](wapi::api::cache::split_reference)) of the DNS providers whose required
+    /// credentials (see
+    /// [`required_credentials`](wapi::api::provider::required_credentials)) are not all set, e.g. because they were loaded
+    /// without a secrets file. These DNS providers cannot be used until their credentials are set.
+    pub fn incomplete_providers(&self) -> Vec<String> {
+        self.data
+            .dns_providers
+            .iter()
+            .filter(|p| {
+                provider::required_credentials(&p.id).iter().any(|credential| match *credential {
+                    "api_key" => p.api_key.trim().is_empty(),
+                    _ => p.secret_api_key.trim().is_empty(),
+                })
+            })
+            .map(DNSProvider::reference)
+            .collect()
+    }
+
+    /// Enables or disables the read-only mode of the [`Cache`](wapi::Cache) instance. In read-only mode, the cache can still be
+    /// modified in memory (so an update run can still be carried out), but [`Cache::save`](wapi::Cache) refuses to write it to
+    /// the cache file. This is meant for deployments where the cache file is managed by another tool (e.g. Ansible). The mode
+    /// itself is never written to the cache file, and is disabled by default.
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    /// Returns `true` if the [`Cache`](wapi::Cache) instance is in read-only mode.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Returns an error naming the path the cache was about to be written to if the [`Cache`](wapi::Cache) instance is in
+    /// read-only mode, so that every place writing the cache refuses it the same way.
+    pub(crate) fn ensure_writable(&self, path: &Path) -> Result<()> {
+        match self.read_only {
+            true => Err(Error::CachePermission {
+                path: path.to_path_buf(),
+                reason: "The cache is in read-only mode, so its changes cannot be written.".into(),
+            }),
+            false => Ok(()),
+        }
+    }
+
+    /// Adds a DNS provider to the cache. If the DNS provider already exists in the cache, it is replaced with the new one, but
+    /// the records it manages, its parameters, its labels, and its extra headers are kept. The DNS provider is given an alias
+    /// if the ID is followed by one (see [`split_reference`](wapi::api::cache::split_reference)), so that another account of
+    /// the same DNS provider can be added next to it.
     pub fn add_dns_provider(&mut self, id: String, api_key: String, secret_api_key: String) {
         self.fmt();
-        self.data.dns_providers.push(DNSProvider { id, api_key, secret_api_key });
+        let (provider_id, alias) = split_reference(&id);
+        let (provider_id, alias) = (provider_id.to_string(), alias.map(String::from));
+        let provider = match self.data.dns_providers.iter().find(|p| p.is(&id)) {
+            Some(p) => DNSProvider { api_key, secret_api_key, ..p.clone() },
+            None => DNSProvider {
+                id: provider_id,
+                alias,
+                api_key,
+                secret_api_key,
+                records: Vec::new(),
+                params: BTreeMap::new(),
+                last_success: None,
+                labels: Vec::new(),
+                last_error: None,
+                extra_headers: Vec::new(),
+                enabled: true,
+            },
+        };
+        self.data.dns_providers.push(provider);
         self.fmt();
     }
 
+    /// Adds a DNS provider to the cache, or replaces its credentials if it already exists (keeping its records, parameters,
+    /// and labels), and tells which of the two happened. Nothing is changed if the DNS provider already exists with the same
+    /// credentials, so the caller can skip saving the cache. An error is returned if the DNS provider is not supported.
+    pub fn ensure_provider(&mut self, id: &str, api_key: &str, secret_api_key: &str) -> Result<UpsertOutcome> {
+        if !provider::is_supported(split_reference(id).0) {
+            return Err(Error::UnsupportedProvider { id: id.to_string() });
+        }
+
+        let outcome = match self.provider_credentials(id) {
+            None => UpsertOutcome::Created,
+            Some(credentials) if credentials == (api_key, secret_api_key) => return Ok(UpsertOutcome::Unchanged),
+            Some(_) => UpsertOutcome::Updated,
+        };
+        self.add_dns_provider(id.to_string(), api_key.to_string(), secret_api_key.to_string());
+
+        Ok(outcome)
+    }
+
     /// Removes a DNS provider from the cache. If the DNS provider does not exist in the cache, nothing happens.
     pub fn remove_dns_provider(&mut self, id: String) {
         self.fmt();
-        self.data.dns_providers.retain(|provider| provider.id != id);
+        self.data.dns_providers.retain(|provider| !provider.is(&id));
         self.fmt();
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
+    /// Moves a DNS provider of the cache to another (supported) ID, keeping its credentials, records, and settings. If a DNS
+    /// provider already exists with the new ID, the two are merged: the records of both are kept (the existing ones first),
+    /// and the credentials and settings of the renamed DNS provider take precedence. An error is returned if the new ID is not
+    /// supported, if no DNS provider exists with the old ID, or if the merge creates conflicting records while the conflict
+    /// policy is [`ConflictPolicy::Error`](wapi::api::cache::ConflictPolicy) (in which case nothing is changed). The alias of
+    /// the DNS provider is replaced with the one following the new ID, if any (see
+    /// [`split_reference`](wapi::api::cache::split_reference)), so this also gives, changes, or removes an alias.
+    pub fn rename_provider_id(&mut self, from: &str, to: &str) -> Result<()> {
+        if !provider::is_supported(split_reference(to).0) {
+            return Err(Error::UnsupportedProvider { id: to.to_string() });
+        }
 
-    #[test]
-    fn test_cache() {
-        let mut cache = Cache::new();
-        assert_eq!(cache.metadata.name, "wapi-cache");
-        assert_eq!(cache.metadata.version, env!("CARGO_PKG_VERSION"));
-        assert_eq!(cache.metadata.description, "The cache file for the Wapi client.");
-        assert_eq!(cache.metadata.homepage, "https://github.com/AmonRayfa/wapi");
-        assert_eq!(cache.data.ipv4_address, "0.0.0.0");
-        assert_eq!(cache.data.ipv6_address, "0:0:0:0:0:0:0:0");
-        assert_eq!(cache.data.dns_providers.len(), 0);
+        self.fmt();
+        let index = match self.data.dns_providers.iter().position(|p| p.is(from)) {
+            Some(i) => i,
+            None => return Err(Error::UnknownProvider { id: from.to_string() }),
+        };
+        if from == to {
+            return Ok(());
+        }
 
-        cache.add_dns_provider("cloudflare".to_string(), "SOME_API_KEY_1".to_string(), "SOME_SECRET_API_KEY_1".to_string());
+        // Renames the DNS provider and merges the DNS provider that already has the new ID into it.
+        let previous_providers = self.data.dns_providers.clone();
+        let mut renamed = self.data.dns_providers.remove(index);
+        let (id, alias) = split_reference(to);
+        (renamed.id, renamed.alias) = (id.to_string(), alias.map(String::from));
+        if let Some(i) = self.data.dns_providers.iter().position(|p| p.is(to)) {
+            let existing = self.data.dns_providers.remove(i);
+            renamed.records = [existing.records, renamed.records].concat();
+            renamed.params = existing.params.into_iter().chain(renamed.params).collect();
+            renamed.labels = [existing.labels, renamed.labels].concat();
+            renamed.extra_headers = [existing.extra_headers, renamed.extra_headers].concat();
+        }
+        self.data.dns_providers.push(renamed);
+        self.fmt();
+
+        // Restores the previous DNS providers if the merge created conflicting records.
+        if let Err(e) = self.validate() {
+            self.data.dns_providers = previous_providers;
+            self.fmt();
+            return Err(e.context(format!("Could not rename the DNS provider \"{}\" to \"{}\".", from, to)));
+        }
+
+        Ok(())
+    }
+
+    /// Adds a record to a DNS provider of the cache. The records of a DNS provider do not need to share the same domain. If the
+    /// record already exists for the DNS provider, nothing happens. An error is returned if the DNS provider does not exist in
+    /// the cache, if the value template or the static addresses of the record are incompatible with its type, or if the record
+    /// conflicts with another record of the DNS provider while the conflict policy is
+    /// [`ConflictPolicy::Error`](wapi::api::cache::ConflictPolicy) (in which case the record is not added).
+    pub fn add_record(&mut self, provider_id: &str, record: RecordConfig) -> Result<()> {
+        self.fmt();
+        let previous_records = match self.data.dns_providers.iter_mut().find(|p| p.is(provider_id)) {
+            Some(p) => {
+                let previous_records = p.records.clone();
+                p.records.push(record);
+                previous_records
+            }
+            None => return Err(Error::UnknownProvider { id: provider_id.to_string() }),
+        };
+        self.fmt();
+
+        // Restores the previous records if the new one conflicts with them.
+        if let Err(e) = self.validate() {
+            if let Some(p) = self.data.dns_providers.iter_mut().find(|p| p.is(provider_id)) {
+                p.records = previous_records;
+            }
+            self.fmt();
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    /// Checks that no DNS provider of the cache has several records targeting the same fully qualified domain name with the
+    /// same type. Such conflicts can only remain in the cache while the conflict policy is
+    /// [`ConflictPolicy::Error`](wapi::api::cache::ConflictPolicy). It also checks that the value template of every record is
+    /// compatible with its type (see [`RecordConfig::value_template`](wapi::RecordConfig)), and that its static addresses
+    /// belong to its family (see [`RecordConfig::static_values`](wapi::RecordConfig)). An error naming the first conflicting or
+    /// invalid record and its DNS provider is returned if one is found.
+    pub fn validate(&self) -> Result<()> {
+        for p in self.data.dns_providers.iter() {
+            let mut filtered_targets = HashSet::new();
+            for record in p.records.iter() {
+                if !filtered_targets.insert((record.fqdn(), record.record_type)) {
+                    return Err(Error::CacheInvalid {
+                        reason: format!(
+                            "The DNS provider \"{}\" has conflicting {} records for \"{}\".",
+                            p.reference(),
+                            record.record_type,
+                            record.fqdn()
+                        )
+                        .into(),
+                    });
+                }
+                if let Err(reason) = record.check_template().and_then(|_| record.check_static_values()) {
+                    return Err(Error::CacheInvalid {
+                        reason: format!(
+                            "The {} record \"{}\" of the DNS provider \"{}\" is invalid: {}",
+                            record.record_type,
+                            record.fqdn(),
+                            p.reference(),
+                            reason
+                        )
+                        .into(),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the policy applied to the records that target the same fully qualified domain name with the same type.
+    pub fn conflict_policy(&self) -> ConflictPolicy {
+        self.data.conflict_policy
+    }
+
+    /// Sets the policy applied to the records that target the same fully qualified domain name with the same type, and
+    /// resolves the existing conflicts accordingly.
+    pub fn set_conflict_policy(&mut self, policy: ConflictPolicy) {
+        self.data.conflict_policy = policy;
+        self.fmt();
+    }
+
+    /// Returns the representation of the timestamps stored in the cache.
+    pub fn timestamp_config(&self) -> &TimestampConfig {
+        &self.data.timestamps
+    }
+
+    /// Sets the representation of the timestamps stored in the cache, which applies to the timestamps written from now on
+    /// (the existing ones are kept, and can still be parsed with [`TimestampConfig::parse`]). An error is returned (and
+    /// nothing is changed) if the custom format string is invalid.
+    pub fn set_timestamp_config(&mut self, config: TimestampConfig) -> Result<()> {
+        if let TimestampFormat::Custom(pattern) = &config.format {
+            if !is_valid_timestamp_format(pattern) {
+                return Err(Error::CacheInvalid {
+                    reason: format!("The timestamp format \"{}\" is not a valid chrono format string.", pattern).into(),
+                });
+            }
+        }
+        self.data.timestamps = config;
+        self.fmt();
+        Ok(())
+    }
+
+    /// Removes every record of a DNS provider matching the fully qualified domain name and type. If the DNS provider or the
+    /// record does not exist in the cache, nothing happens.
+    pub fn remove_record(&mut self, provider_id: &str, fqdn: &str, record_type: RecordType) {
+        self.fmt();
+        let fqdn = fqdn.trim().trim_end_matches('.').to_lowercase();
+        if let Some(p) = self.data.dns_providers.iter_mut().find(|p| p.is(provider_id)) {
+            p.records.retain(|r| r.fqdn() != fqdn || r.record_type != record_type);
+        }
+        self.fmt();
+    }
+
+    /// Returns the credentials (`api_key` and `secret_api_key`) of a DNS provider as borrowed strings, or `None` if the DNS
+    /// provider does not exist in the cache. This gives the provider implementations access to the credentials they need to
+    /// sign their requests, without exposing them outside of the crate or cloning them.
+    pub(crate) fn provider_credentials(&self, id: &str) -> Option<(&str, &str)> {
+        self.data.dns_providers.iter().find(|p| p.is(id)).map(|p| (p.api_key.as_str(), p.secret_api_key.as_str()))
+    }
+
+    /// Returns the value of an extra parameter of a DNS provider (e.g. the update URL of the `dyndns2` provider), or `None` if
+    /// the parameter is not set or if the DNS provider does not exist in the cache.
+    pub fn provider_param(&self, id: &str, key: &str) -> Option<&str> {
+        self.data.dns_providers.iter().find(|p| p.is(id))?.params.get(key).map(|v| v.as_str())
+    }
+
+    /// Sets the value of an extra parameter of a DNS provider, or removes it if the value is `None`. If the DNS provider does
+    /// not exist in the cache, nothing happens.
+    pub fn set_provider_param(&mut self, id: &str, key: &str, value: Option<String>) {
+        self.fmt();
+        if let Some(p) = self.data.dns_providers.iter_mut().find(|p| p.is(id)) {
+            match value {
+                Some(v) => p.params.insert(key.to_string(), v),
+                None => p.params.remove(key),
+            };
+        }
+        self.fmt();
+    }
+
+    /// Returns the zone ID cached for a domain of a DNS provider, or `None` if the zone has not been resolved yet (or if the
+    /// DNS provider or the domain does not exist in the cache).
+    pub fn zone_id(&self, provider_id: &str, domain: &str) -> Option<&str> {
+        let p = self.data.dns_providers.iter().find(|p| p.is(provider_id))?;
+        p.records.iter().filter(|r| r.domain == domain).find_map(|r| r.zone_id.as_deref())
+    }
+
+    /// Caches the zone ID of a domain on every record of a DNS provider belonging to that domain. Passing `None` clears the
+    /// cached zone ID, which should be done when a DNS provider reports it as invalid (e.g. with a 404 status) so that the zone
+    /// is resolved again on the next update. If the DNS provider does not exist in the cache, nothing happens.
+    pub fn set_zone_id(&mut self, provider_id: &str, domain: &str, zone_id: Option<String>) {
+        self.fmt();
+        if let Some(p) = self.data.dns_providers.iter_mut().find(|p| p.is(provider_id)) {
+            for record in p.records.iter_mut().filter(|r| r.domain == domain) {
+                record.zone_id = zone_id.clone();
+            }
+        }
+        self.fmt();
+    }
+
+    /// Stores the IPv4 address that the records of the cache now point to.
+    pub fn set_ipv4(&mut self, address: Ipv4Addr) {
+        self.fmt();
+        self.data.ipv4_address = address.to_string();
+        self.fmt();
+    }
+
+    /// Stores the IPv6 address that the records of the cache now point to.
+    pub fn set_ipv6(&mut self, address: Ipv6Addr) {
+        self.fmt();
+        self.data.ipv6_address = address.to_string();
+        self.fmt();
+    }
+
+    /// Returns the IPv4 address that the records of the cache point to, or `None` if it is unknown (i.e. if it was never set,
+    /// in which case the cache holds the unspecified address `0.0.0.0`).
+    pub fn ipv4(&self) -> Option<Ipv4Addr> {
+        self.data.ipv4_address.parse::<Ipv4Addr>().ok().filter(|a| !a.is_unspecified())
+    }
+
+    /// Returns the IPv6 address that the records of the cache point to, or `None` if it is unknown (i.e. if it was never set,
+    /// in which case the cache holds the unspecified address `::`).
+    pub fn ipv6(&self) -> Option<Ipv6Addr> {
+        self.data.ipv6_address.parse::<Ipv6Addr>().ok().filter(|a| !a.is_unspecified())
+    }
+
+    /// Returns `true` if the IPv4 address differs from the one stored in the cache (the records must then be updated).
+    pub fn ipv4_changed(&self, address: Ipv4Addr) -> bool {
+        self.data.ipv4_address.parse::<Ipv4Addr>().ok() != Some(address)
+    }
+
+    /// Returns `true` if the IPv6 address differs from the one stored in the cache (the records must then be updated).
+    pub fn ipv6_changed(&self, address: Ipv6Addr) -> bool {
+        self.data.ipv6_address.parse::<Ipv6Addr>().ok() != Some(address)
+    }
+
+    /// Appends the outcome of an update run to the run history of the cache. Only the 100 most recent runs are kept.
+    pub fn record_run(&mut self, updated: usize, unchanged: usize, failed: usize) {
+        self.fmt();
+        self.data.run_history.push(RunRecord { timestamp: self.data.timestamps.now(), updated, unchanged, failed });
+        self.fmt();
+    }
+
+    /// Returns the run history of the cache, from the oldest run to the most recent one.
+    pub fn run_history(&self) -> &[RunRecord] {
+        &self.data.run_history
+    }
+
+    /// Returns a SHA-256 checksum (as a lowercase hexadecimal string) of the cache's data. The metadata (including the
+    /// timestamp), the run history, and the times of the last success and error of the DNS providers are not part of the
+    /// checksum, so it only changes when the IP addresses, the DNS providers, or their records change (and not on every run).
+    /// This is meant for external tools that want to detect changes to the cache without comparing whole files.
+    pub fn checksum(&self) -> String {
+        let mut data = self.data.clone();
+        data.run_history.clear();
+        for p in data.dns_providers.iter_mut() {
+            p.last_success = None;
+            if let Some((timestamp, _)) = p.last_error.as_mut() {
+                timestamp.clear();
+            }
+        }
+        let data = serde_json::to_vec(&data).unwrap_or_default();
+        format!("{:x}", Sha256::digest(data))
+    }
+
+    /// Returns the records managed through a DNS provider, or `None` if the DNS provider does not exist in the cache.
+    pub fn records(&self, provider_id: &str) -> Option<&[RecordConfig]> {
+        self.data.dns_providers.iter().find(|p| p.is(provider_id)).map(|p| p.records.as_slice())
+    }
+
+    /// Returns the total number of records managed through the DNS providers of the cache.
+    pub fn record_count(&self) -> usize {
+        self.data.dns_providers.iter().map(|p| p.records.len()).sum()
+    }
+
+    /// Returns the number of records managed through a DNS provider, or `None` if the DNS provider does not exist in the cache.
+    pub fn provider_record_count(&self, id: &str) -> Option<usize> {
+        self.records(id).map(|records| records.len())
+    }
+
+    /// Returns a redacted view of a DNS provider (see [`ProviderView`](wapi::api::cache::ProviderView)), or `None` if the DNS
+    /// provider does not exist in the cache.
+    pub fn provider(&self, id: &str) -> Option<ProviderView<'_>> {
+        self.data.dns_providers.iter().find(|p| p.is(id)).map(ProviderView::new)
+    }
+
+    /// Returns an iterator over redacted views of the DNS providers of the cache (see
+    /// [`ProviderView`](wapi::api::cache::ProviderView)). This is the iteration counterpart of
+    /// [`Cache::provider`](wapi::Cache), and is meant for status output and user interfaces.
+    pub fn providers(&self) -> impl Iterator<Item = ProviderView<'_>> {
+        self.data.dns_providers.iter().map(ProviderView::new)
+    }
+
+    /// Replaces the labels of a DNS provider, which are free-form names used to group DNS providers (e.g. to only update the
+    /// ones of a project). The labels are trimmed and lowercased, and empty and duplicate ones are removed. If the DNS provider
+    /// does not exist in the cache, nothing happens.
+    pub fn set_provider_labels(&mut self, id: &str, labels: Vec<String>) {
+        self.fmt();
+        if let Some(p) = self.data.dns_providers.iter_mut().find(|p| p.is(id)) {
+            p.labels = labels;
+        }
+        self.fmt();
+    }
+
+    /// Enables or disables a DNS provider. The records of a disabled DNS provider are skipped by the update runs (see
+    /// [`RecordOutcome::Disabled`](wapi::api::update::RecordOutcome)), but its configuration and its history are kept, so that
+    /// it can be paused without being removed. The DNS providers are enabled by default. If the DNS provider does not exist in
+    /// the cache, nothing happens.
+    pub fn set_provider_enabled(&mut self, id: &str, enabled: bool) {
+        self.fmt();
+        if let Some(p) = self.data.dns_providers.iter_mut().find(|p| p.is(id)) {
+            p.enabled = enabled;
+        }
+        self.fmt();
+    }
+
+    /// Returns the extra HTTP headers sent with every request made to a DNS provider, or `None` if the DNS provider does not
+    /// exist in the cache.
+    pub fn extra_headers(&self, id: &str) -> Option<&[(String, String)]> {
+        self.data.dns_providers.iter().find(|p| p.is(id)).map(|p| p.extra_headers.as_slice())
+    }
+
+    /// Replaces the extra HTTP headers sent with every request made to a DNS provider (e.g. the `CF-Access-Client-Id` header
+    /// of an authentication gateway). The headers are trimmed, and the malformed ones are removed. If the DNS provider does not
+    /// exist in the cache, nothing happens.
+    pub fn set_extra_headers(&mut self, id: &str, headers: Vec<(String, String)>) {
+        self.fmt();
+        if let Some(p) = self.data.dns_providers.iter_mut().find(|p| p.is(id)) {
+            p.extra_headers = headers;
+        }
+        self.fmt();
+    }
+
+    /// Records that a record was just updated successfully through a DNS provider, which also clears its last error. If the
+    /// DNS provider does not exist in the cache, nothing happens.
+    pub fn record_provider_success(&mut self, id: &str) {
+        self.fmt();
+        if let Some(p) = self.data.dns_providers.iter_mut().find(|p| p.is(id)) {
+            p.last_success = Some(self.data.timestamps.now());
+            p.last_error = None;
+        }
+        self.fmt();
+    }
+
+    /// Records that a record could not be updated through a DNS provider, replacing its previous error. If the DNS provider
+    /// does not exist in the cache, nothing happens.
+    pub fn record_provider_failure(&mut self, id: &str, message: &str) {
+        self.fmt();
+        if let Some(p) = self.data.dns_providers.iter_mut().find(|p| p.is(id)) {
+            p.last_error = Some((self.data.timestamps.now(), message.to_string()));
+        }
+        self.fmt();
+    }
+
+    /// Returns the most recent error of a DNS provider as a `(timestamp, message)` pair, or `None` if the DNS provider
+    /// succeeded since its last error (or never failed, or does not exist in the cache).
+    pub fn last_error_for(&self, id: &str) -> Option<(&str, &str)> {
+        let p = self.data.dns_providers.iter().find(|p| p.is(id))?;
+        p.last_error.as_ref().map(|(timestamp, message)| (timestamp.as_str(), message.as_str()))
+    }
+}
+
+/// Runs the parsing and migration layers of the cache over untrusted content, ignoring their errors. It is used by the fuzz
+/// target of the cache (see the `fuzz` directory) and by the tests replaying its corpus, and is not part of the stable API.
+#[doc(hidden)]
+pub fn fuzz_parse(cache_file: &[u8]) {
+    let format = JsonFormat::default();
+    if let Ok(mut cache) = format.deserialize(cache_file) {
+        let _ = cache.validate();
+        let _ = cache.checksum();
+        let _ = cache.incomplete_providers();
+        let _ = cache.providers().count();
+        cache.fmt();
+        let _ = format.serialize(&cache);
+    }
+    let _ = Cache::upgrade(cache_file);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cache() {
+        let mut cache = Cache::new();
+        assert_eq!(cache.metadata.name, "wapi-cache");
+        assert_eq!(cache.metadata.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(cache.metadata.description, "The cache file for the Wapi client.");
+        assert_eq!(cache.metadata.homepage, "https://github.com/AmonRayfa/wapi");
+        assert_eq!(cache.data.ipv4_address, "0.0.0.0");
+        assert_eq!(cache.data.ipv6_address, "0:0:0:0:0:0:0:0");
+        assert_eq!(cache.data.dns_providers.len(), 0);
+
+        cache.add_dns_provider("cloudflare".to_string(), "SOME_API_KEY_1".to_string(), "SOME_SECRET_API_KEY_1".to_string());
         assert_eq!(cache.data.dns_providers.len(), 1);
         assert_eq!(cache.data.dns_providers[0].api_key, "SOME_API_KEY_1");
         assert_eq!(cache.data.dns_providers[0].secret_api_key, "SOME_SECRET_API_KEY_1");
@@ -284,4 +1576,682 @@ mod test {
             Err(e) => panic!("{}", e),
         };
     }
+
+    #[test]
+    fn test_records() {
+        let mut cache = Cache::new();
+        let record =
+            |domain: &str, subdomain: &str, record_type: RecordType| RecordConfig::new(domain, subdomain, record_type, 600);
+
+        assert!(matches!(
+            cache.add_record("cloudflare", record("example.com", "home", RecordType::A)),
+            Err(Error::UnknownProvider { id }) if id == "cloudflare"
+        ));
+        assert!(cache.records("cloudflare").is_none());
+
+        cache.add_dns_provider("cloudflare".to_string(), "SOME_API_KEY".to_string(), "SOME_SECRET_API_KEY".to_string());
+        assert_eq!(cache.records("cloudflare").map(|r| r.len()), Some(0));
+
+        cache.add_record("cloudflare", record("example.com", "home", RecordType::A)).unwrap();
+        cache.add_record("cloudflare", record("Example.ORG.", "", RecordType::Aaaa)).unwrap();
+        cache.add_record("cloudflare", record("example.net", "nas", RecordType::A)).unwrap();
+        cache.add_record("cloudflare", record("example.com", "home", RecordType::A)).unwrap();
+        cache.add_record("cloudflare", record("", "home", RecordType::A)).unwrap();
+
+        let records = cache.records("cloudflare").unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].fqdn(), "home.example.com");
+        assert_eq!(records[1].fqdn(), "example.org");
+        assert_eq!(records[1].record_type, RecordType::Aaaa);
+        assert_eq!(records[2].fqdn(), "nas.example.net");
+
+        cache.add_dns_provider("cloudflare".to_string(), "SOME_API_KEY_2".to_string(), "SOME_SECRET_API_KEY_2".to_string());
+        assert_eq!(cache.records("cloudflare").map(|r| r.len()), Some(3));
+
+        cache.remove_record("cloudflare", "example.org.", RecordType::A);
+        assert_eq!(cache.records("cloudflare").map(|r| r.len()), Some(3));
+        cache.remove_record("cloudflare", "example.org.", RecordType::Aaaa);
+        assert_eq!(cache.records("cloudflare").map(|r| r.len()), Some(2));
+    }
+
+    #[test]
+    fn test_checksum() {
+        let mut cache = Cache::new();
+        let checksum = cache.checksum();
+        assert_eq!(checksum.len(), 64);
+
+        cache.fmt();
+        assert_eq!(cache.checksum(), checksum);
+
+        cache.add_dns_provider("porkbun".to_string(), "SOME_API_KEY".to_string(), "SOME_SECRET_API_KEY".to_string());
+        assert_ne!(cache.checksum(), checksum);
+
+        cache.remove_dns_provider("porkbun".to_string());
+        assert_eq!(cache.checksum(), checksum);
+
+        // Ensures that two identical runs leave the checksum unchanged.
+        cache.add_dns_provider("porkbun".to_string(), "SOME_API_KEY".to_string(), "SOME_SECRET_API_KEY".to_string());
+        cache.add_dns_provider("ovh".to_string(), "SOME_API_KEY".to_string(), "SOME_SECRET_API_KEY".to_string());
+        let checksums: Vec<String> = (0..2)
+            .map(|_| {
+                cache.record_provider_success("porkbun");
+                cache.record_provider_failure("ovh", "503 Service Unavailable");
+                cache.record_run(1, 0, 1);
+                cache.checksum()
+            })
+            .collect();
+        assert_eq!(checksums[0], checksums[1]);
+    }
+
+    #[test]
+    fn test_zone_id() {
+        let mut cache = Cache::new();
+        cache.add_dns_provider("cloudflare".to_string(), "SOME_API_KEY".to_string(), "SOME_SECRET_API_KEY".to_string());
+        cache.add_record("cloudflare", RecordConfig::new("example.com", "home", RecordType::A, 600)).unwrap();
+        cache.add_record("cloudflare", RecordConfig::new("example.com", "nas", RecordType::Aaaa, 600)).unwrap();
+        cache.add_record("cloudflare", RecordConfig::new("example.org", "", RecordType::A, 600)).unwrap();
+        assert_eq!(cache.zone_id("cloudflare", "example.com"), None);
+
+        cache.set_zone_id("cloudflare", "example.com", Some(String::from("SOME_ZONE_ID")));
+        assert_eq!(cache.zone_id("cloudflare", "example.com"), Some("SOME_ZONE_ID"));
+        assert_eq!(cache.zone_id("cloudflare", "example.org"), None);
+        assert_eq!(cache.zone_id("porkbun", "example.com"), None);
+
+        cache.set_zone_id("cloudflare", "example.com", None);
+        assert_eq!(cache.zone_id("cloudflare", "example.com"), None);
+    }
+
+    #[test]
+    fn test_provider_credentials() {
+        let mut cache = Cache::new();
+        assert_eq!(cache.provider_credentials("porkbun"), None);
+
+        cache.add_dns_provider("porkbun".to_string(), "SOME_API_KEY".to_string(), "SOME_SECRET_API_KEY".to_string());
+        assert_eq!(cache.provider_credentials("porkbun"), Some(("SOME_API_KEY", "SOME_SECRET_API_KEY")));
+        assert_eq!(cache.provider_credentials("cloudflare"), None);
+    }
+
+    #[test]
+    fn test_run_history() {
+        let mut cache = Cache::new();
+        assert!(cache.run_history().is_empty());
+
+        cache.record_run(2, 1, 0);
+        cache.record_run(0, 2, 1);
+        assert_eq!(cache.run_history().len(), 2);
+        assert_eq!(
+            (cache.run_history()[0].updated, cache.run_history()[0].unchanged, cache.run_history()[0].failed),
+            (2, 1, 0)
+        );
+        assert_eq!(
+            (cache.run_history()[1].updated, cache.run_history()[1].unchanged, cache.run_history()[1].failed),
+            (0, 2, 1)
+        );
+
+        for i in 0..MAX_RUN_HISTORY {
+            cache.record_run(i, 0, 0);
+        }
+        assert_eq!(cache.run_history().len(), MAX_RUN_HISTORY);
+        assert_eq!(cache.run_history()[0].updated, 0);
+        assert_eq!(cache.run_history()[MAX_RUN_HISTORY - 1].updated, MAX_RUN_HISTORY - 1);
+    }
+
+    #[test]
+    fn test_timestamps() {
+        let mut cache = Cache::new();
+        assert_eq!(cache.timestamp_config(), &TimestampConfig::default());
+        let plain = cache.timestamp_config().now();
+        assert!(NaiveDateTime::parse_from_str(&plain, PLAIN_TIMESTAMP_FORMAT).is_ok());
+
+        // Ensures that the timestamps are written in the configured representation.
+        let rfc3339 = TimestampConfig { zone: TimestampZone::Utc, format: TimestampFormat::Rfc3339 };
+        cache.set_timestamp_config(rfc3339.clone()).unwrap();
+        cache.record_run(1, 0, 0);
+        let timestamp = &cache.run_history()[0].timestamp;
+        assert!(timestamp.ends_with('Z') && DateTime::parse_from_rfc3339(timestamp).is_ok());
+        let custom =
+            TimestampConfig { zone: TimestampZone::Utc, format: TimestampFormat::Custom(String::from("%d/%m/%Y %H:%M")) };
+        assert_eq!(TimestampConfig { format: TimestampFormat::Custom(String::from("%Y")), ..custom.clone() }.now().len(), 4);
+        assert!(cache
+            .set_timestamp_config(TimestampConfig { format: TimestampFormat::Custom(String::from("%Q")), ..custom.clone() })
+            .is_err());
+        assert_eq!(cache.timestamp_config(), &rfc3339);
+
+        // Ensures that the timestamps can be parsed whichever representation they were written with.
+        let noon = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+        for config in [&rfc3339, &custom] {
+            assert_eq!(config.parse("2025-01-01 12:00:00.000"), Some(noon));
+            assert_eq!(config.parse("2025-01-01T13:00:00.000+01:00"), Some(noon));
+            assert_eq!(config.parse("2025-01-01T12:00:00Z"), Some(noon));
+            assert_eq!(config.parse("not a timestamp"), None);
+        }
+        assert_eq!(custom.parse("01/01/2025 12:00"), Some(noon));
+        assert!(TimestampConfig::default().parse(&plain).is_some());
+    }
+
+    #[test]
+    fn test_conflict_policy() {
+        let mut cache = Cache::new();
+        cache.add_dns_provider("gandi".to_string(), "SOME_API_KEY".to_string(), "SOME_SECRET_API_KEY".to_string());
+        assert_eq!(cache.conflict_policy(), ConflictPolicy::Error);
+
+        cache.add_record("gandi", RecordConfig::new("example.com", "home", RecordType::A, 300)).unwrap();
+        cache.add_record("gandi", RecordConfig::new("example.com", "home", RecordType::Aaaa, 300)).unwrap();
+        match cache.add_record("gandi", RecordConfig::new("Example.com.", "home", RecordType::A, 3600)) {
+            Ok(_) => panic!("A conflicting record was added."),
+            Err(e) => assert!(format!("{:?}", e).contains("gandi") && format!("{:?}", e).contains("home.example.com")),
+        }
+        assert_eq!(cache.records("gandi").unwrap().len(), 2);
+        assert!(cache.validate().is_ok());
+
+        cache.set_conflict_policy(ConflictPolicy::KeepLast);
+        cache.add_record("gandi", RecordConfig::new("example.com", "home", RecordType::A, 3600)).unwrap();
+        assert_eq!(cache.records("gandi").unwrap().len(), 2);
+        assert_eq!(cache.records("gandi").unwrap()[1].ttl, 3600);
+
+        cache.set_conflict_policy(ConflictPolicy::KeepFirst);
+        cache.add_record("gandi", RecordConfig::new("example.com", "home", RecordType::A, 60)).unwrap();
+        assert_eq!(cache.records("gandi").unwrap().len(), 2);
+        assert_eq!(cache.records("gandi").unwrap()[1].ttl, 3600);
+        assert!(cache.validate().is_ok());
+    }
+
+    #[test]
+    fn test_read_only() {
+        let mut cache = Cache::new();
+        assert!(!cache.is_read_only());
+
+        cache.set_read_only(true);
+        cache.add_dns_provider("ovh".to_string(), "SOME_API_KEY".to_string(), "SOME_SECRET_API_KEY".to_string());
+        assert!(cache.is_read_only());
+        assert!(cache.records("ovh").is_some());
+        match cache.save() {
+            Ok(_) => panic!("A read-only cache was saved."),
+            Err(e) => assert!(matches!(e, Error::CachePermission { .. })),
+        }
+        match cache.compact() {
+            Ok(_) => panic!("A read-only cache was compacted."),
+            Err(e) => assert!(matches!(e, Error::CachePermission { .. })),
+        }
+    }
+
+    #[test]
+    fn test_provider_params() {
+        let mut cache = Cache::new();
+        cache.set_provider_param("ovh", "update_url", Some(String::from("https://dyn.example.net/nic/update")));
+        assert_eq!(cache.provider_param("ovh", "update_url"), None);
+
+        cache.add_dns_provider("ovh".to_string(), "SOME_USERNAME".to_string(), "SOME_PASSWORD".to_string());
+        cache.set_provider_param("ovh", "update_url", Some(String::from("https://dyn.example.net/nic/update")));
+        assert_eq!(cache.provider_param("ovh", "update_url"), Some("https://dyn.example.net/nic/update"));
+
+        cache.add_dns_provider("ovh".to_string(), "SOME_USERNAME_2".to_string(), "SOME_PASSWORD_2".to_string());
+        assert_eq!(cache.provider_param("ovh", "update_url"), Some("https://dyn.example.net/nic/update"));
+
+        cache.set_provider_param("ovh", "update_url", None);
+        assert_eq!(cache.provider_param("ovh", "update_url"), None);
+    }
+
+    #[test]
+    fn test_providers() {
+        let mut cache = Cache::new();
+        cache.add_dns_provider(String::from("porkbun"), String::from("pk1_0123456789abcd"), String::from("short"));
+        cache.add_dns_provider(String::from("godaddy"), String::new(), String::from("0123456789ab"));
+        cache.add_record("porkbun", RecordConfig::new("example.com", "", RecordType::A, 600)).unwrap();
+        cache.add_record("porkbun", RecordConfig::new("example.com", "www", RecordType::A, 600)).unwrap();
+        cache.record_provider_success("porkbun");
+
+        let views: Vec<ProviderView> = cache.providers().collect();
+        assert_eq!(views.len(), 2);
+
+        let porkbun = cache.provider("porkbun").unwrap();
+        assert_eq!(porkbun.id, "porkbun");
+        assert_eq!(porkbun.api_key, "****abcd");
+        assert_eq!(porkbun.secret_api_key, "****");
+        assert_eq!(porkbun.record_count, 2);
+        assert!(porkbun.last_success.is_some());
+        assert!(views.contains(&porkbun));
+
+        let godaddy = cache.provider("godaddy").unwrap();
+        assert_eq!(godaddy.api_key, "");
+        assert_eq!(godaddy.secret_api_key, "****89ab");
+        assert_eq!(godaddy.record_count, 0);
+        assert_eq!(godaddy.last_success, None);
+
+        // Ensures that replacing the credentials of a DNS provider keeps its last success.
+        cache.add_dns_provider(String::from("porkbun"), String::from("new"), String::from("new"));
+        assert!(cache.provider("porkbun").unwrap().last_success.is_some());
+        assert!(cache.provider("namecheap").is_none());
+    }
+
+    #[test]
+    fn test_ip_changes() {
+        let mut cache = Cache::new();
+        let ipv4 = Ipv4Addr::new(203, 0, 113, 7);
+        let ipv6 = "2001:db8::1".parse::<Ipv6Addr>().unwrap();
+        assert!(cache.ipv4_changed(ipv4));
+        assert!(cache.ipv6_changed(ipv6));
+        assert_eq!((cache.ipv4(), cache.ipv6()), (None, None));
+
+        cache.set_ipv4(ipv4);
+        cache.set_ipv6(ipv6);
+        assert_eq!((cache.ipv4(), cache.ipv6()), (Some(ipv4), Some(ipv6)));
+        assert!(!cache.ipv4_changed(ipv4));
+        assert!(!cache.ipv6_changed(ipv6));
+        assert!(cache.ipv4_changed(Ipv4Addr::new(203, 0, 113, 8)));
+        assert_eq!(cache.data.ipv6_address, "2001:db8::1");
+    }
+
+    #[test]
+    fn test_provider_labels() {
+        let mut cache = Cache::new();
+        cache.add_dns_provider("gandi".to_string(), "SOME_API_KEY".to_string(), "SOME_SECRET_API_KEY".to_string());
+        assert!(cache.provider("gandi").unwrap().labels.is_empty());
+
+        let labels = vec![" Prod ".to_string(), "home".to_string(), "prod".to_string(), "".to_string()];
+        cache.set_provider_labels("gandi", labels);
+        assert_eq!(cache.provider("gandi").unwrap().labels, ["prod", "home"]);
+
+        // Ensures that replacing the credentials of a DNS provider keeps its labels.
+        cache.add_dns_provider("gandi".to_string(), "NEW_API_KEY".to_string(), "NEW_SECRET_API_KEY".to_string());
+        assert_eq!(cache.provider("gandi").unwrap().labels, ["prod", "home"]);
+
+        cache.set_provider_labels("gandi", Vec::new());
+        assert!(cache.provider("gandi").unwrap().labels.is_empty());
+        cache.set_provider_labels("ovh", vec!["prod".to_string()]);
+        assert!(cache.provider("ovh").is_none());
+    }
+
+    #[test]
+    fn test_ensure_provider() {
+        let mut cache = Cache::new();
+        assert_eq!(cache.ensure_provider("ovh", "SOME_API_KEY", "SOME_SECRET_API_KEY").unwrap(), UpsertOutcome::Created);
+        cache.add_record("ovh", RecordConfig::new("example.com", "", RecordType::A, 600)).unwrap();
+        assert_eq!(cache.ensure_provider("ovh", "SOME_API_KEY", "SOME_SECRET_API_KEY").unwrap(), UpsertOutcome::Unchanged);
+        assert_eq!(cache.ensure_provider("ovh", "NEW_API_KEY", "SOME_SECRET_API_KEY").unwrap(), UpsertOutcome::Updated);
+        assert_eq!(cache.provider_credentials("ovh"), Some(("NEW_API_KEY", "SOME_SECRET_API_KEY")));
+        assert_eq!(cache.records("ovh").unwrap().len(), 1);
+        assert!(matches!(
+            cache.ensure_provider("unknown", "SOME_API_KEY", "SOME_SECRET_API_KEY"),
+            Err(Error::UnsupportedProvider { id }) if id == "unknown"
+        ));
+        assert_eq!(cache.data.dns_providers.len(), 1);
+    }
+
+    #[test]
+    fn test_record_count() {
+        let mut cache = Cache::new();
+        assert_eq!(cache.record_count(), 0);
+        assert_eq!(cache.provider_record_count("gandi"), None);
+
+        cache.add_dns_provider("gandi".to_string(), "SOME_API_KEY".to_string(), "SOME_SECRET_API_KEY".to_string());
+        cache.add_dns_provider("ovh".to_string(), "SOME_API_KEY".to_string(), "SOME_SECRET_API_KEY".to_string());
+        assert_eq!(cache.provider_record_count("gandi"), Some(0));
+
+        cache.add_record("gandi", RecordConfig::new("example.com", "", RecordType::A, 600)).unwrap();
+        cache.add_record("gandi", RecordConfig::new("example.com", "", RecordType::Aaaa, 600)).unwrap();
+        cache.add_record("ovh", RecordConfig::new("example.org", "www", RecordType::A, 600)).unwrap();
+        assert_eq!(cache.provider_record_count("gandi"), Some(2));
+        assert_eq!(cache.provider_record_count("ovh"), Some(1));
+        assert_eq!(cache.record_count(), 3);
+    }
+
+    #[test]
+    fn test_last_error() {
+        let mut cache = Cache::new();
+        cache.add_dns_provider("godaddy".to_string(), "SOME_API_KEY".to_string(), "SOME_SECRET_API_KEY".to_string());
+        assert_eq!(cache.last_error_for("godaddy"), None);
+
+        cache.record_provider_failure("godaddy", "401 Unauthorized");
+        let (timestamp, message) = cache.last_error_for("godaddy").unwrap();
+        assert!(!timestamp.is_empty());
+        assert_eq!(message, "401 Unauthorized");
+
+        cache.record_provider_failure("godaddy", "503 Service Unavailable");
+        assert_eq!(cache.last_error_for("godaddy").unwrap().1, "503 Service Unavailable");
+
+        // Ensures that a success clears the last error.
+        cache.record_provider_success("godaddy");
+        assert_eq!(cache.last_error_for("godaddy"), None);
+        cache.record_provider_failure("ovh", "401 Unauthorized");
+        assert_eq!(cache.last_error_for("ovh"), None);
+    }
+
+    #[test]
+    fn test_extra_headers() {
+        let mut cache = Cache::new();
+        cache.set_extra_headers("cloudflare", vec![(String::from("X-Api-Key"), String::from("value"))]);
+        assert_eq!(cache.extra_headers("cloudflare"), None);
+
+        cache.add_dns_provider("cloudflare".to_string(), "SOME_API_KEY".to_string(), "SOME_SECRET_API_KEY".to_string());
+        let headers = vec![
+            (String::from(" CF-Access-Client-Id "), String::from(" SOME_CLIENT_ID ")),
+            (String::from("Invalid Name"), String::from("value")),
+            (String::from("X-Injected"), String::from("value\r\nX-Other: 1")),
+        ];
+        cache.set_extra_headers("cloudflare", headers);
+        assert_eq!(
+            cache.extra_headers("cloudflare").unwrap(),
+            [(String::from("CF-Access-Client-Id"), String::from("SOME_CLIENT_ID"))]
+        );
+
+        // Ensures that replacing the credentials of a DNS provider keeps its extra headers.
+        cache.add_dns_provider("cloudflare".to_string(), "NEW_API_KEY".to_string(), "NEW_SECRET_API_KEY".to_string());
+        assert_eq!(cache.extra_headers("cloudflare").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_rename_provider_id() {
+        let mut cache = Cache::new();
+        cache.add_dns_provider("cloudflare".to_string(), "SOME_API_KEY".to_string(), "SOME_SECRET_API_KEY".to_string());
+        cache.add_record("cloudflare", RecordConfig::new("example.com", "", RecordType::A, 600)).unwrap();
+        cache.set_provider_param("cloudflare", "update_url", Some(String::from("https://dyn.example.net/nic/update")));
+
+        assert!(matches!(cache.rename_provider_id("cloudflare", "some_random_name"), Err(Error::UnsupportedProvider { .. })));
+        assert!(matches!(cache.rename_provider_id("namecheap", "porkbun"), Err(Error::UnknownProvider { .. })));
+        cache.rename_provider_id("cloudflare", "porkbun").unwrap();
+        assert!(cache.records("cloudflare").is_none());
+        assert_eq!(cache.records("porkbun").unwrap().len(), 1);
+        assert_eq!(cache.provider_credentials("porkbun"), Some(("SOME_API_KEY", "SOME_SECRET_API_KEY")));
+        assert_eq!(cache.provider_param("porkbun", "update_url"), Some("https://dyn.example.net/nic/update"));
+
+        // Ensures that the records are merged into an existing DNS provider, whose credentials are replaced.
+        cache.add_dns_provider("ovh".to_string(), "OVH_API_KEY".to_string(), "OVH_SECRET_API_KEY".to_string());
+        cache.add_record("ovh", RecordConfig::new("example.org", "", RecordType::A, 600)).unwrap();
+        cache.rename_provider_id("ovh", "porkbun").unwrap();
+        assert_eq!(cache.data.dns_providers.len(), 1);
+        assert_eq!(cache.records("porkbun").unwrap().len(), 2);
+        assert_eq!(cache.records("porkbun").unwrap()[0].domain, "example.com");
+        assert_eq!(cache.provider_credentials("porkbun"), Some(("OVH_API_KEY", "OVH_SECRET_API_KEY")));
+
+        // Ensures that a merge creating conflicting records is refused.
+        cache.add_dns_provider("gandi".to_string(), "SOME_API_KEY".to_string(), "SOME_SECRET_API_KEY".to_string());
+        cache.add_record("gandi", RecordConfig::new("example.org", "", RecordType::A, 300)).unwrap();
+        assert!(cache.rename_provider_id("gandi", "porkbun").is_err());
+        assert_eq!(cache.records("gandi").unwrap().len(), 1);
+        assert_eq!(cache.records("porkbun").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_split_storage() {
+        let dir = std::env::temp_dir().join(format!("wapi-test-split-{}", std::process::id()));
+        let (config_path, secrets_path) = (dir.join("config.json"), dir.join("secrets.json"));
+
+        let mut cache = Cache::new();
+        cache.add_dns_provider("cloudflare".to_string(), "SOME_API_KEY".to_string(), "SOME_SECRET_API_KEY".to_string());
+        cache.add_record("cloudflare", RecordConfig::new("example.com", "home", RecordType::A, 600)).unwrap();
+        cache.save_split_to(&config_path, &secrets_path).unwrap();
+
+        // Ensures that the credentials are only written to the secrets file.
+        let config_file = std::fs::read_to_string(&config_path).unwrap();
+        assert!(config_file.contains("example.com"));
+        assert!(!config_file.contains("SOME_API_KEY"));
+        assert!(std::fs::read_to_string(&secrets_path).unwrap().contains("SOME_SECRET_API_KEY"));
+
+        let loaded = Cache::load_split_from(&config_path, &secrets_path).unwrap();
+        assert_eq!(loaded.provider_credentials("cloudflare"), Some(("SOME_API_KEY", "SOME_SECRET_API_KEY")));
+        assert_eq!(loaded.checksum(), cache.checksum());
+        assert!(loaded.incomplete_providers().is_empty());
+
+        // Ensures that the DNS providers are loaded without credentials if the secrets file is missing.
+        std::fs::remove_file(&secrets_path).unwrap();
+        let loaded = Cache::load_split_from(&config_path, &secrets_path).unwrap();
+        assert_eq!(loaded.provider_credentials("cloudflare"), Some(("", "")));
+        assert_eq!(loaded.records("cloudflare").unwrap().len(), 1);
+        assert_eq!(loaded.incomplete_providers(), vec!["cloudflare"]);
+
+        cache.set_read_only(true);
+        assert!(cache.save_split_to(&config_path, &secrets_path).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_incomplete_providers() {
+        let mut cache = Cache::new();
+        cache.add_dns_provider("cloudflare".to_string(), "SOME_API_KEY".to_string(), "SOME_SECRET_API_KEY".to_string());
+        cache.add_dns_provider("ovh".to_string(), "SOME_API_KEY".to_string(), " ".to_string());
+        cache.add_dns_provider("mock:success".to_string(), String::new(), String::new());
+        assert_eq!(cache.incomplete_providers(), vec!["ovh"]);
+    }
+
+    #[test]
+    fn test_load_errors() {
+        let dir = std::env::temp_dir().join(format!("wapi-test-load-errors-{}", std::process::id()));
+        let (config_path, secrets_path) = (dir.join("config.json"), dir.join("secrets.json"));
+
+        // Ensures that the callers can tell a missing file apart from a corrupted one.
+        match Cache::load_split_from(&config_path, &secrets_path) {
+            Err(Error::CacheRead { path, .. }) => assert_eq!(path, config_path),
+            _ => panic!("A missing cache file did not return a read error."),
+        }
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&config_path, "not json").unwrap();
+        match Cache::load_split_from(&config_path, &secrets_path) {
+            Err(err @ Error::CacheParse { .. }) => assert!(err.to_string().contains("config.json")),
+            _ => panic!("A corrupted cache file did not return a parsing error."),
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_provider_enabled() {
+        let mut cache = Cache::new();
+        cache.add_dns_provider("ovh".to_string(), "SOME_API_KEY".to_string(), "SOME_SECRET_API_KEY".to_string());
+        cache.add_record("ovh", RecordConfig::new("example.com", "", RecordType::A, 600)).unwrap();
+        assert!(cache.provider("ovh").unwrap().enabled);
+
+        // Ensures that disabling a DNS provider keeps its configuration, even if it is added again.
+        cache.set_provider_enabled("ovh", false);
+        cache.add_dns_provider("ovh".to_string(), "NEW_API_KEY".to_string(), "NEW_SECRET_API_KEY".to_string());
+        assert!(!cache.provider("ovh").unwrap().enabled);
+        assert_eq!(cache.provider_record_count("ovh"), Some(1));
+        cache.set_provider_enabled("ovh", true);
+        assert!(cache.provider("ovh").unwrap().enabled);
+        cache.set_provider_enabled("gandi", false);
+        assert!(cache.provider("gandi").is_none());
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("0.4.2"), Some((0, 4, 2)));
+        assert_eq!(parse_version("1.10.0-beta.1+build.5"), Some((1, 10, 0)));
+        assert!(parse_version("0.4.2") < parse_version("0.10.0"));
+        assert_eq!(parse_version("1.2"), None);
+        assert_eq!(parse_version("1.2.3.4"), None);
+        assert_eq!(parse_version(""), None);
+    }
+
+    #[test]
+    fn test_version() {
+        let mut cache = Cache::new();
+        assert_eq!(cache.version(), env!("CARGO_PKG_VERSION"));
+        assert!(cache.is_compatible_with(env!("CARGO_PKG_VERSION")));
+
+        // Ensures that the versions are compatible if they share their major version (or their minor version before 1.0.0).
+        cache.metadata.version = String::from("1.4.2");
+        assert!(cache.is_compatible_with("1.0.0") && cache.is_compatible_with("1.10.1-beta.1"));
+        assert!(!cache.is_compatible_with("2.0.0") && !cache.is_compatible_with("0.4.2"));
+        cache.metadata.version = String::from("0.4.2");
+        assert!(cache.is_compatible_with("0.4.0") && cache.is_compatible_with("0.4.10"));
+        assert!(!cache.is_compatible_with("0.5.0") && !cache.is_compatible_with("1.4.2"));
+        assert!(!cache.is_compatible_with("0.4"));
+        cache.metadata.version = String::new();
+        assert!(!cache.is_compatible_with(env!("CARGO_PKG_VERSION")));
+    }
+
+    #[test]
+    fn test_upgrade_in_place() {
+        let dir = std::env::temp_dir().join(format!("wapi-test-upgrade-{}", std::process::id()));
+        let path = dir.join("cache.json");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // Writes a cache file as an older version of the program would have.
+        let mut cache = Cache::new();
+        cache.add_dns_provider("ovh".to_string(), "SOME_API_KEY".to_string(), "SOME_SECRET_API_KEY".to_string());
+        cache.metadata.version = String::from("0.0.1");
+        std::fs::write(&path, JsonFormat::default().serialize(&cache).unwrap()).unwrap();
+
+        // Ensures that the cache file is only rewritten once.
+        assert!(Cache::upgrade_in_place(&path).unwrap());
+        let upgraded = std::fs::read(&path).unwrap();
+        let loaded = JsonFormat::default().deserialize(&upgraded).unwrap();
+        assert_eq!(loaded.metadata.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(loaded.provider_record_count("ovh"), Some(0));
+        assert!(!Cache::upgrade_in_place(&path).unwrap());
+        assert_eq!(std::fs::read(&path).unwrap(), upgraded);
+
+        // Ensures that a cache file written by a newer version of the program is not downgraded.
+        cache.metadata.version = String::from("999.0.0");
+        std::fs::write(&path, JsonFormat::default().serialize(&cache).unwrap()).unwrap();
+        assert!(!Cache::upgrade_in_place(&path).unwrap());
+
+        assert!(matches!(Cache::upgrade_in_place(&dir.join("missing.json")), Err(Error::CacheRead { .. })));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_fuzz_corpus() {
+        // Ensures that none of the inputs of the corpus (corrupted or hostile cache files) makes the parsing panic.
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures").join("cache");
+        let mut inputs = 0;
+        for entry in std::fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            let cache_file = std::fs::read(&path).unwrap();
+            assert!(std::panic::catch_unwind(|| fuzz_parse(&cache_file)).is_ok(), "The input {:?} panicked.", path);
+            inputs += 1;
+        }
+        assert!(inputs > 0);
+    }
+
+    #[test]
+    fn test_value_template() {
+        let ipv4: Option<Ipv4Addr> = Some("203.0.113.7".parse().unwrap());
+        let ipv6: Option<Ipv6Addr> = Some("2001:db8::1".parse().unwrap());
+        let spf =
+            RecordConfig::new("example.com", "", RecordType::Txt, 600).with_value_template("v=spf1 ip4:{ipv4} ip6:{ipv6} -all");
+        assert!(spf.uses_ipv4() && spf.uses_ipv6());
+        assert_eq!(spf.render_value(ipv4, ipv6), Some(String::from("v=spf1 ip4:203.0.113.7 ip6:2001:db8::1 -all")));
+        assert_eq!(spf.render_value(ipv4, None), None);
+        let alias = RecordConfig::new("example.com", "home", RecordType::Cname, 600).with_value_template("{ipv4}.nip.io");
+        assert!(alias.uses_ipv4() && !alias.uses_ipv6());
+        assert_eq!(alias.render_value(ipv4, None), Some(String::from("203.0.113.7.nip.io")));
+        assert_eq!(RecordConfig::new("example.com", "", RecordType::A, 600).render_value(ipv4, ipv6), None);
+
+        // Ensures that the templates incompatible with the type of their record are rejected.
+        let mut cache = Cache::new();
+        cache.add_dns_provider("cloudflare".to_string(), "SOME_API_KEY".to_string(), "SOME_SECRET_API_KEY".to_string());
+        cache.add_record("cloudflare", spf).unwrap();
+        cache.add_record("cloudflare", alias).unwrap();
+        cache
+            .add_record("cloudflare", RecordConfig::new("example.com", "", RecordType::A, 600).with_value_template("{ipv4}"))
+            .unwrap();
+        for record in [
+            RecordConfig::new("example.com", "nas", RecordType::Aaaa, 600).with_value_template("{ipv4}"),
+            RecordConfig::new("example.com", "nas", RecordType::A, 600).with_value_template("v=spf1 ip4:{ipv4} -all"),
+            RecordConfig::new("example.com", "nas", RecordType::Txt, 600),
+            RecordConfig::new("example.com", "nas", RecordType::Cname, 600).with_value_template("home.example.net"),
+        ] {
+            assert!(matches!(cache.add_record("cloudflare", record), Err(Error::CacheInvalid { .. })));
+        }
+        assert_eq!(cache.records("cloudflare").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_static_values() {
+        let detected: IpAddr = "203.0.113.7".parse().unwrap();
+        let other: IpAddr = "198.51.100.1".parse().unwrap();
+        let record = RecordConfig::new("example.com", "www", RecordType::A, 600).with_static_values(&[other, detected]);
+        assert_eq!(record.addresses(detected), [detected, other]);
+        assert_eq!(RecordConfig::new("example.com", "www", RecordType::A, 600).addresses(detected), [detected]);
+
+        // Ensures that the static addresses must belong to the family of their record.
+        let mut cache = Cache::new();
+        cache.add_dns_provider("cloudflare".to_string(), "SOME_API_KEY".to_string(), "SOME_SECRET_API_KEY".to_string());
+        cache.add_record("cloudflare", record).unwrap();
+        let ipv6: IpAddr = "2001:db8::1".parse().unwrap();
+        for record in [
+            RecordConfig::new("example.com", "nas", RecordType::A, 600).with_static_values(&[ipv6]),
+            RecordConfig::new("example.com", "nas", RecordType::Aaaa, 600).with_static_values(&[other]),
+            RecordConfig::new("example.com", "nas", RecordType::Txt, 600)
+                .with_value_template("{ipv4}")
+                .with_static_values(&[other]),
+        ] {
+            assert!(matches!(cache.add_record("cloudflare", record), Err(Error::CacheInvalid { .. })));
+        }
+        assert_eq!(cache.records("cloudflare").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_touch_and_save() {
+        let dir = std::env::temp_dir().join(format!("wapi-test-touch-{}", std::process::id()));
+        let path = dir.join("cache.json");
+        let mut cache = Cache::new();
+        cache.add_dns_provider("ovh".to_string(), "SOME_API_KEY".to_string(), "SOME_SECRET_API_KEY".to_string());
+
+        // Ensures that a cache that is not in sync with the cache file is saved whole.
+        cache.metadata.timestamp = String::from("2020-01-01 00:00:00.000");
+        cache.touch_and_save_to(&path).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(!content.contains("2020-01-01") && content.contains(&cache.metadata.timestamp));
+        assert_eq!(cache.synced.as_deref(), Some(path.as_path()));
+
+        // Ensures that only the timestamp is overwritten once the cache is in sync with the cache file.
+        let mut loaded = Cache::load_from(&path, &JsonFormat::default()).unwrap();
+        loaded.metadata.timestamp = String::from("2020-01-01 00:00:00.000");
+        let previous = content.replacen(&cache.metadata.timestamp, "2020-01-01 00:00:00.000", 1);
+        std::fs::write(&path, &previous).unwrap();
+        loaded.touch_and_save_to(&path).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content.len(), previous.len());
+        assert_eq!(content, previous.replacen("2020-01-01 00:00:00.000", &loaded.metadata.timestamp, 1));
+        assert_eq!(Cache::load_from(&path, &JsonFormat::default()).unwrap().checksum(), cache.checksum());
+
+        // Ensures that the changes made since the cache was loaded are not lost.
+        loaded.set_ipv4(Ipv4Addr::new(203, 0, 113, 7));
+        loaded.touch_and_save_to(&path).unwrap();
+        assert_eq!(Cache::load_from(&path, &JsonFormat::default()).unwrap().ipv4(), Some(Ipv4Addr::new(203, 0, 113, 7)));
+        assert!(!patch_timestamp(&path, "2020-01-01 00:00:00.000", "2021-01-01 00:00:00.000").unwrap());
+        assert!(!patch_timestamp(&path, &loaded.metadata.timestamp, "2021-01-01").unwrap());
+
+        // Ensures that a read-only cache is still not written.
+        loaded.set_read_only(true);
+        assert!(matches!(loaded.touch_and_save_to(&path), Err(Error::CachePermission { .. })));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_provider_aliases() {
+        assert_eq!(split_reference("cloudflare"), ("cloudflare", None));
+        assert_eq!(split_reference("cloudflare/work"), ("cloudflare", Some("work")));
+
+        // Ensures that the accounts of a DNS provider are kept side by side, each with its own credentials and records.
+        let mut cache = Cache::new();
+        cache.add_dns_provider("cloudflare/personal".to_string(), "PERSONAL_API_KEY".to_string(), String::new());
+        cache.add_dns_provider("cloudflare/work".to_string(), "WORK_API_KEY".to_string(), String::new());
+        cache.add_dns_provider("cloudflare".to_string(), "SOME_API_KEY".to_string(), String::new());
+        cache.add_record("cloudflare/work", RecordConfig::new("example.com", "vpn", RecordType::A, 600)).unwrap();
+        assert_eq!(cache.providers().count(), 3);
+        assert_eq!(cache.provider_credentials("cloudflare/personal"), Some(("PERSONAL_API_KEY", "")));
+        assert_eq!(cache.provider_credentials("cloudflare/work"), Some(("WORK_API_KEY", "")));
+        assert_eq!(cache.provider_credentials("cloudflare"), Some(("SOME_API_KEY", "")));
+        assert_eq!(cache.provider_record_count("cloudflare/work"), Some(1));
+        assert_eq!(cache.provider_record_count("cloudflare/personal"), Some(0));
+        let work = cache.provider("cloudflare/work").unwrap();
+        assert_eq!((work.id, work.alias, work.reference()), ("cloudflare", Some("work"), String::from("cloudflare/work")));
+        assert_eq!(cache.incomplete_providers(), vec!["cloudflare/personal", "cloudflare/work", "cloudflare"]);
+
+        // Ensures that the duplicates are detected by the pair of the ID and the alias, and that an empty alias is removed.
+        cache.add_dns_provider("cloudflare/work".to_string(), "NEW_API_KEY".to_string(), String::new());
+        assert_eq!(cache.providers().count(), 3);
+        assert_eq!(cache.provider_record_count("cloudflare/work"), Some(1));
+        cache.data.dns_providers[0].alias = Some(String::from(" "));
+        cache.fmt();
+        assert_eq!(cache.providers().map(|p| p.reference()).collect::<Vec<_>>(), vec!["cloudflare", "cloudflare/work"]);
+
+        // Ensures that an alias can be given to a DNS provider, and that removing an account leaves the other ones untouched.
+        cache.rename_provider_id("cloudflare", "cloudflare/home").unwrap();
+        assert_eq!(cache.provider_credentials("cloudflare/home"), Some(("SOME_API_KEY", "")));
+        cache.remove_dns_provider("cloudflare/work".to_string());
+        assert_eq!(cache.providers().map(|p| p.reference()).collect::<Vec<_>>(), vec!["cloudflare/home"]);
+    }
 }
@@ -0,0 +1,81 @@
+// Copyright 2025 Amon Rayfa.
+// SPDX-License-Identifier: Apache-2.0.
+
+//! This module contains the token used to cancel the long-running operations (the detection, the update runs, and the
+//! verification of the propagation), e.g. from the cancel button of an application embedding the library.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The longest time the waits between the retries sleep without checking whether the operation was cancelled.
+pub(crate) const CHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The token cancelling the operations it is given to. The cancellation is cooperative: it is checked between the requests
+/// (e.g. between the records of a run, or between the attempts of a detection), so a request that already started is never
+/// interrupted beyond its own timeout. Cloning it is cheap, and the clones cancel the same operations.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Creates a token that is not cancelled yet.
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// Cancels the operations the token was given to. Cancelling a token more than once has no effect.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns `true` if the token was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Returns `true` if the token is set and was cancelled.
+pub(crate) fn is_cancelled(token: Option<&CancellationToken>) -> bool {
+    token.is_some_and(CancellationToken::is_cancelled)
+}
+
+/// Sleeps for the duration, or until the token (if any) is cancelled.
+pub(crate) fn sleep(duration: Duration, token: Option<&CancellationToken>) {
+    let deadline = Instant::now() + duration;
+    while !is_cancelled(token) {
+        match deadline.checked_duration_since(Instant::now()) {
+            Some(remaining) if !remaining.is_zero() => std::thread::sleep(remaining.min(CHECK_INTERVAL)),
+            _ => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cancellation_token() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(!token.is_cancelled() && !is_cancelled(Some(&token)) && !is_cancelled(None));
+        clone.cancel();
+        assert!(token.is_cancelled() && is_cancelled(Some(&token)));
+
+        // Ensures that the waits end early once the token is cancelled.
+        let start = Instant::now();
+        sleep(Duration::from_secs(60), Some(&token));
+        assert!(start.elapsed() < Duration::from_secs(1));
+        let token = CancellationToken::new();
+        let canceller = token.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            canceller.cancel();
+        });
+        sleep(Duration::from_secs(60), Some(&token));
+        assert!(start.elapsed() < Duration::from_secs(2));
+        let start = Instant::now();
+        sleep(Duration::from_millis(20), None);
+        assert!(start.elapsed() >= Duration::from_millis(20));
+    }
+}
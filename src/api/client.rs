@@ -0,0 +1,780 @@
+// Copyright 2025 Amon Rayfa.
+// SPDX-License-Identifier: Apache-2.0.
+
+//! This module contains the [`Client`](wapi::Client), which owns everything needed to keep the records up to date (the
+//! detection and HTTP configurations, and the store of the cache), along with the stores the cache can be kept in.
+
+use crate::api::cache::{Cache, RecordConfig, RunRecord, UpsertOutcome};
+use crate::api::cancel::{self, CancellationToken};
+use crate::api::format::{CacheFormat, JsonFormat};
+use crate::api::ip::{self, DetectionResult, IpConfig};
+use crate::api::observer::WapiObserver;
+use crate::api::request::{HttpClient, HttpConfig};
+use crate::api::sync::lock;
+use crate::api::update::{self, UpdateOptions, UpdateReport};
+#[cfg(feature = "async")]
+use crate::api::watch::{self, IntervalTicks, IpWatch};
+use crate::error::api::{Error, Result};
+#[cfg(feature = "async")]
+use futures_core::Stream;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// The trait implemented by every store the cache can be kept in.
+pub trait CacheStore: Send + Sync {
+    /// Loads the cache from the store. An [`Error::CacheRead`](wapi::Error) with the `NotFound` I/O error kind is returned if
+    /// the store holds no cache yet.
+    fn load(&self) -> Result<Cache>;
+
+    /// Saves the cache to the store, replacing the previous one.
+    fn save(&self, cache: &mut Cache) -> Result<()>;
+}
+
+/// The store keeping the cache in a file, which is the cache file of the program by default (see
+/// [`Cache::get_path`](wapi::Cache)).
+pub struct FileStore {
+    path: Option<PathBuf>,
+    format: Box<dyn CacheFormat + Send + Sync>,
+}
+
+impl FileStore {
+    /// Creates a store keeping the cache in the file at the given path, in JSON.
+    pub fn new(path: &Path) -> FileStore {
+        FileStore { path: Some(path.to_path_buf()), format: Box::new(JsonFormat::default()) }
+    }
+
+    /// Sets the format the cache is stored in.
+    pub fn with_format(mut self, format: impl CacheFormat + Send + Sync + 'static) -> FileStore {
+        self.format = Box::new(format);
+        self
+    }
+
+    /// Returns the path of the file the cache is kept in, or an error if it cannot be located.
+    fn path(&self) -> Result<PathBuf> {
+        self.path.clone().or_else(Cache::get_path).ok_or_else(|| Error::CacheLocate {
+            reason: "No valid user home directory path could be retrieved from the operating system.".into(),
+        })
+    }
+}
+
+impl Default for FileStore {
+    fn default() -> Self {
+        FileStore { path: None, format: Box::new(JsonFormat::default()) }
+    }
+}
+
+impl CacheStore for FileStore {
+    fn load(&self) -> Result<Cache> {
+        Cache::load_from(&self.path()?, self.format.as_ref())
+    }
+
+    fn save(&self, cache: &mut Cache) -> Result<()> {
+        cache.save_to(&self.path()?, self.format.as_ref())
+    }
+}
+
+/// The store keeping the cache in memory, which never touches the filesystem (e.g. for the tests).
+#[derive(Default)]
+pub struct MemoryStore {
+    cache: Mutex<Option<Cache>>,
+}
+
+impl MemoryStore {
+    /// Creates a store holding no cache yet.
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+
+    /// Creates a store holding the cache.
+    pub fn with_cache(cache: Cache) -> MemoryStore {
+        MemoryStore { cache: Mutex::new(Some(cache)) }
+    }
+}
+
+impl CacheStore for MemoryStore {
+    fn load(&self) -> Result<Cache> {
+        match lock(&self.cache).as_ref() {
+            Some(cache) => Ok(cache.clone()),
+            None => Err(Error::CacheRead { path: PathBuf::new(), source: std::io::ErrorKind::NotFound.into() }),
+        }
+    }
+
+    fn save(&self, cache: &mut Cache) -> Result<()> {
+        cache.ensure_writable(Path::new(""))?;
+        *lock(&self.cache) = Some(cache.clone());
+        Ok(())
+    }
+}
+
+/// The store coalescing the saves of another store, for the long-running processes that save the cache several times in
+/// quick succession (e.g. after each record of a busy run). The first save opens a window (e.g. 500ms) during which the next
+/// saves only replace the pending cache, which is written to the wrapped store once the window elapses, so that a burst of
+/// saves results in a single write. The pending cache is also written by [`DebouncedStore::flush`] and when the store is
+/// dropped, so that no change is lost on shutdown.
+pub struct DebouncedStore {
+    inner: Arc<dyn CacheStore>,
+    window: Duration,
+    state: Arc<(Mutex<PendingSave>, Condvar)>,
+}
+
+/// The state shared between a [`DebouncedStore`] and the thread writing its pending cache.
+#[derive(Default)]
+struct PendingSave {
+    cache: Option<Cache>,
+    deadline: Option<Instant>,
+    writing: bool,
+    error: Option<Error>,
+}
+
+impl DebouncedStore {
+    /// Creates a store coalescing the saves made to the given store within the window.
+    pub fn new(store: impl CacheStore + 'static, window: Duration) -> DebouncedStore {
+        DebouncedStore { inner: Arc::new(store), window, state: Arc::default() }
+    }
+
+    /// Writes the pending cache to the wrapped store right away, if any. The error of the write is returned, or the one of
+    /// the previous write made at the end of a window if it failed.
+    pub fn flush(&self) -> Result<()> {
+        let mut pending = lock(&self.state.0);
+        pending.deadline = None;
+        self.state.1.notify_all();
+        let result = match pending.cache.take() {
+            Some(mut cache) => self.inner.save(&mut cache),
+            None => Ok(()),
+        };
+        match pending.error.take() {
+            Some(error) => Err(error),
+            None => result,
+        }
+    }
+
+    /// Starts the thread writing the pending cache once the window elapses.
+    fn spawn_writer(&self) {
+        let (inner, state) = (self.inner.clone(), self.state.clone());
+        std::thread::spawn(move || {
+            let (mutex, wake) = &*state;
+            let mut pending = lock(mutex);
+            // Waits for the end of the window, which is closed early by a flush.
+            while let Some(deadline) = pending.deadline {
+                match deadline.checked_duration_since(Instant::now()) {
+                    Some(timeout) if !timeout.is_zero() => {
+                        pending = wake.wait_timeout(pending, timeout).unwrap_or_else(|e| e.into_inner()).0;
+                    }
+                    _ => break,
+                }
+            }
+            if let Some(mut cache) = pending.cache.take() {
+                if let Err(error) = inner.save(&mut cache) {
+                    tracing::warn!(error = ?error, "The pending cache could not be saved.");
+                    pending.error = Some(error);
+                }
+            }
+            pending.deadline = None;
+            pending.writing = false;
+        });
+    }
+}
+
+impl CacheStore for DebouncedStore {
+    fn load(&self) -> Result<Cache> {
+        match lock(&self.state.0).cache.as_ref() {
+            Some(cache) => Ok(cache.clone()),
+            None => self.inner.load(),
+        }
+    }
+
+    fn save(&self, cache: &mut Cache) -> Result<()> {
+        // Lets the wrapped store report right away that a read-only cache cannot be saved.
+        if cache.is_read_only() {
+            return self.inner.save(cache);
+        }
+
+        let mut pending = lock(&self.state.0);
+        pending.cache = Some(cache.clone());
+        if pending.deadline.is_none() {
+            pending.deadline = Some(Instant::now() + self.window);
+        }
+        if !pending.writing {
+            pending.writing = true;
+            self.spawn_writer();
+        }
+        match pending.error.take() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for DebouncedStore {
+    fn drop(&mut self) {
+        if let Err(error) = self.flush() {
+            tracing::warn!(error = ?error, "The pending cache could not be saved on shutdown.");
+        }
+    }
+}
+
+/// The summary of the state of the cache returned by [`Client::status`](wapi::Client).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Status {
+    /// The state of each DNS provider, in the order of the cache.
+    pub providers: Vec<ProviderStatus>,
+    /// The most recent update run, if any.
+    pub last_run: Option<RunRecord>,
+}
+
+/// The state of a DNS provider of the cache.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProviderStatus {
+    /// The ID of the DNS provider, followed by its alias if it has one (see
+    /// [`split_reference`](wapi::api::cache::split_reference)).
+    pub id: String,
+    /// Whether the DNS provider is enabled (see [`Cache::set_provider_enabled`](wapi::Cache)).
+    pub enabled: bool,
+    /// The number of records of the DNS provider.
+    pub records: usize,
+    /// The time of the last successful update of the DNS provider, if any.
+    pub last_success: Option<String>,
+    /// The time and the message of the last error of the DNS provider, if any.
+    pub last_error: Option<(String, String)>,
+}
+
+/// The entry point of the library, which owns the detection and HTTP configurations and the store of the cache, so that the
+/// operations do not depend on any global state (e.g. the location of the cache file). It is created with
+/// [`Client::builder`](wapi::Client). Cloning it is cheap, and the clones share the same store and observers.
+#[derive(Clone)]
+pub struct Client {
+    store: Arc<dyn CacheStore>,
+    ip: IpConfig,
+    http: HttpConfig,
+    observers: Vec<Arc<dyn WapiObserver>>,
+}
+
+impl Client {
+    /// Creates a builder of [`Client`](wapi::Client), with the default configurations and the cache file of the program.
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::default()
+    }
+
+    /// Detects the public IPv4 and IPv6 addresses of the machine (see [`detect_all`](wapi::api::ip::detect_all)).
+    pub fn detect(&self) -> DetectionResult {
+        self.detect_with(&self.ip)
+    }
+
+    /// Detects the public IP addresses like [`Client::detect`](wapi::Client), but stops early if the token is cancelled (see
+    /// [`IpConfig::cancel`](wapi::api::ip::IpConfig)).
+    pub fn detect_cancellable(&self, token: &CancellationToken) -> DetectionResult {
+        self.detect_with(&IpConfig { cancel: Some(token.clone()), ..self.ip.clone() })
+    }
+
+    /// Detects the public IP addresses with the configuration, and notifies the observers.
+    fn detect_with(&self, config: &IpConfig) -> DetectionResult {
+        let detected = ip::detect_all(config);
+        self.observers.iter().for_each(|o| o.on_detection(&detected));
+        detected
+    }
+
+    /// Detects the public IP addresses, updates the records of the cache's DNS providers accordingly (see
+    /// [`run`](wapi::api::update::run)), and saves the cache. An error is returned if the cache cannot be loaded or saved, or
+    /// if the run is aborted. The observers are notified of the outcome of each record once the run is over, and then of the
+    /// completion of the run once the cache is saved. If the run is cancelled (see
+    /// [`UpdateOptions::cancel`](wapi::api::update::UpdateOptions)), the outcome of the records that were sent is still saved,
+    /// and an [`Error::Cancelled`](wapi::Error) is returned instead of the report.
+    pub fn update(&self, options: &UpdateOptions) -> Result<UpdateReport> {
+        let report = self.observe(self.run_update(options))?;
+        self.observers.iter().for_each(|o| o.on_run_complete(&report));
+        Ok(report)
+    }
+
+    /// Runs the update of [`Client::update`](wapi::Client), notifying the observers of the outcome of each record.
+    fn run_update(&self, options: &UpdateOptions) -> Result<UpdateReport> {
+        let mut cache = self.store.load()?;
+        let detected = match &options.cancel {
+            Some(token) => self.detect_cancellable(token),
+            None => self.detect(),
+        };
+        if cancel::is_cancelled(options.cancel.as_ref()) {
+            return Err(Error::Cancelled(String::from("update"), "The run was cancelled during the detection.".into()));
+        }
+
+        let report = update::run(&mut cache, detected.ipv4.ok(), detected.ipv6.ok(), options, &self.http)?;
+        for record in report.records.iter() {
+            self.observers.iter().for_each(|o| o.on_record_outcome(record));
+        }
+        self.store.save(&mut cache)?;
+        match report.cancelled {
+            true => Err(Error::Cancelled(
+                String::from("update"),
+                format!("The run was cancelled after {} records were updated.", report.updated()).into(),
+            )),
+            false => Ok(report),
+        }
+    }
+
+    /// Returns the state of the DNS providers of the cache and the most recent update run. An error is returned if the cache
+    /// cannot be loaded.
+    pub fn status(&self) -> Result<Status> {
+        let cache = self.observe(self.store.load())?;
+        let providers = cache
+            .providers()
+            .map(|p| {
+                let id = p.reference();
+                ProviderStatus {
+                    enabled: p.enabled,
+                    records: p.record_count,
+                    last_success: p.last_success.map(String::from),
+                    last_error: cache.last_error_for(&id).map(|(time, message)| (time.to_string(), message.to_string())),
+                    id,
+                }
+            })
+            .collect();
+
+        Ok(Status { providers, last_run: cache.run_history().last().cloned() })
+    }
+
+    /// Returns the records of the cache along with the ID of their DNS provider (followed by its alias if it has one), in the
+    /// order of the cache. An error is returned if the cache cannot be loaded.
+    pub fn list_records(&self) -> Result<Vec<(String, RecordConfig)>> {
+        let cache = self.observe(self.store.load())?;
+        let ids: Vec<String> = cache.providers().map(|p| p.reference()).collect();
+        Ok(ids
+            .into_iter()
+            .flat_map(|id| {
+                let records = cache.records(&id).unwrap_or_default().to_vec();
+                records.into_iter().map(move |record| (id.clone(), record))
+            })
+            .collect())
+    }
+
+    /// Adds a DNS provider to the cache, or replaces its credentials if it already exists (see
+    /// [`Cache::ensure_provider`](wapi::Cache)), and saves the cache. A new cache is created if the store holds none yet.
+    pub fn add_provider(&self, id: &str, api_key: &str, secret_api_key: &str) -> Result<UpsertOutcome> {
+        self.observe(self.upsert_provider(id, api_key, secret_api_key))
+    }
+
+    /// Adds the DNS provider of [`Client::add_provider`](wapi::Client) to the cache of the store.
+    fn upsert_provider(&self, id: &str, api_key: &str, secret_api_key: &str) -> Result<UpsertOutcome> {
+        let mut cache = match self.store.load() {
+            Err(Error::CacheRead { source, .. }) if source.kind() == std::io::ErrorKind::NotFound => Cache::new(),
+            result => result?,
+        };
+        let outcome = cache.ensure_provider(id, api_key, secret_api_key)?;
+        if outcome != UpsertOutcome::Unchanged {
+            self.store.save(&mut cache)?;
+        }
+        Ok(outcome)
+    }
+
+    /// Notifies the observers of the error of an operation, if any, and returns its result unchanged.
+    fn observe<T>(&self, result: Result<T>) -> Result<T> {
+        if let Err(error) = &result {
+            self.observers.iter().for_each(|o| o.on_error(error));
+        }
+        result
+    }
+}
+
+#[cfg(feature = "async")]
+impl Client {
+    /// Detects the public IP addresses like [`Client::detect`](wapi::Client), without blocking the asynchronous runtime (the
+    /// detection runs on the blocking thread pool of tokio).
+    pub async fn detect_async(&self) -> DetectionResult {
+        self.spawn_blocking(|client| client.detect()).await
+    }
+
+    /// Updates the records like [`Client::update`](wapi::Client), without blocking the asynchronous runtime (the run, including
+    /// the requests sent to the DNS providers, happens on the blocking thread pool of tokio).
+    pub async fn update_async(&self, options: &UpdateOptions) -> Result<UpdateReport> {
+        let options = options.clone();
+        self.spawn_blocking(move |client| client.update(&options)).await
+    }
+
+    /// Returns the stream of the changes of the public IP addresses, driven by an internal scheduler running a detection cycle
+    /// every interval (starting immediately). The first changes are relative to the addresses stored in the cache, if it can
+    /// be loaded. It must be called within a tokio runtime.
+    pub fn watch(&self, interval: Duration) -> IpWatch {
+        self.watch_with(IntervalTicks::new(interval))
+    }
+
+    /// Returns the stream of the changes of the public IP addresses like [`Client::watch`](wapi::Client), but with a
+    /// detection cycle run on each item of the given stream of ticks instead of the internal scheduler. The stream of changes
+    /// ends once the ticks end. It must be called within a tokio runtime.
+    pub fn watch_with<S>(&self, ticks: S) -> IpWatch
+    where
+        S: Stream + Send + Unpin + 'static,
+    {
+        let cache = self.store.load().ok();
+        let client = self.clone();
+        watch::spawn(move || client.detect(), cache.as_ref().and_then(Cache::ipv4), cache.as_ref().and_then(Cache::ipv6), ticks)
+    }
+
+    /// Runs an operation of the client on the blocking thread pool of tokio, so that the asynchronous API shares the
+    /// implementation of the blocking one. The panics of the operation are propagated to the caller.
+    async fn spawn_blocking<T, F>(&self, operation: F) -> T
+    where
+        T: Send + 'static,
+        F: FnOnce(&Client) -> T + Send + 'static,
+    {
+        let client = self.clone();
+        match tokio::task::spawn_blocking(move || operation(&client)).await {
+            Ok(value) => value,
+            Err(err) => std::panic::resume_unwind(err.into_panic()),
+        }
+    }
+}
+
+/// The builder of [`Client`](wapi::Client).
+#[derive(Default)]
+pub struct ClientBuilder {
+    store: Option<Arc<dyn CacheStore>>,
+    ip: Option<IpConfig>,
+    http: Option<HttpConfig>,
+    http_client: Option<HttpClient>,
+    timeout: Option<Duration>,
+    observers: Vec<Arc<dyn WapiObserver>>,
+}
+
+impl ClientBuilder {
+    /// Sets the store the cache is kept in. Defaults to the cache file of the program (see [`FileStore`]).
+    pub fn cache_store(mut self, store: impl CacheStore + 'static) -> ClientBuilder {
+        self.store = Some(Arc::new(store));
+        self
+    }
+
+    /// Sets the configuration used to detect the public IP addresses. Defaults to [`IpConfig::default`].
+    pub fn config(mut self, config: IpConfig) -> ClientBuilder {
+        self.ip = Some(config);
+        self
+    }
+
+    /// Sets the configuration of the HTTP clients used to reach the DNS providers. Defaults to [`HttpConfig::default`].
+    pub fn http(mut self, http: HttpConfig) -> ClientBuilder {
+        self.http = Some(http);
+        self
+    }
+
+    /// Sets the pre-configured HTTP client used both for the detection and the DNS providers instead of building one (see
+    /// [`HttpConfig::client`](wapi::api::request::HttpConfig)), e.g. to share the connection pool, the proxy, or the DNS
+    /// resolver of an application. The timeouts of the client apply instead of the ones of the configurations. The requests
+    /// that stay on the local network (to the UPnP gateway or to the instance metadata service) still use their own clients.
+    pub fn http_client(mut self, client: impl Into<HttpClient>) -> ClientBuilder {
+        self.http_client = Some(client.into());
+        self
+    }
+
+    /// Sets the request timeout of every HTTP client of the client (both for the detection and the DNS providers), overriding
+    /// the one of their configuration.
+    pub fn timeout(mut self, timeout: Duration) -> ClientBuilder {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Registers an observer notified of the events of the operations of the client (see
+    /// [`WapiObserver`](wapi::api::observer::WapiObserver)). It can be called several times, in which case the observers are
+    /// notified in the order they were registered.
+    pub fn observer(mut self, observer: impl WapiObserver + 'static) -> ClientBuilder {
+        self.observers.push(Arc::new(observer));
+        self
+    }
+
+    /// Builds the [`Client`](wapi::Client). An error is returned if an HTTP client cannot be built from the configurations
+    /// (e.g. because of an invalid extra header).
+    pub fn build(self) -> Result<Client> {
+        let mut ip = self.ip.unwrap_or_default();
+        let mut http = self.http.unwrap_or_default();
+        if let Some(timeout) = self.timeout {
+            ip.http.request_timeout = timeout;
+            http.request_timeout = timeout;
+        }
+        if let Some(client) = self.http_client {
+            ip.http.client = Some(client.clone());
+            http.client = Some(client);
+        }
+
+        // Ensures that the HTTP clients can be built, so that a misconfiguration is reported before any operation. They are
+        // not built here, since the client may be built within an asynchronous runtime.
+        ip.http.validate()?;
+        http.validate()?;
+
+        Ok(Client { store: self.store.unwrap_or_else(|| Arc::new(FileStore::default())), ip, http, observers: self.observers })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::cache::RecordType;
+    use crate::api::testing;
+
+    /// Serves the detected IPv4 address on a local port, so that the detection does not depend on the network, and returns the
+    /// URL of the server.
+    fn serve_address() -> String {
+        testing::serve(|_| ("200 OK", "203.0.113.7"))
+    }
+
+    /// Builds a client keeping its cache in memory and detecting no IP address, so that it never touches the filesystem nor
+    /// the network.
+    fn offline_client(store: MemoryStore) -> Client {
+        let config = IpConfig { ipv4_endpoints: Vec::new(), ipv6_endpoints: Vec::new(), ..IpConfig::default() };
+        Client::builder().cache_store(store).config(config).timeout(Duration::from_secs(1)).build().unwrap()
+    }
+
+    #[test]
+    fn test_client() {
+        let client = offline_client(MemoryStore::new());
+        assert_eq!(client.ip.http.request_timeout, Duration::from_secs(1));
+        assert_eq!(client.http.request_timeout, Duration::from_secs(1));
+        assert!(client.http.client.is_none());
+        assert!(matches!(client.status(), Err(Error::CacheRead { .. })));
+
+        // Ensures that adding a DNS provider creates the cache in the store.
+        assert_eq!(client.add_provider("ovh", "SOME_API_KEY", "SOME_SECRET_API_KEY").unwrap(), UpsertOutcome::Created);
+        assert_eq!(client.add_provider("ovh", "SOME_API_KEY", "SOME_SECRET_API_KEY").unwrap(), UpsertOutcome::Unchanged);
+        assert!(client.add_provider("some_random_name", "SOME_API_KEY", "").is_err());
+        let status = client.status().unwrap();
+        assert_eq!(status.providers.len(), 1);
+        assert_eq!((status.providers[0].id.as_str(), status.providers[0].records), ("ovh", 0));
+        assert_eq!(status.last_run, None);
+        assert!(client.list_records().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_client_update() {
+        let mut cache = Cache::new();
+        cache.add_dns_provider("ovh".to_string(), "SOME_API_KEY".to_string(), "SOME_SECRET_API_KEY".to_string());
+        cache.add_record("ovh", RecordConfig::new("example.com", "home", RecordType::A, 600)).unwrap();
+        let client = offline_client(MemoryStore::with_cache(cache));
+
+        let records = client.list_records().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!((records[0].0.as_str(), records[0].1.fqdn()), ("ovh", String::from("home.example.com")));
+
+        // Ensures that the run is saved to the store, even if no address was detected.
+        let report = client.update(&UpdateOptions::default()).unwrap();
+        assert_eq!(report.skipped(), 1);
+        assert_eq!(client.status().unwrap().last_run.map(|r| r.updated), Some(0));
+
+        // Ensures that a cancelled run is reported as such, without being recorded.
+        let token = CancellationToken::new();
+        token.cancel();
+        let options = UpdateOptions { cancel: Some(token), ..UpdateOptions::default() };
+        assert!(client.update(&options).is_err_and(|e| e.is_cancelled() && e.code() == "E_CANCELLED"));
+        assert_eq!(client.status().unwrap().last_run.map(|r| r.updated), Some(0));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_client_async() {
+        let url = serve_address();
+
+        let mut cache = Cache::new();
+        cache.add_dns_provider(String::from("mock:success"), String::new(), String::new());
+        cache.add_record("mock:success", RecordConfig::new("example.com", "home", RecordType::A, 600)).unwrap();
+        let config = IpConfig { ipv4_endpoints: vec![url], ipv6_endpoints: Vec::new(), ..IpConfig::default() };
+        let client = Client::builder().cache_store(MemoryStore::with_cache(cache)).config(config).build().unwrap();
+
+        // Ensures that the asynchronous operations behave like the blocking ones, and share the store of the client.
+        assert_eq!(client.detect_async().await.ipv4.ok(), Some("203.0.113.7".parse().unwrap()));
+        let report = client.update_async(&UpdateOptions::default()).await.unwrap();
+        assert_eq!((report.updated(), report.skipped()), (1, 0));
+        assert_eq!(client.status().unwrap().last_run.map(|r| r.updated), Some(1));
+        let report = client.update_async(&UpdateOptions::default()).await.unwrap();
+        assert_eq!(report.unchanged(), 1);
+    }
+
+    #[test]
+    fn test_http_client() {
+        let http_client = reqwest::blocking::Client::builder().build().unwrap();
+        let client = Client::builder().cache_store(MemoryStore::new()).http_client(http_client).build().unwrap();
+        assert!(client.ip.http.client.is_some() && client.http.client.is_some());
+
+        // Serves the detected IPv4 address only to the requests carrying the extra header.
+        let url = testing::serve(|request| match request.contains("x-api-key: SOME_KEY") {
+            true => ("200 OK", "203.0.113.7"),
+            false => ("403 Forbidden", ""),
+        });
+
+        // Ensures that the extra headers are sent along with the requests of a pre-configured HTTP client.
+        let headers = vec![(String::from("X-Api-Key"), String::from("SOME_KEY"))];
+        let http = HttpConfig { headers, ..HttpConfig::default() };
+        let config = IpConfig { ipv4_endpoints: vec![url], ipv6_endpoints: Vec::new(), http: http.clone(), ..IpConfig::default() };
+        let client = Client::builder()
+            .cache_store(MemoryStore::new())
+            .config(config)
+            .http(http)
+            .http_client(reqwest::blocking::Client::builder().build().unwrap())
+            .build()
+            .unwrap();
+        assert_eq!(client.detect().ipv4.ok(), Some("203.0.113.7".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_observers() {
+        use crate::api::observer::WapiObserver;
+        use crate::api::provider::mock::{MockMethod, MockProvider, MockResponse};
+        use crate::api::provider::UpdateStatus;
+        use crate::api::testing::TempCache;
+        use crate::api::update::{RecordOutcome, RecordReport};
+        use crate::error::api::ErrorKind;
+        /// Records the events it is notified of, as strings.
+        #[derive(Default, Clone)]
+        struct Recorder(Arc<Mutex<Vec<String>>>);
+
+        impl WapiObserver for Recorder {
+            fn on_detection(&self, detection: &DetectionResult) {
+                self.0.lock().unwrap().push(format!("detection {:?}", detection.ipv4.as_ref().ok()));
+            }
+
+            fn on_record_outcome(&self, record: &RecordReport) {
+                let outcome = match record.outcome {
+                    RecordOutcome::Failed(_) => "failed",
+                    _ => "other",
+                };
+                self.0.lock().unwrap().push(format!("record {} {}", record.provider, outcome));
+            }
+
+            fn on_run_complete(&self, report: &UpdateReport) {
+                self.0.lock().unwrap().push(format!("run {} {}", report.updated(), report.failed()));
+            }
+
+            fn on_error(&self, _error: &Error) {
+                self.0.lock().unwrap().push(String::from("error"));
+            }
+        }
+
+        // Ensures that the failed operations are notified to every observer.
+        let (first, second) = (Recorder::default(), Recorder::default());
+        let client =
+            Client::builder().cache_store(MemoryStore::new()).observer(first.clone()).observer(second.clone()).build().unwrap();
+        assert!(client.update(&UpdateOptions::default()).is_err());
+        assert!(client.status().is_err());
+        assert_eq!(*first.0.lock().unwrap(), vec!["error", "error"]);
+        assert_eq!(*second.0.lock().unwrap(), vec!["error", "error"]);
+
+        let url = serve_address();
+
+        // Ensures that a mixed run notifies the detection, then each record in order, and then the completion of the run.
+        let failing = MockProvider::scripted("mock:observers").unwrap().respond_with(
+            MockMethod::UpdateRecord,
+            [MockResponse::Fail(ErrorKind::Http), MockResponse::Succeed(UpdateStatus::Unchanged)],
+        );
+        failing.register();
+        let mut cache = Cache::new();
+        for id in ["mock:success", "mock:observers"] {
+            cache.add_dns_provider(String::from(id), String::new(), String::new());
+            cache.add_record(id, RecordConfig::new("example.com", "home", RecordType::A, 600)).unwrap();
+        }
+        let temp = TempCache::with_cache(cache).unwrap();
+        let recorder = Recorder::default();
+        let config = IpConfig { ipv4_endpoints: vec![url], ipv6_endpoints: Vec::new(), ..IpConfig::default() };
+        let client = Client::builder().cache_store(temp.store()).config(config).observer(recorder.clone()).build().unwrap();
+        client.update(&UpdateOptions::default()).unwrap();
+        MockProvider::unregister("mock:observers");
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            vec!["detection Some(203.0.113.7)", "record mock:success other", "record mock:observers failed", "run 1 1"]
+        );
+        assert_eq!(failing.calls(), vec![(String::from("home.example.com"), "203.0.113.7".parse().unwrap())]);
+        assert!(temp.load().unwrap().last_error_for("mock:observers").is_some());
+    }
+
+    #[test]
+    fn test_debounced_store() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        /// Counts the writes made to a store kept in memory.
+        struct CountingStore(Arc<MemoryStore>, Arc<AtomicUsize>);
+
+        impl CacheStore for CountingStore {
+            fn load(&self) -> Result<Cache> {
+                self.0.load()
+            }
+
+            fn save(&self, cache: &mut Cache) -> Result<()> {
+                self.1.fetch_add(1, Ordering::SeqCst);
+                self.0.save(cache)
+            }
+        }
+
+        let (memory, writes) = (Arc::new(MemoryStore::new()), Arc::new(AtomicUsize::new(0)));
+        let store = DebouncedStore::new(CountingStore(memory.clone(), writes.clone()), Duration::from_millis(200));
+
+        // Ensures that the saves made within the window are coalesced into a single write, and that the pending cache is
+        // returned by the store in the meantime.
+        let mut cache = Cache::new();
+        for id in ["ovh", "gandi", "porkbun"] {
+            cache.add_dns_provider(String::from(id), String::from("SOME_API_KEY"), String::from("SOME_SECRET_API_KEY"));
+            store.save(&mut cache).unwrap();
+        }
+        assert_eq!(writes.load(Ordering::SeqCst), 0);
+        assert_eq!(store.load().unwrap().providers().count(), 3);
+        assert!(memory.load().is_err());
+        std::thread::sleep(Duration::from_millis(500));
+        assert_eq!(writes.load(Ordering::SeqCst), 1);
+        assert_eq!(memory.load().unwrap().providers().count(), 3);
+
+        // Ensures that the pending cache is written right away when the store is flushed or dropped.
+        cache.add_dns_provider(String::from("mock:success"), String::from("SOME_API_KEY"), String::new());
+        store.save(&mut cache).unwrap();
+        store.flush().unwrap();
+        assert_eq!(writes.load(Ordering::SeqCst), 2);
+        store.flush().unwrap();
+        assert_eq!(writes.load(Ordering::SeqCst), 2);
+        cache.add_dns_provider(String::from("mock:failure"), String::from("SOME_API_KEY"), String::new());
+        store.save(&mut cache).unwrap();
+        drop(store);
+        assert_eq!(writes.load(Ordering::SeqCst), 3);
+        assert_eq!(memory.load().unwrap().providers().count(), 5);
+    }
+
+    #[test]
+    fn test_tracing() {
+        use crate::api::testing::TempCache;
+        use std::io::Write;
+        use tracing_subscriber::fmt::format::FmtSpan;
+
+        /// Writes the traces into a shared buffer, so that they can be inspected once the run is over.
+        struct Capture(Arc<Mutex<Vec<u8>>>);
+
+        impl Write for Capture {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let url = serve_address();
+
+        let mut cache = Cache::new();
+        for id in ["mock:success", "mock:fail"] {
+            cache.add_dns_provider(String::from(id), String::from("SOME_API_KEY"), String::from("SOME_SECRET_API_KEY"));
+            cache.add_record(id, RecordConfig::new("example.com", "home", RecordType::A, 600)).unwrap();
+        }
+        let temp = TempCache::with_cache(cache).unwrap();
+        let config = IpConfig { ipv4_endpoints: vec![url], ipv6_endpoints: Vec::new(), ..IpConfig::default() };
+        let client = Client::builder().cache_store(temp.store()).config(config).build().unwrap();
+
+        // Runs the whole pipeline with a subscriber capturing every trace, including the closing of the spans.
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::TRACE)
+            .with_span_events(FmtSpan::CLOSE)
+            .with_ansi(false)
+            .with_writer(move || Capture(writer.clone()))
+            .finish();
+        tracing::subscriber::with_default(subscriber, || client.update(&UpdateOptions::default()).unwrap());
+
+        // Ensures that each stage of the pipeline is traced, and that the credentials never appear in the traces.
+        let traces = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        for span in ["detect", "provider.update", "cache.save"] {
+            assert!(traces.contains(&format!("{}{{", span)), "The \"{}\" span is missing:\n{}", span, traces);
+        }
+        assert!(traces.contains("outcome=\"updated\"") && traces.contains("outcome=\"failed\""));
+        assert!(!traces.contains("SOME_API_KEY") && !traces.contains("SOME_SECRET_API_KEY"));
+    }
+}
@@ -0,0 +1,343 @@
+// Copyright 2025 Amon Rayfa.
+// SPDX-License-Identifier: Apache-2.0.
+
+//! This module contains the structs and functions used to check whether the records of the cache's DNS providers currently
+//! resolve to the detected IP addresses, without updating them.
+
+use crate::api::cache::{Cache, RecordType};
+use crate::api::ip::{self, IpConfig};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::Duration;
+
+/// The amount of time to wait for the answer of a resolver before trying the next one.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// The state of a record compared to the detected IP address of its family.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DriftStatus {
+    /// The record resolves to the detected IP address.
+    InSync,
+    /// The record does not resolve to the detected IP address (or does not resolve at all).
+    Drifted,
+    /// The record could not be resolved (e.g. because no resolver answered), with the reason.
+    Unresolved(String),
+    /// The IP address of the record's family could not be detected, so the record could not be checked.
+    Undetected,
+}
+
+/// The struct describing the state of a record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordDrift {
+    /// The ID of the DNS provider managing the record, followed by its alias if it has one (see
+    /// [`split_reference`](wapi::api::cache::split_reference)).
+    pub provider: String,
+    /// The fully qualified domain name of the record.
+    pub fqdn: String,
+    /// The type of the record.
+    pub record_type: RecordType,
+    /// The detected IP address the record should resolve to, if it was detected.
+    pub expected: Option<IpAddr>,
+    /// The IP addresses the record currently resolves to.
+    pub resolved: Vec<IpAddr>,
+    /// The state of the record.
+    pub status: DriftStatus,
+}
+
+/// The struct summarizing a drift check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DriftReport {
+    /// The detected public IPv4 address, if it was detected.
+    pub ipv4: Option<Ipv4Addr>,
+    /// The detected public IPv6 address, if it was detected.
+    pub ipv6: Option<Ipv6Addr>,
+    /// The state of every record of the cache, in the order of the cache.
+    pub records: Vec<RecordDrift>,
+}
+
+impl DriftReport {
+    /// Returns the number of records that do not resolve to the detected IP address of their family.
+    pub fn drifted(&self) -> usize {
+        self.records.iter().filter(|r| r.status == DriftStatus::Drifted).count()
+    }
+
+    /// Returns `true` if every record was checked and resolves to the detected IP address of its family, which is what a
+    /// health check expects.
+    pub fn is_in_sync(&self) -> bool {
+        self.records.iter().all(|r| r.status == DriftStatus::InSync)
+    }
+}
+
+/// Detects the public IP addresses of the machine (with the default [`IpConfig`](wapi::api::ip::IpConfig)), resolves every
+/// record of the cache, and reports the records that do not resolve to the detected IP address of their family. Nothing is
+/// sent to the DNS providers. The resolvers are queried in order until one of them answers; if none is given, the resolver of
+/// the operating system is used instead.
+pub fn check_drift(cache: &Cache, resolvers: &[SocketAddr]) -> DriftReport {
+    check_drift_with(cache, resolvers, &IpConfig::default())
+}
+
+/// Checks the records of the cache like [`check_drift`](wapi::api::drift::check_drift), but detects the public IP addresses
+/// with the given configuration.
+pub fn check_drift_with(cache: &Cache, resolvers: &[SocketAddr], config: &IpConfig) -> DriftReport {
+    let detection = ip::detect_all(config);
+    compare(cache, detection.ipv4.ok(), detection.ipv6.ok(), |fqdn, record_type| resolve(fqdn, record_type, resolvers))
+}
+
+/// Compares the addresses every record of the cache resolves to (according to the resolver) with the detected IP addresses.
+fn compare<F>(cache: &Cache, ipv4: Option<Ipv4Addr>, ipv6: Option<Ipv6Addr>, resolve: F) -> DriftReport
+where
+    F: Fn(&str, RecordType) -> Result<Vec<IpAddr>, String>,
+{
+    let mut records = Vec::new();
+    for p in cache.providers() {
+        let id = p.reference();
+        for record in cache.records(&id).unwrap_or_default() {
+            let fqdn = record.fqdn();
+            let expected = match record.record_type {
+                RecordType::A => ipv4.map(IpAddr::V4),
+                RecordType::Aaaa => ipv6.map(IpAddr::V6),
+                // The TXT and CNAME records do not resolve to an address, so their drift cannot be checked.
+                RecordType::Txt | RecordType::Cname => continue,
+            };
+
+            // Only resolves the records whose expected address is known.
+            let (resolved, status) = match expected {
+                None => (Vec::new(), DriftStatus::Undetected),
+                Some(address) => match resolve(&fqdn, record.record_type) {
+                    Ok(resolved) if resolved.contains(&address) => (resolved, DriftStatus::InSync),
+                    Ok(resolved) => (resolved, DriftStatus::Drifted),
+                    Err(e) => (Vec::new(), DriftStatus::Unresolved(e)),
+                },
+            };
+
+            records.push(RecordDrift {
+                provider: id.clone(),
+                fqdn,
+                record_type: record.record_type,
+                expected,
+                resolved,
+                status,
+            });
+        }
+    }
+
+    DriftReport { ipv4, ipv6, records }
+}
+
+/// Resolves the addresses of a record with the resolvers (in order, until one of them answers), or with the resolver of the
+/// operating system if none is given. An error describing the last failure is returned if none of them answers.
+fn resolve(fqdn: &str, record_type: RecordType, resolvers: &[SocketAddr]) -> Result<Vec<IpAddr>, String> {
+    if resolvers.is_empty() {
+        return match (fqdn, 0).to_socket_addrs() {
+            Ok(addresses) => Ok(addresses.map(|a| a.ip()).filter(|a| matches_type(a, record_type)).collect()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(format!("The resolver of the operating system failed: {}", e)),
+        };
+    }
+
+    let mut last_error = String::new();
+    for resolver in resolvers {
+        match query(*resolver, fqdn, record_type, QUERY_TIMEOUT) {
+            Ok(addresses) => return Ok(addresses),
+            Err(e) => last_error = format!("The resolver {} failed: {}", resolver, e),
+        }
+    }
+
+    Err(last_error)
+}
+
+/// Returns `true` if the address belongs to the family of the record type.
+fn matches_type(address: &IpAddr, record_type: RecordType) -> bool {
+    matches!((address, record_type), (IpAddr::V4(_), RecordType::A) | (IpAddr::V6(_), RecordType::Aaaa))
+}
+
+/// Sends a DNS query for the record to the resolver over UDP, and returns the addresses of the answer. A name that does not
+/// exist resolves to no address. An error is returned if the resolver does not answer in time, or if its answer is invalid
+/// or reports a failure.
+fn query(resolver: SocketAddr, fqdn: &str, record_type: RecordType, timeout: Duration) -> Result<Vec<IpAddr>, String> {
+    let bind_address = match resolver {
+        SocketAddr::V4(_) => "0.0.0.0:0",
+        SocketAddr::V6(_) => "[::]:0",
+    };
+    let socket = UdpSocket::bind(bind_address).map_err(|e| e.to_string())?;
+    socket.set_read_timeout(Some(timeout)).map_err(|e| e.to_string())?;
+
+    let id = (std::process::id() as u16) ^ (std::time::UNIX_EPOCH.elapsed().map_or(0, |d| d.subsec_nanos()) as u16);
+    let request = build_query(id, fqdn, record_type)?;
+    socket.send_to(&request, resolver).map_err(|e| e.to_string())?;
+
+    // Ignores the datagrams that do not answer the query.
+    let mut buffer = [0; 4096];
+    loop {
+        let (size, source) = socket.recv_from(&mut buffer).map_err(|e| e.to_string())?;
+        if source == resolver && size >= 2 && u16::from_be_bytes([buffer[0], buffer[1]]) == id {
+            return parse_answer(&buffer[..size], record_type);
+        }
+    }
+}
+
+/// Builds a DNS query (with recursion desired) for the record.
+fn build_query(id: u16, fqdn: &str, record_type: RecordType) -> Result<Vec<u8>, String> {
+    let mut request = Vec::with_capacity(fqdn.len() + 18);
+    request.extend_from_slice(&id.to_be_bytes());
+    request.extend_from_slice(&[0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+    for label in fqdn.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(format!("The name \"{}\" is not a valid domain name.", fqdn));
+        }
+        request.push(label.len() as u8);
+        request.extend_from_slice(label.as_bytes());
+    }
+    request.push(0);
+    request.extend_from_slice(&qtype(record_type).to_be_bytes());
+    request.extend_from_slice(&[0x00, 0x01]);
+
+    Ok(request)
+}
+
+/// Returns the DNS type code of the record type.
+fn qtype(record_type: RecordType) -> u16 {
+    match record_type {
+        RecordType::A => 1,
+        RecordType::Aaaa => 28,
+        RecordType::Txt => 16,
+        RecordType::Cname => 5,
+    }
+}
+
+/// Parses the answer of a resolver into the addresses of the requested type (the other records of the answer, such as the
+/// CNAME records leading to them, are skipped).
+fn parse_answer(answer: &[u8], record_type: RecordType) -> Result<Vec<IpAddr>, String> {
+    let invalid = || String::from("The answer of the resolver is invalid.");
+    let read_u16 = |pos: usize| answer.get(pos..pos + 2).map(|b| u16::from_be_bytes([b[0], b[1]])).ok_or_else(invalid);
+
+    // Checks the response code (3 means that the name does not exist).
+    match read_u16(2)? & 0x000F {
+        0 => {}
+        3 => return Ok(Vec::new()),
+        code => return Err(format!("The resolver answered with the response code {}.", code)),
+    }
+
+    // Skips the questions.
+    let (questions, answers) = (read_u16(4)?, read_u16(6)?);
+    let mut pos = 12;
+    for _ in 0..questions {
+        pos = skip_name(answer, pos).ok_or_else(invalid)? + 4;
+    }
+
+    let mut addresses = Vec::new();
+    for _ in 0..answers {
+        pos = skip_name(answer, pos).ok_or_else(invalid)?;
+        let (answer_type, length) = (read_u16(pos)?, read_u16(pos + 8)? as usize);
+        let data = answer.get(pos + 10..pos + 10 + length).ok_or_else(invalid)?;
+        if answer_type == qtype(record_type) {
+            match data.len() {
+                4 => addresses.push(IpAddr::from(<[u8; 4]>::try_from(data).map_err(|_| invalid())?)),
+                16 => addresses.push(IpAddr::from(<[u8; 16]>::try_from(data).map_err(|_| invalid())?)),
+                _ => return Err(invalid()),
+            }
+        }
+        pos += 10 + length;
+    }
+
+    Ok(addresses)
+}
+
+/// Returns the position following the (possibly compressed) domain name starting at the position, or `None` if the name is
+/// truncated.
+fn skip_name(message: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let length = *message.get(pos)?;
+        match length {
+            0 => return Some(pos + 1),
+            l if l & 0xC0 == 0xC0 => return message.get(pos + 1).map(|_| pos + 2),
+            l => pos += 1 + l as usize,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::cache::RecordConfig;
+
+    /// Starts a resolver on a random local port that answers every query with the address (or with no address at all).
+    fn serve(address: Option<IpAddr>) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let resolver = socket.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let mut buffer = [0; 512];
+            while let Ok((size, source)) = socket.recv_from(&mut buffer) {
+                let mut answer = buffer[..size].to_vec();
+                answer[2..4].copy_from_slice(&[0x81, 0x80]);
+                if let Some(address) = address {
+                    answer[6..8].copy_from_slice(&[0x00, 0x01]);
+                    let (answer_type, data) = match address {
+                        IpAddr::V4(a) => (1u16, a.octets().to_vec()),
+                        IpAddr::V6(a) => (28u16, a.octets().to_vec()),
+                    };
+                    answer.extend_from_slice(&[0xC0, 0x0C]);
+                    answer.extend_from_slice(&answer_type.to_be_bytes());
+                    answer.extend_from_slice(&[0x00, 0x01, 0x00, 0x00, 0x0E, 0x10]);
+                    answer.extend_from_slice(&(data.len() as u16).to_be_bytes());
+                    answer.extend_from_slice(&data);
+                }
+                let _ = socket.send_to(&answer, source);
+            }
+        });
+        resolver
+    }
+
+    #[test]
+    fn test_query() {
+        let resolver = serve(Some("203.0.113.7".parse().unwrap()));
+        let addresses = query(resolver, "home.example.com", RecordType::A, Duration::from_secs(2)).unwrap();
+        assert_eq!(addresses, vec!["203.0.113.7".parse::<IpAddr>().unwrap()]);
+
+        let resolver = serve(Some("2001:db8::1".parse().unwrap()));
+        let addresses = query(resolver, "home.example.com.", RecordType::Aaaa, Duration::from_secs(2)).unwrap();
+        assert_eq!(addresses, vec!["2001:db8::1".parse::<IpAddr>().unwrap()]);
+
+        // Ensures that the answers of other types and the invalid names are handled.
+        assert!(query(resolver, "home.example.com", RecordType::A, Duration::from_secs(2)).unwrap().is_empty());
+        assert!(query(resolver, "home..example.com", RecordType::A, Duration::from_secs(2)).is_err());
+        assert!(parse_answer(&[0x00, 0x01, 0x81, 0x83, 0, 0, 0, 0, 0, 0, 0, 0], RecordType::A).unwrap().is_empty());
+        assert!(parse_answer(&[0x00, 0x01, 0x81, 0x82, 0, 0, 0, 0, 0, 0, 0, 0], RecordType::A).is_err());
+        assert!(parse_answer(&[0x00, 0x01, 0x81, 0x80, 0, 1], RecordType::A).is_err());
+
+        // Ensures that the next resolver is queried if one does not answer.
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let resolvers = [silent.local_addr().unwrap(), serve(Some("203.0.113.7".parse().unwrap()))];
+        drop(silent);
+        assert_eq!(resolve("example.com", RecordType::A, &resolvers).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_compare() {
+        let mut cache = Cache::new();
+        cache.add_dns_provider("ovh".to_string(), "SOME_API_KEY".to_string(), "SOME_SECRET_API_KEY".to_string());
+        cache.add_record("ovh", RecordConfig::new("example.com", "home", RecordType::A, 600)).unwrap();
+        cache.add_record("ovh", RecordConfig::new("example.com", "nas", RecordType::A, 600)).unwrap();
+        cache.add_record("ovh", RecordConfig::new("example.com", "vpn", RecordType::A, 600)).unwrap();
+        cache.add_record("ovh", RecordConfig::new("example.com", "home", RecordType::Aaaa, 600)).unwrap();
+
+        let report = compare(&cache, Some(Ipv4Addr::new(203, 0, 113, 7)), None, |fqdn, _| match fqdn {
+            "home.example.com" => Ok(vec!["203.0.113.7".parse().unwrap()]),
+            "nas.example.com" => Ok(vec!["198.51.100.1".parse().unwrap()]),
+            _ => Err(String::from("timeout")),
+        });
+        let statuses: Vec<&DriftStatus> = report.records.iter().map(|r| &r.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                &DriftStatus::InSync,
+                &DriftStatus::Drifted,
+                &DriftStatus::Unresolved(String::from("timeout")),
+                &DriftStatus::Undetected
+            ]
+        );
+        assert_eq!(report.drifted(), 1);
+        assert!(!report.is_in_sync());
+        assert_eq!(report.records[1].resolved, vec!["198.51.100.1".parse::<IpAddr>().unwrap()]);
+        assert_eq!(report.records[1].provider, "ovh");
+    }
+}
@@ -0,0 +1,72 @@
+// Copyright 2025 Amon Rayfa.
+// SPDX-License-Identifier: Apache-2.0.
+
+//! This module contains the trait used to store the program's cache in different formats, and the formats provided by the
+//! program.
+
+use crate::api::cache::Cache;
+use crate::error::api::{Error, Result};
+use std::path::PathBuf;
+
+/// The trait implemented by every format the cache can be stored in.
+pub trait CacheFormat {
+    /// Serializes the cache into the content of the cache file.
+    fn serialize(&self, cache: &Cache) -> Result<Vec<u8>>;
+
+    /// Deserializes the content of the cache file into a cache.
+    fn deserialize(&self, bytes: &[u8]) -> Result<Cache>;
+
+    /// Returns `true` if the format is not meant to be read by humans, in which case the warning of the cache's metadata
+    /// (which tells not to edit the file manually) is omitted. Defaults to `false`.
+    fn is_binary(&self) -> bool {
+        false
+    }
+}
+
+/// The JSON format, which is the default format of the cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JsonFormat {
+    /// If `true`, the JSON is indented to be easier to read. Defaults to `true`.
+    pub pretty: bool,
+}
+
+impl Default for JsonFormat {
+    fn default() -> Self {
+        JsonFormat { pretty: true }
+    }
+}
+
+impl CacheFormat for JsonFormat {
+    fn serialize(&self, cache: &Cache) -> Result<Vec<u8>> {
+        match self.pretty {
+            true => serde_json::to_vec_pretty(cache),
+            false => serde_json::to_vec(cache),
+        }
+        .map_err(|source| Error::CacheSerialize { source })
+    }
+
+    fn deserialize(&self, bytes: &[u8]) -> Result<Cache> {
+        // The path of the cache file is set by the cache (see `Error::with_path`), since the format does not know it.
+        serde_json::from_slice(bytes).map_err(|err| Error::CacheParse { path: PathBuf::new(), source: err })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_json_format() {
+        let mut cache = Cache::new();
+        cache.add_dns_provider("ovh".to_string(), "SOME_API_KEY".to_string(), "SOME_SECRET_API_KEY".to_string());
+
+        let pretty = JsonFormat::default().serialize(&cache).unwrap();
+        let compact = JsonFormat { pretty: false }.serialize(&cache).unwrap();
+        assert!(compact.len() < pretty.len());
+        assert!(!JsonFormat::default().is_binary());
+
+        let loaded = JsonFormat::default().deserialize(&compact).unwrap();
+        assert_eq!(loaded.checksum(), cache.checksum());
+        assert!(JsonFormat::default().deserialize(b"not json").is_err());
+    }
+}
@@ -0,0 +1,960 @@
+// Copyright 2025 Amon Rayfa.
+// SPDX-License-Identifier: Apache-2.0.
+
+//! This module contains the structs and functions used to retrieve the machine's IP addresses.
+
+use crate::api::cancel::{self, CancellationToken};
+use crate::api::request::HttpConfig;
+use crate::error::api::{Error, Result, ResultExt};
+use reqwest::blocking::{Client, RequestBuilder};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, UdpSocket};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+/// The interface address flag marking a temporary address generated by the IPv6 privacy extensions (`IFA_F_TEMPORARY`).
+const IFA_F_TEMPORARY: u32 = 0x01;
+
+/// The interface address flag marking an address whose preferred lifetime has expired (`IFA_F_DEPRECATED`).
+const IFA_F_DEPRECATED: u32 = 0x20;
+
+/// The interface address flag marking an address that has not passed duplicate address detection yet (`IFA_F_TENTATIVE`).
+const IFA_F_TENTATIVE: u32 = 0x40;
+
+/// The interface address scope of globally routable addresses (`RT_SCOPE_UNIVERSE`).
+const RT_SCOPE_UNIVERSE: u32 = 0x00;
+
+/// The multicast address on which the UPnP devices listen for SSDP discovery requests.
+const SSDP_ADDRESS: &str = "239.255.255.250:1900";
+
+/// The base URL of the instance metadata services of the cloud platforms, which are only reachable from the instances.
+const METADATA_URL: &str = "http://169.254.169.254";
+
+/// The timeout of the requests sent to the instance metadata services, which answer almost instantly when they are reachable.
+const METADATA_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The types of the UPnP services able to return the external IP address of an Internet gateway device.
+const IGD_SERVICES: &[&str] = &[
+    "urn:schemas-upnp-org:service:WANIPConnection:1",
+    "urn:schemas-upnp-org:service:WANIPConnection:2",
+    "urn:schemas-upnp-org:service:WANPPPConnection:1",
+];
+
+/// The strategy used to query the detection endpoints of an IP address family.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DetectionStrategy {
+    /// The endpoints are queried one after the other, in order, until one of them returns a valid address (see
+    /// [`IpConfig::max_attempts`](wapi::api::ip::IpConfig) and [`IpConfig::retry_backoff`](wapi::api::ip::IpConfig)).
+    #[default]
+    Sequential,
+    /// The endpoints are queried concurrently, and the first valid public address returned by one of them is used, which
+    /// minimizes the detection latency. The other requests are then cancelled: the ones that were not sent yet are not sent,
+    /// and the answers of the ones already sent are dropped. Since a request that was sent cannot be interrupted, the
+    /// detection returns once these requests end, which the timeout bounds (so it should be short). The endpoints are queried
+    /// once, so the attempt budget and the backoff of the configuration are not used.
+    Race {
+        /// The time allowed for each endpoint to answer.
+        timeout: Duration,
+    },
+}
+
+/// The struct used to configure how the IP addresses are retrieved.
+#[derive(Debug, Clone)]
+pub struct IpConfig {
+    /// If `true`, the public IPv6 address is read from the stable addresses of the network interfaces, skipping the temporary
+    /// addresses generated by the privacy extensions (RFC 8981), which the detection endpoints would report since they are used
+    /// for the outgoing connections. This prevents the AAAA records from changing every time the temporary address rotates.
+    /// The IPv6 endpoints are only queried if no stable global address is found. Defaults to `false`.
+    pub prefer_stable_ipv6: bool,
+    /// The endpoints queried (in order) to detect the public IPv4 address. Each endpoint must answer with the IP address of
+    /// the client as plain text.
+    pub ipv4_endpoints: Vec<String>,
+    /// The endpoints queried (in order) to detect the public IPv6 address. Each endpoint must answer with the IP address of
+    /// the client as plain text.
+    pub ipv6_endpoints: Vec<String>,
+    /// The total number of requests allowed to detect an IP address. The attempts rotate across the endpoints (A, B, C, A, B,
+    /// ...), so a budget larger than the number of endpoints retries them in order instead of giving up after a single pass.
+    /// If `None`, each endpoint is tried exactly once. Defaults to `None`.
+    pub max_attempts: Option<usize>,
+    /// The delay waited before each new pass over the endpoints, doubled after every pass. Defaults to 1 second.
+    pub retry_backoff: Duration,
+    /// The strategy used to query the endpoints. Defaults to
+    /// [`DetectionStrategy::Sequential`](wapi::api::ip::DetectionStrategy).
+    pub strategy: DetectionStrategy,
+    /// The configuration of the HTTP client used to query the endpoints.
+    pub http: HttpConfig,
+    /// The token cancelling the detection, checked before each attempt (including after the waits between the passes, which
+    /// end early once it is cancelled). The detection of a cancelled family fails with an
+    /// [`Error::Cancelled`](wapi::Error). Defaults to `None`.
+    pub cancel: Option<CancellationToken>,
+}
+
+impl Default for IpConfig {
+    fn default() -> Self {
+        IpConfig {
+            prefer_stable_ipv6: false,
+            ipv4_endpoints: vec![
+                String::from("https://api.ipify.org"),
+                String::from("https://ipv4.icanhazip.com"),
+                String::from("https://v4.ident.me"),
+            ],
+            ipv6_endpoints: vec![
+                String::from("https://api6.ipify.org"),
+                String::from("https://ipv6.icanhazip.com"),
+                String::from("https://v6.ident.me"),
+            ],
+            max_attempts: None,
+            retry_backoff: Duration::from_secs(1),
+            strategy: DetectionStrategy::default(),
+            http: HttpConfig::default(),
+            cancel: None,
+        }
+    }
+}
+
+/// The struct holding the outcome of the detection of both IP address families.
+#[derive(Debug)]
+pub struct DetectionResult {
+    /// The detected public IPv4 address, or the error that prevented its detection.
+    pub ipv4: Result<Ipv4Addr>,
+    /// The detected public IPv6 address, or the error that prevented its detection.
+    pub ipv6: Result<Ipv6Addr>,
+    /// The endpoint that returned the IPv4 address, if it was detected.
+    pub ipv4_source: Option<String>,
+    /// The endpoint that returned the IPv6 address, if it was detected.
+    pub ipv6_source: Option<String>,
+}
+
+/// Detects the public IPv4 address of the machine by querying the IPv4 endpoints of the configuration in order, until one of
+/// them returns a valid address. An error is returned if none of them does.
+pub fn detect_ipv4(config: &IpConfig) -> Result<Ipv4Addr> {
+    let client = config.http.build_client()?;
+    detect(&client, &config.ipv4_endpoints, config)
+        .map(|(address, _)| address)
+        .with_context(|| "Could not detect the public IPv4 address.")
+}
+
+/// Detects the public IPv6 address of the machine by querying the IPv6 endpoints of the configuration in order, until one of
+/// them returns a valid address, unless a stable address of the network interfaces is preferred and found (see
+/// [`IpConfig::prefer_stable_ipv6`](wapi::api::ip::IpConfig)). An error is returned if none of them does.
+pub fn detect_ipv6(config: &IpConfig) -> Result<Ipv6Addr> {
+    if let Some((address, _)) = stable_ipv6(config) {
+        return Ok(address);
+    }
+    let client = config.http.build_client()?;
+    detect(&client, &config.ipv6_endpoints, config)
+        .map(|(address, _)| address)
+        .with_context(|| "Could not detect the public IPv6 address.")
+}
+
+/// Detects both the public IPv4 and IPv6 addresses of the machine, along with the endpoints that returned them (the source of
+/// an IPv6 address read from the network interfaces is `interfaces`, see
+/// [`IpConfig::prefer_stable_ipv6`](wapi::api::ip::IpConfig)). The two detections run concurrently and independently, so the
+/// failure of one family never prevents the other from being detected.
+pub fn detect_all(config: &IpConfig) -> DetectionResult {
+    let client = match config.http.build_client() {
+        Ok(c) => c,
+        Err(e) => {
+            // Builds the client again to report the failure of each family with its own source, since the errors cannot be
+            // cloned.
+            let ipv6 = config.http.build_client().err().unwrap_or_else(|| Error::Ip(String::from("http"), e.to_string().into()));
+            return DetectionResult {
+                ipv4: Err(Error::Ip(String::from("http"), e.into())),
+                ipv6: Err(ipv6),
+                ipv4_source: None,
+                ipv6_source: None,
+            }
+        }
+    };
+
+    let (ipv4, ipv6) = std::thread::scope(|scope| {
+        // Runs the IPv4 detection with the subscriber and the span of the caller, which are not inherited by the thread.
+        let (ipv4_client, dispatch, span) =
+            (client.clone(), tracing::dispatcher::get_default(|d| d.clone()), tracing::Span::current());
+        let ipv4 = scope.spawn(move || {
+            tracing::dispatcher::with_default(&dispatch, || {
+                span.in_scope(|| detect::<Ipv4Addr>(&ipv4_client, &config.ipv4_endpoints, config))
+            })
+        });
+        let ipv6 = match stable_ipv6(config) {
+            Some(found) => Ok(found),
+            None => detect::<Ipv6Addr>(&client, &config.ipv6_endpoints, config),
+        };
+        let ipv4 = ipv4
+            .join()
+            .unwrap_or_else(|_| Err(Error::Ip(String::from("http"), "The IPv4 address detection thread panicked.".into())));
+        (ipv4, ipv6)
+    });
+
+    let (ipv4, ipv4_source) = match ipv4 {
+        Ok((address, source)) => (Ok(address), Some(source)),
+        Err(e) => (Err(e.context("Could not detect the public IPv4 address.")), None),
+    };
+    let (ipv6, ipv6_source) = match ipv6 {
+        Ok((address, source)) => (Ok(address), Some(source)),
+        Err(e) => (Err(e.context("Could not detect the public IPv6 address.")), None),
+    };
+
+    DetectionResult { ipv4, ipv6, ipv4_source, ipv6_source }
+}
+
+/// Returns the first stable global IPv6 address of the network interfaces, along with its source (`interfaces`), if
+/// [`IpConfig::prefer_stable_ipv6`](wapi::api::ip::IpConfig) is set. The unique local addresses (`fc00::/7`), which are not
+/// routable on the Internet, are skipped. `None` is returned if the stable addresses are not preferred, if there is none, or if
+/// the interface addresses cannot be read, in which case the detection endpoints are queried instead.
+fn stable_ipv6(config: &IpConfig) -> Option<(Ipv6Addr, String)> {
+    stable_ipv6_with(config, interface_ipv6_addresses)
+}
+
+/// Returns the first stable global IPv6 address among the interface addresses returned by `read`, as `stable_ipv6`
+/// does, which lets the selection be tested on fixed interface addresses.
+fn stable_ipv6_with<F>(config: &IpConfig, read: F) -> Option<(Ipv6Addr, String)>
+where
+    F: FnOnce(&IpConfig) -> Result<Vec<Ipv6Addr>>,
+{
+    if !config.prefer_stable_ipv6 {
+        return None;
+    }
+
+    match read(config) {
+        Ok(addresses) => {
+            let address = addresses.into_iter().find(|a| is_public(IpAddr::V6(*a)));
+            tracing::debug!(found = address.is_some(), "Read the stable IPv6 addresses of the network interfaces.");
+            address.map(|a| (a, String::from("interfaces")))
+        }
+        Err(err) => {
+            tracing::debug!("The stable IPv6 addresses of the network interfaces could not be read: {:?}", err);
+            None
+        }
+    }
+}
+
+/// Queries the endpoints with the strategy of the configuration until one of them returns a valid IP address of the requested
+/// family, and returns it along with the endpoint that returned it. An error describing the last failure is returned if none
+/// of them succeeds. The detection is traced in a `detect` span, which records the endpoint that answered, the duration and
+/// the outcome.
+fn detect<T>(client: &Client, endpoints: &[String], config: &IpConfig) -> Result<(T, String)>
+where
+    T: FromStr + Into<IpAddr> + Copy + Send + 'static,
+{
+    let span = tracing::info_span!(
+        "detect",
+        endpoints = endpoints.len(),
+        strategy = ?config.strategy,
+        source = tracing::field::Empty,
+        duration_ms = tracing::field::Empty,
+        outcome = tracing::field::Empty,
+    )
+    .entered();
+    let start = Instant::now();
+    let result = match config.strategy {
+        DetectionStrategy::Sequential => query_endpoints::<T>(client, endpoints, config),
+        DetectionStrategy::Race { timeout } => race_endpoints::<T>(client, endpoints, config, timeout),
+    };
+
+    span.record("duration_ms", u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX));
+    match &result {
+        Ok((_, source)) => span.record("source", source.as_str()).record("outcome", "detected"),
+        Err(e) if e.is_cancelled() => span.record("outcome", "cancelled"),
+        Err(_) => span.record("outcome", "failed"),
+    };
+    result
+}
+
+/// Queries the endpoints in order until one of them returns a valid IP address of the requested family (see
+/// [`DetectionStrategy::Sequential`](wapi::api::ip::DetectionStrategy)). The endpoints are rotated until the attempt budget of
+/// the configuration is exhausted, with a growing backoff between the passes, and the detection stops early if a failure is
+/// not retryable (see [`Error::is_retryable`](wapi::Error)).
+fn query_endpoints<T>(client: &Client, endpoints: &[String], config: &IpConfig) -> Result<(T, String)>
+where
+    T: FromStr + Into<IpAddr> + Copy,
+{
+    let mut last_error = String::from("No endpoint is configured.");
+    let mut backoff = config.retry_backoff;
+    let mut retry_after = None;
+
+    for attempt in 0..config.max_attempts.unwrap_or(endpoints.len()) {
+        if endpoints.is_empty() {
+            break;
+        }
+
+        // Waits before starting a new pass over the endpoints, or longer if the last failure asked for it.
+        if attempt > 0 && attempt % endpoints.len() == 0 {
+            let delay = retry_after.map_or(backoff, |delay: Duration| delay.max(backoff));
+            tracing::debug!(
+                attempt = attempt + 1,
+                delay_ms = u64::try_from(delay.as_millis()).unwrap_or(u64::MAX),
+                "Retrying the detection endpoints."
+            );
+            cancel::sleep(delay, config.cancel.as_ref());
+            backoff = backoff.saturating_mul(2);
+        }
+        if cancel::is_cancelled(config.cancel.as_ref()) {
+            return Err(Error::Cancelled(String::from("detect"), "The detection was cancelled.".into()));
+        }
+
+        let endpoint = &endpoints[attempt % endpoints.len()];
+        match query_endpoint::<T>(client.get(endpoint), endpoint, &config.http) {
+            Ok(address) => return Ok((address, endpoint.clone())),
+            Err((message, None)) => last_error = message,
+            Err((message, Some(err))) => {
+                // Gives up if the request cannot succeed on any endpoint (e.g. the HTTP client is misconfigured).
+                last_error = message;
+                if !err.is_retryable() {
+                    break;
+                }
+                retry_after = err.retry_after();
+            }
+        }
+    }
+
+    Err(Error::Ip(String::from("http"), last_error.into()))
+}
+
+/// Queries all the endpoints concurrently, and returns the first valid IP address of the requested family returned by one of
+/// them (see [`DetectionStrategy::Race`](wapi::api::ip::DetectionStrategy)). The other requests are cancelled at that point,
+/// and no thread is left running once it returns.
+fn race_endpoints<T>(client: &Client, endpoints: &[String], config: &IpConfig, timeout: Duration) -> Result<(T, String)>
+where
+    T: FromStr + Into<IpAddr> + Copy + Send,
+{
+    let cancelled = || Error::Cancelled(String::from("detect"), "The detection was cancelled.".into());
+    if cancel::is_cancelled(config.cancel.as_ref()) {
+        return Err(cancelled());
+    }
+
+    // Runs each request on a scoped thread of its own, with the subscriber and the span of the caller, and bounds it with the
+    // timeout (which is also reported by its timeout errors). The threads do not send their request once the race is over.
+    let (sender, receiver) = mpsc::channel();
+    let over = AtomicBool::new(false);
+    let http = HttpConfig { request_timeout: timeout, ..config.http.clone() };
+    let (dispatch, span) = (tracing::dispatcher::get_default(|d| d.clone()), tracing::Span::current());
+    std::thread::scope(|scope| {
+        for endpoint in endpoints.iter() {
+            let (request, http, over, sender) = (client.get(endpoint).timeout(timeout), &http, &over, sender.clone());
+            let (dispatch, span) = (dispatch.clone(), span.clone());
+            scope.spawn(move || {
+                if over.load(Ordering::SeqCst) {
+                    return;
+                }
+                let result = tracing::dispatcher::with_default(&dispatch, || {
+                    span.in_scope(|| query_endpoint::<T>(request, endpoint, http))
+                });
+                let _ = sender.send((endpoint.clone(), result));
+            });
+        }
+        drop(sender);
+
+        // Waits for the first valid address until every request failed, checking regularly whether the detection was
+        // cancelled, then ends the race (the scope waits for the requests already sent).
+        let mut last_error = String::from("No endpoint is configured.");
+        let result = loop {
+            if cancel::is_cancelled(config.cancel.as_ref()) {
+                break Err(cancelled());
+            }
+            match receiver.recv_timeout(cancel::CHECK_INTERVAL) {
+                Ok((endpoint, Ok(address))) => break Ok((address, endpoint)),
+                Ok((_, Err((message, _)))) => last_error = message,
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break Err(Error::Ip(String::from("http"), last_error.into())),
+            }
+        };
+        over.store(true, Ordering::SeqCst);
+        result
+    })
+}
+
+/// Sends the request to a detection endpoint, and returns the IP address it answered with. If it did not answer with a valid
+/// IP address of the requested family, a message describing the failure is returned, along with the error of the request if
+/// the request itself failed.
+fn query_endpoint<T>(
+    request: RequestBuilder,
+    endpoint: &str,
+    http: &HttpConfig,
+) -> std::result::Result<T, (String, Option<Error>)>
+where
+    T: FromStr + Into<IpAddr> + Copy,
+{
+    let response = http.apply_headers(request).send();
+    match response.and_then(|response| response.error_for_status()).and_then(|response| response.text()) {
+        Ok(body) => parse_address::<T>(&body).ok_or_else(|| {
+            tracing::debug!(endpoint = %endpoint, "The detection endpoint returned an invalid IP address.");
+            (format!("The endpoint \"{}\" returned an invalid IP address.", endpoint), None)
+        }),
+        Err(err) => {
+            let err = http.map_error(err);
+            tracing::debug!(endpoint = %endpoint, retryable = err.is_retryable(), "The detection endpoint failed: {}", err);
+            Err((format!("The endpoint \"{}\" failed: {:?}", endpoint, err), Some(err)))
+        }
+    }
+}
+
+/// Parses the body returned by a detection endpoint. `None` is returned if the body is not an IP address of the requested
+/// family, or if the address is not public (see [`is_public`]).
+fn parse_address<T>(body: &str) -> Option<T>
+where
+    T: FromStr + Into<IpAddr> + Copy,
+{
+    body.trim().parse::<T>().ok().filter(|address| is_public((*address).into()))
+}
+
+/// Returns `true` if the address can be the public address of the machine, i.e. if it is not unspecified, loopback, private,
+/// shared (carrier-grade NAT), link-local, or unique local.
+fn is_public(address: IpAddr) -> bool {
+    match address {
+        IpAddr::V4(a) => {
+            // Matches 10.0.0.0/8, 172.16.0.0/12, 192.168.0.0/16, 100.64.0.0/10, and 169.254.0.0/16.
+            let [first, second, ..] = a.octets();
+            !(a.is_unspecified() || a.is_loopback() || a.is_private() || a.is_link_local() || (first == 100 && second & 0xc0 == 64))
+        }
+        IpAddr::V6(a) => {
+            // Matches fc00::/7 and fe80::/10.
+            let first = a.segments()[0];
+            !(a.is_unspecified() || a.is_loopback() || first & 0xfe00 == 0xfc00 || first & 0xffc0 == 0xfe80)
+        }
+    }
+}
+
+/// Detects the public IPv4 address of the machine by asking the router for it through UPnP IGD, which does not depend on any
+/// third-party endpoint. The Internet gateway device is discovered with SSDP, then its `GetExternalIPAddress` action is called.
+/// The timeout applies to the discovery and to each request sent to the device. An `Error::Ip` with the `upnp` method is
+/// returned if no device answers, if it does not expose a WAN connection service, or if it does not return a public address.
+/// The requests never use the HTTP client injected with [`HttpConfig::client`](wapi::api::request::HttpConfig), whose proxy
+/// cannot reach the local network.
+pub fn detect_via_upnp(timeout: Duration) -> Result<Ipv4Addr> {
+    let upnp_error = |detail: String| Error::Ip(String::from("upnp"), detail.into());
+
+    // Sends an SSDP discovery request and waits for an Internet gateway device to answer with the URL of its description.
+    let socket = UdpSocket::bind("0.0.0.0:0").map_err(|err| upnp_error(err.to_string()))?;
+    let request = "M-SEARCH * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\nMAN: \"ssdp:discover\"\r\nMX: 2\r\n\
+                   ST: urn:schemas-upnp-org:device:InternetGatewayDevice:1\r\n\r\n";
+    socket.send_to(request.as_bytes(), SSDP_ADDRESS).map_err(|err| upnp_error(err.to_string()))?;
+
+    let deadline = Instant::now() + timeout;
+    let mut buffer = [0; 2048];
+    let location = loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let answer = match remaining.is_zero() {
+            true => None,
+            false => socket.set_read_timeout(Some(remaining)).and_then(|_| socket.recv_from(&mut buffer)).ok(),
+        };
+        match answer {
+            Some((size, _)) => {
+                if let Some(location) = parse_ssdp_location(&String::from_utf8_lossy(&buffer[..size])) {
+                    break location;
+                }
+            }
+            None => return Err(upnp_error(format!("No Internet gateway device answered within {:?}.", timeout))),
+        }
+    };
+
+    // Downloads the description of the device to find the URL of its WAN connection service.
+    let http = HttpConfig { connect_timeout: timeout, request_timeout: timeout, headers: Vec::new(), client: None };
+    let client = http.build_client()?;
+    let description = client
+        .get(&location)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.text())
+        .map_err(|err| upnp_error(format!("{:?}", http.map_error(err))))?;
+    let (service, control_url) = find_control_url(&description, &location).ok_or_else(|| {
+        upnp_error(format!("The Internet gateway device at \"{}\" does not expose a WAN connection service.", location))
+    })?;
+
+    // Calls the `GetExternalIPAddress` action of the service.
+    let body = format!(
+        "<?xml version=\"1.0\"?><s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+         s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\"><s:Body>\
+         <u:GetExternalIPAddress xmlns:u=\"{}\"></u:GetExternalIPAddress></s:Body></s:Envelope>",
+        service
+    );
+    let response = client
+        .post(&control_url)
+        .header("Content-Type", "text/xml; charset=\"utf-8\"")
+        .header("SOAPAction", format!("\"{}#GetExternalIPAddress\"", service))
+        .body(body)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.text())
+        .map_err(|err| upnp_error(format!("{:?}", http.map_error(err))))?;
+
+    external_address(&response, &location)
+}
+
+/// Returns the address of the answer to the `GetExternalIPAddress` action of the device at the location. An `Error::Ip` with
+/// the `upnp` method is returned if there is none, or if it is a private, shared (carrier-grade NAT), or link-local address,
+/// which means that the device is not the edge of the network.
+fn external_address(response: &str, location: &str) -> Result<Ipv4Addr> {
+    let upnp_error = |detail: String| Error::Ip(String::from("upnp"), detail.into());
+    let address = xml_value(response, "NewExternalIPAddress")
+        .and_then(|value| value.parse::<Ipv4Addr>().ok())
+        .filter(|address| !address.is_unspecified() && !address.is_loopback())
+        .ok_or_else(|| {
+            upnp_error(format!("The Internet gateway device at \"{}\" did not return a public IPv4 address.", location))
+        })?;
+
+    if !is_public(IpAddr::V4(address)) {
+        return Err(upnp_error(format!(
+            "The Internet gateway device at \"{}\" returned the non-public address {}, so it is not the edge of the network \
+             (e.g. it is behind another router or a carrier-grade NAT).",
+            location, address
+        )));
+    }
+
+    Ok(address)
+}
+
+/// Returns the `LOCATION` header (the URL of the device description) of an SSDP answer, or `None` if there is none.
+fn parse_ssdp_location(answer: &str) -> Option<String> {
+    answer.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        match name.trim().eq_ignore_ascii_case("location") {
+            true => Some(value.trim().to_string()),
+            false => None,
+        }
+    })
+}
+
+/// Finds the first WAN connection service in the description of an Internet gateway device, and returns its type along with
+/// its absolute control URL (resolved against the `URLBase` of the description, or else the location of the description).
+fn find_control_url(description: &str, location: &str) -> Option<(String, String)> {
+    let (service, control_url) = description.split("<service>").skip(1).find_map(|service| {
+        let service_type = xml_value(service, "serviceType")?;
+        match IGD_SERVICES.contains(&service_type) {
+            true => Some((service_type.to_string(), xml_value(service, "controlURL")?.to_string())),
+            false => None,
+        }
+    })?;
+
+    if control_url.starts_with("http://") || control_url.starts_with("https://") {
+        return Some((service, control_url));
+    }
+
+    // Keeps only the scheme and the authority of the base URL.
+    let base = xml_value(description, "URLBase").unwrap_or(location);
+    let authority_end = base.find("://").map(|i| i + 3).map(|i| base[i..].find('/').map_or(base.len(), |j| i + j))?;
+    Some((service, format!("{}/{}", &base[..authority_end], control_url.trim_start_matches('/'))))
+}
+
+/// Returns the trimmed text of the first `tag` element of an XML document, or `None` if there is no such element.
+fn xml_value<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<{}>", tag))? + tag.len() + 2;
+    let end = xml[start..].find(&format!("</{}>", tag))? + start;
+    Some(xml[start..end].trim())
+}
+
+/// The cloud platforms whose instance metadata service can return the public IP address of the instance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloudProvider {
+    /// Amazon Web Services (EC2), whose metadata service is queried with an IMDSv2 session token.
+    Aws,
+    /// Google Cloud Platform (Compute Engine).
+    Gcp,
+    /// Microsoft Azure, whose metadata service only knows the public IP address of the instance if it is a Basic SKU public
+    /// IP address.
+    Azure,
+}
+
+impl fmt::Display for CloudProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CloudProvider::Aws => write!(f, "AWS"),
+            CloudProvider::Gcp => write!(f, "GCP"),
+            CloudProvider::Azure => write!(f, "Azure"),
+        }
+    }
+}
+
+/// Detects the public IP address of a cloud instance by reading it from the instance metadata service of its platform, which
+/// does not depend on any third-party endpoint. An `Error::Ip` with the `metadata` method is returned if the metadata
+/// service cannot be reached (e.g. if the machine is not an instance of the platform), or if the instance has no public IP
+/// address. The requests never use the HTTP client injected with [`HttpConfig::client`](wapi::api::request::HttpConfig),
+/// whose proxy cannot reach the link-local metadata service.
+pub fn detect_from_metadata(provider: CloudProvider) -> Result<IpAddr> {
+    detect_from_metadata_at(METADATA_URL, provider)
+}
+
+/// Detects the public IP address of a cloud instance from the instance metadata service at the base URL.
+fn detect_from_metadata_at(base_url: &str, provider: CloudProvider) -> Result<IpAddr> {
+    let metadata_error = |detail: String| Error::Ip(String::from("metadata"), detail.into());
+    let http =
+        HttpConfig { connect_timeout: METADATA_TIMEOUT, request_timeout: METADATA_TIMEOUT, headers: Vec::new(), client: None };
+    let client = http.build_client()?;
+
+    let request = match provider {
+        CloudProvider::Aws => {
+            // Requests a session token first, as required by IMDSv2.
+            let token = client
+                .put(format!("{}/latest/api/token", base_url))
+                .header("X-aws-ec2-metadata-token-ttl-seconds", "60")
+                .send()
+                .and_then(|response| response.error_for_status())
+                .and_then(|response| response.text())
+                .map_err(|err| metadata_error(format!("No session token was issued: {:?}", http.map_error(err))))?;
+            client.get(format!("{}/latest/meta-data/public-ipv4", base_url)).header("X-aws-ec2-metadata-token", token.trim())
+        }
+        CloudProvider::Gcp => client
+            .get(format!("{}/computeMetadata/v1/instance/network-interfaces/0/access-configs/0/external-ip", base_url))
+            .header("Metadata-Flavor", "Google"),
+        CloudProvider::Azure => client
+            .get(format!(
+                "{}/metadata/instance/network/interface/0/ipv4/ipAddress/0/publicIpAddress?api-version=2021-02-01&format=text",
+                base_url
+            ))
+            .header("Metadata", "true"),
+    };
+    let body = request
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.text())
+        .map_err(|err| metadata_error(format!("{:?}", http.map_error(err))))?;
+
+    parse_address::<IpAddr>(&body).ok_or_else(|| {
+        metadata_error(format!("The {} instance metadata service did not return a public IP address.", provider))
+    })
+}
+
+/// Retrieves the global IPv6 addresses assigned to the machine's network interfaces. Tentative addresses are always skipped,
+/// and temporary (and deprecated) addresses are skipped as well if
+/// [`IpConfig::prefer_stable_ipv6`](wapi::api::ip::IpConfig) is set. An error is returned if the interface addresses cannot be
+/// read from the operating system.
+pub fn interface_ipv6_addresses(config: &IpConfig) -> Result<Vec<Ipv6Addr>> {
+    #[cfg(target_os = "linux")]
+    {
+        let content =
+            std::fs::read_to_string("/proc/net/if_inet6").map_err(|err| Error::Ip(String::from("interfaces"), err.into()))?;
+        Ok(parse_if_inet6(&content, config))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = config;
+        Err(Error::Ip(String::from("interfaces"), String::from("Reading the interface addresses is only supported on Linux.")))
+    }
+}
+
+/// Parses the content of the `/proc/net/if_inet6` file and returns the global IPv6 addresses it contains. Each line of the
+/// file is made of six whitespace-separated columns: the address (32 hexadecimal digits), the interface index, the prefix
+/// length, the scope, the flags, and the interface name. Malformed lines are ignored.
+fn parse_if_inet6(content: &str, config: &IpConfig) -> Vec<Ipv6Addr> {
+    let mut addresses = Vec::new();
+
+    for line in content.lines() {
+        let columns: Vec<&str> = line.split_whitespace().collect();
+        if columns.len() < 6 || columns[0].len() != 32 {
+            continue;
+        }
+
+        // Parses the address, the scope, and the flags of the line, and skips it if any of them is malformed.
+        let address = match u128::from_str_radix(columns[0], 16) {
+            Ok(a) => Ipv6Addr::from(a),
+            Err(_) => continue,
+        };
+        let (scope, flags) = match (u32::from_str_radix(columns[3], 16), u32::from_str_radix(columns[4], 16)) {
+            (Ok(s), Ok(f)) => (s, f),
+            _ => continue,
+        };
+
+        // Skips the addresses that are not global or that cannot be used yet.
+        if scope != RT_SCOPE_UNIVERSE || flags & IFA_F_TENTATIVE != 0 {
+            continue;
+        }
+
+        // Skips the temporary addresses generated by the privacy extensions if stable addresses are preferred.
+        if config.prefer_stable_ipv6 && flags & (IFA_F_TEMPORARY | IFA_F_DEPRECATED) != 0 {
+            continue;
+        }
+
+        addresses.push(address);
+    }
+
+    addresses
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::testing;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::AtomicUsize;
+
+    /// Serves the body to every request received on a local port, and returns the URL of the server.
+    fn serve(body: &'static str) -> String {
+        testing::serve(move |_| ("200 OK", body))
+    }
+
+    /// Answers the first requests received on a local port with an error status, then serves the body to every following
+    /// request, and returns the URL of the server.
+    fn serve_flaky(failures: usize, body: &'static str) -> String {
+        let received = AtomicUsize::new(0);
+        testing::serve(move |_| match received.fetch_add(1, Ordering::SeqCst) < failures {
+            true => ("503 Service Unavailable", ""),
+            false => ("200 OK", body),
+        })
+    }
+
+    /// Serves the body on a local port after waiting for the delay, and returns the URL of the server.
+    fn serve_after(delay: Duration, body: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                std::thread::spawn(move || {
+                    let _ = stream.read(&mut [0; 1024]);
+                    std::thread::sleep(delay);
+                    let response =
+                        format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body);
+                    let _ = stream.write_all(response.as_bytes());
+                });
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn test_detect_all() {
+        let config = IpConfig {
+            ipv4_endpoints: vec![serve("not an address"), serve("0.0.0.0"), serve("203.0.113.7\n")],
+            ipv6_endpoints: vec![serve("203.0.113.7")],
+            ..IpConfig::default()
+        };
+
+        let result = detect_all(&config);
+        assert_eq!(result.ipv4.ok(), Some(Ipv4Addr::new(203, 0, 113, 7)));
+        assert_eq!(result.ipv4_source, Some(config.ipv4_endpoints[2].clone()));
+        assert!(result.ipv6.is_err());
+        assert_eq!(result.ipv6_source, None);
+
+        let config = IpConfig { ipv4_endpoints: Vec::new(), ipv6_endpoints: vec![serve("2001:db8::1")], ..IpConfig::default() };
+        let result = detect_all(&config);
+        assert!(result.ipv4.is_err());
+        assert_eq!(result.ipv6.ok(), Some("2001:db8::1".parse::<Ipv6Addr>().unwrap()));
+
+        // Ensures that the failure to build the HTTP client is kept as the source of the failures of both families.
+        let http = HttpConfig { headers: vec![(String::from("X Api Key"), String::new())], ..HttpConfig::default() };
+        let result = detect_all(&IpConfig { http, ..IpConfig::default() });
+        let source = |err: &Error| std::error::Error::source(err).and_then(|e| e.downcast_ref::<Error>()).map(Error::code);
+        assert_eq!(source(result.ipv4.as_ref().unwrap_err()), Some("E_HTTP_BUILD"));
+        assert_eq!(result.ipv6.unwrap_err().code(), "E_HTTP_BUILD");
+
+        // Ensures that the interface addresses are only read if the stable addresses are preferred, and that the temporary
+        // addresses and the unique local addresses are skipped in favor of the first stable global address.
+        let content = "\
+fd000000000000000000000000000001 02 40 00 80     eth0
+20010db80000000089abcdef01234567 02 40 00 01     eth0
+20010db800000000aaaaaaaaaaaaaaaa 02 40 00 21     eth0
+20010db8000000000211223344556677 02 40 00 80     eth0
+";
+        let read = |config: &IpConfig| Ok(parse_if_inet6(content, config));
+        assert_eq!(stable_ipv6(&IpConfig::default()), None);
+        assert_eq!(stable_ipv6_with(&IpConfig::default(), read), None);
+        let config = IpConfig { prefer_stable_ipv6: true, ..config };
+        let stable = "2001:db8::211:2233:4455:6677".parse::<Ipv6Addr>().unwrap();
+        assert_eq!(stable_ipv6_with(&config, read), Some((stable, String::from("interfaces"))));
+        let read = |_: &IpConfig| Err(Error::Ip(String::from("interfaces"), String::from("Unreadable.").into()));
+        assert_eq!(stable_ipv6_with(&config, read), None);
+    }
+
+    #[test]
+    fn test_is_public() {
+        for address in ["203.0.113.7", "100.128.0.1", "2001:db8::1", "2a00:1450:4007:80e::200e"] {
+            assert!(is_public(address.parse().unwrap()), "{} is not public.", address);
+        }
+        for address in ["0.0.0.0", "127.0.0.1", "10.1.2.3", "172.31.0.1", "192.168.1.1", "100.64.0.1", "169.254.0.1"] {
+            assert!(!is_public(address.parse().unwrap()), "{} is public.", address);
+        }
+        for address in ["::", "::1", "fd00::1", "fc12::1", "fe80::1"] {
+            assert!(!is_public(address.parse().unwrap()), "{} is public.", address);
+        }
+        assert_eq!(parse_address::<Ipv4Addr>(" 203.0.113.7\n"), Some(Ipv4Addr::new(203, 0, 113, 7)));
+        assert_eq!(parse_address::<Ipv4Addr>("10.0.0.1"), None);
+        assert_eq!(parse_address::<Ipv6Addr>("fe80::1"), None);
+    }
+
+    #[test]
+    fn test_parse_if_inet6() {
+        let content = "\
+00000000000000000000000000000001 01 80 10 80       lo
+fe80000000000000021122fffe334455 02 40 20 80     eth0
+20010db8000000000211 02 40 00 00     eth0
+20010db8000000000211223344556677 02 40 00 80     eth0
+20010db80000000089abcdef01234567 02 40 00 01     eth0
+20010db800000000aaaaaaaaaaaaaaaa 02 40 00 21     eth0
+20010db800000000bbbbbbbbbbbbbbbb 02 40 00 40     eth0
+";
+
+        let addresses = parse_if_inet6(content, &IpConfig { prefer_stable_ipv6: false, ..IpConfig::default() });
+        assert_eq!(addresses.len(), 3);
+        assert_eq!(addresses[0], "2001:db8::211:2233:4455:6677".parse::<Ipv6Addr>().unwrap());
+        assert_eq!(addresses[1], "2001:db8::89ab:cdef:123:4567".parse::<Ipv6Addr>().unwrap());
+        assert_eq!(addresses[2], "2001:db8::aaaa:aaaa:aaaa:aaaa".parse::<Ipv6Addr>().unwrap());
+
+        let addresses = parse_if_inet6(content, &IpConfig { prefer_stable_ipv6: true, ..IpConfig::default() });
+        assert_eq!(addresses.len(), 1);
+        assert_eq!(addresses[0], "2001:db8::211:2233:4455:6677".parse::<Ipv6Addr>().unwrap());
+
+        assert!(parse_if_inet6("", &IpConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn test_detect_retry() {
+        let client = HttpConfig::default().build_client().unwrap();
+
+        // Ensures that a single pass over the endpoints is made by default.
+        let config = IpConfig { ipv4_endpoints: vec![serve("invalid"), serve_flaky(1, "203.0.113.7")], ..IpConfig::default() };
+        assert!(detect::<Ipv4Addr>(&client, &config.ipv4_endpoints, &config).is_err());
+
+        // Ensures that the endpoints are rotated (A, B, A, B, A, B) until the attempt budget is exhausted.
+        let endpoints = vec![serve("invalid"), serve_flaky(2, "203.0.113.7")];
+        let config = IpConfig { max_attempts: Some(5), retry_backoff: Duration::ZERO, ..IpConfig::default() };
+        assert!(detect::<Ipv4Addr>(&client, &endpoints, &config).is_err());
+
+        let endpoints = vec![serve("invalid"), serve_flaky(2, "203.0.113.7")];
+        let config = IpConfig { max_attempts: Some(6), retry_backoff: Duration::ZERO, ..IpConfig::default() };
+        let (address, source) = detect::<Ipv4Addr>(&client, &endpoints, &config).unwrap();
+        assert_eq!(address, Ipv4Addr::new(203, 0, 113, 7));
+        assert_eq!(source, endpoints[1]);
+
+        let config = IpConfig { max_attempts: Some(3), ..IpConfig::default() };
+        assert!(detect::<Ipv4Addr>(&client, &[], &config).is_err());
+    }
+
+    #[test]
+    fn test_detect_race() {
+        let client = HttpConfig::default().build_client().unwrap();
+
+        // Ensures that the first valid public answer is used without waiting for the answers of the slower endpoints, whatever
+        // their order, and that the race ends once their requests are cancelled or time out.
+        let strategy = DetectionStrategy::Race { timeout: Duration::from_millis(500) };
+        let config = IpConfig { strategy, ..IpConfig::default() };
+        let endpoints = vec![
+            serve_after(Duration::from_secs(3), "198.51.100.1"),
+            serve("invalid"),
+            serve("192.168.1.20"),
+            serve_after(Duration::from_millis(100), "203.0.113.7"),
+        ];
+        let start = Instant::now();
+        let (address, source) = detect::<Ipv4Addr>(&client, &endpoints, &config).unwrap();
+        assert_eq!((address, source), (Ipv4Addr::new(203, 0, 113, 7), endpoints[3].clone()));
+        assert!(start.elapsed() < Duration::from_secs(2));
+
+        // Ensures that the detection fails once every endpoint failed or timed out.
+        let config = IpConfig { strategy: DetectionStrategy::Race { timeout: Duration::from_millis(200) }, ..config };
+        let endpoints = vec![serve_after(Duration::from_secs(3), "198.51.100.1"), serve("invalid"), serve("100.64.0.1")];
+        let start = Instant::now();
+        assert!(detect::<Ipv4Addr>(&client, &endpoints, &config).is_err_and(|e| !e.is_cancelled()));
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(detect::<Ipv4Addr>(&client, &[], &config).is_err());
+
+        // Ensures that a cancelled race sends no request.
+        let token = CancellationToken::new();
+        token.cancel();
+        let config = IpConfig { cancel: Some(token), ..config };
+        assert!(detect::<Ipv4Addr>(&client, &[serve("203.0.113.7")], &config).is_err_and(|e| e.is_cancelled()));
+    }
+
+    #[test]
+    fn test_detect_cancel() {
+        // Ensures that a cancelled detection sends no request, and fails with a cancellation.
+        let token = CancellationToken::new();
+        token.cancel();
+        let config = IpConfig { ipv6_endpoints: vec![serve("2001:db8::1")], cancel: Some(token), ..IpConfig::default() };
+        let result = detect_all(&config);
+        assert!(result.ipv4.is_err_and(|e| e.is_cancelled()));
+        assert!(result.ipv6.is_err_and(|e| e.is_cancelled()));
+
+        // Ensures that the wait between the passes over the endpoints ends early once the token is cancelled.
+        let token = CancellationToken::new();
+        let canceller = token.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            canceller.cancel();
+        });
+        let config = IpConfig {
+            max_attempts: Some(2),
+            retry_backoff: Duration::from_secs(60),
+            cancel: Some(token),
+            ..IpConfig::default()
+        };
+        let start = Instant::now();
+        let client = HttpConfig::default().build_client().unwrap();
+        assert!(detect::<Ipv4Addr>(&client, &[serve("invalid")], &config).is_err_and(|e| e.is_cancelled()));
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_upnp() {
+        let answer = "HTTP/1.1 200 OK\r\nCACHE-CONTROL: max-age=120\r\nLocation: http://192.168.1.1:5000/rootDesc.xml\r\n\r\n";
+        assert_eq!(parse_ssdp_location(answer), Some(String::from("http://192.168.1.1:5000/rootDesc.xml")));
+        assert_eq!(parse_ssdp_location("HTTP/1.1 200 OK\r\n\r\n"), None);
+
+        let description = "<root><device><serviceList>\
+            <service><serviceType>urn:schemas-upnp-org:service:Layer3Forwarding:1</serviceType>\
+            <controlURL>/ctl/L3F</controlURL></service>\
+            <service><serviceType>urn:schemas-upnp-org:service:WANIPConnection:1</serviceType>\
+            <controlURL>/ctl/IPConn</controlURL></service>\
+            </serviceList></device></root>";
+        assert_eq!(
+            find_control_url(description, "http://192.168.1.1:5000/rootDesc.xml"),
+            Some((
+                String::from("urn:schemas-upnp-org:service:WANIPConnection:1"),
+                String::from("http://192.168.1.1:5000/ctl/IPConn")
+            ))
+        );
+        let description = description.replace("<root>", "<root><URLBase>http://192.168.1.254:49000/</URLBase>");
+        assert_eq!(
+            find_control_url(&description, "http://192.168.1.1:5000/rootDesc.xml").map(|(_, url)| url),
+            Some(String::from("http://192.168.1.254:49000/ctl/IPConn"))
+        );
+        assert_eq!(find_control_url("<root></root>", "http://192.168.1.1:5000/rootDesc.xml"), None);
+
+        let location = "http://192.168.1.1:5000/rootDesc.xml";
+        let response = "<s:Envelope><s:Body><u:GetExternalIPAddressResponse>\
+            <NewExternalIPAddress>203.0.113.7</NewExternalIPAddress>\
+            </u:GetExternalIPAddressResponse></s:Body></s:Envelope>";
+        assert_eq!(external_address(response, location).unwrap(), Ipv4Addr::new(203, 0, 113, 7));
+        assert!(external_address("<NewExternalIPAddress>0.0.0.0</NewExternalIPAddress>", location).is_err());
+        assert!(external_address("<root></root>", location).is_err());
+
+        // Ensures that the gateways that are not the edge of the network are rejected.
+        for address in ["10.0.0.2", "172.16.4.1", "192.168.0.10", "100.64.12.3", "100.127.255.254", "169.254.1.1"] {
+            let response = format!("<NewExternalIPAddress>{}</NewExternalIPAddress>", address);
+            assert!(matches!(
+                external_address(&response, location),
+                Err(Error::Ip(method, cause)) if method == "upnp" && cause.to_string().contains("not the edge")
+            ));
+        }
+        assert!(external_address("<NewExternalIPAddress>100.128.0.1</NewExternalIPAddress>", location).is_ok());
+    }
+
+    #[test]
+    fn test_detect_from_metadata() {
+        // Serves a fake instance metadata service, which only answers the requests carrying the headers of the platform.
+        let url = testing::serve(|request| {
+            let request = request.to_lowercase();
+            let body = if request.starts_with("put /latest/api/token") {
+                "SOME_TOKEN"
+            } else if request.contains("/latest/meta-data/public-ipv4")
+                && request.contains("x-aws-ec2-metadata-token: some_token")
+            {
+                "203.0.113.7"
+            } else if request.contains("/external-ip") && request.contains("metadata-flavor: google") {
+                "203.0.113.8"
+            } else if request.contains("api-version=2021-02-01") && request.contains("metadata: true") {
+                "127.0.0.1"
+            } else {
+                ""
+            };
+            (if body.is_empty() { "404 Not Found" } else { "200 OK" }, body)
+        });
+
+        assert_eq!(detect_from_metadata_at(&url, CloudProvider::Aws).ok(), Some(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7))));
+        assert_eq!(detect_from_metadata_at(&url, CloudProvider::Gcp).ok(), Some(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 8))));
+        let err = detect_from_metadata_at(&url, CloudProvider::Azure).unwrap_err();
+        assert_eq!(err.code(), "E_DETECT_METADATA");
+        assert!(format!("{:?}", err).contains("Azure"));
+    }
+}
@@ -0,0 +1,68 @@
+// Copyright 2025 Amon Rayfa.
+// SPDX-License-Identifier: Apache-2.0.
+
+//! This module contains the function used to set up the program's logging from the environment.
+
+use crate::error::api::{Error, Result};
+use tracing_subscriber::EnvFilter;
+
+/// The environment variable holding the logging level (or a full filter directive, e.g. `wapi=debug`).
+pub const LEVEL_VARIABLE: &str = "WAPI_LOG";
+
+/// The environment variable holding the logging format (`text` or `json`).
+pub const FORMAT_VARIABLE: &str = "WAPI_LOG_FORMAT";
+
+/// The format of the log events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// Compact, human-readable lines.
+    #[default]
+    Text,
+    /// One JSON object per event, meant to be ingested by log pipelines.
+    Json,
+}
+
+/// Sets up the global logger from the environment. The level is read from `WAPI_LOG` (defaults to `info`) and the format from
+/// `WAPI_LOG_FORMAT` (`text` or `json`, defaults to `text`). An `Error::Log` is returned if either variable is invalid, or if
+/// a global logger was already set up.
+pub fn init_logging() -> Result<()> {
+    let filter = match std::env::var(LEVEL_VARIABLE) {
+        Ok(level) => EnvFilter::try_new(&level).map_err(|err| Error::Log(String::from("level"), err.to_string().into()))?,
+        Err(_) => EnvFilter::new("info"),
+    };
+    let format = parse_format(std::env::var(FORMAT_VARIABLE).ok().as_deref())?;
+
+    let builder = tracing_subscriber::fmt().with_env_filter(filter).with_writer(std::io::stderr);
+    match format {
+        LogFormat::Text => builder.compact().try_init(),
+        LogFormat::Json => builder.json().try_init(),
+    }
+    .map_err(|err| Error::Log(String::from("init"), err.to_string().into()))
+}
+
+/// Parses the value of the `WAPI_LOG_FORMAT` environment variable (case-insensitively). An error is returned if the value is
+/// neither `text` nor `json`.
+fn parse_format(value: Option<&str>) -> Result<LogFormat> {
+    match value.map(|v| v.trim().to_lowercase()).as_deref() {
+        None | Some("") | Some("text") => Ok(LogFormat::Text),
+        Some("json") => Ok(LogFormat::Json),
+        Some(v) => Err(Error::Log(
+            String::from("format"),
+            format!("The log format \"{}\" is not supported (expected \"text\" or \"json\").", v).into(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!(parse_format(None).unwrap(), LogFormat::Text);
+        assert_eq!(parse_format(Some("")).unwrap(), LogFormat::Text);
+        assert_eq!(parse_format(Some("text")).unwrap(), LogFormat::Text);
+        assert_eq!(parse_format(Some(" JSON ")).unwrap(), LogFormat::Json);
+        assert!(parse_format(Some("xml")).is_err());
+    }
+}
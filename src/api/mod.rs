@@ -2,4 +2,23 @@
 // SPDX-License-Identifier: Apache-2.0.
 
 pub mod cache;
+pub mod cancel;
+pub mod client;
+pub mod drift;
+pub mod format;
+pub mod ip;
+pub mod logging;
+pub mod observer;
+pub mod provider;
+#[cfg(any(test, feature = "testing"))]
+pub mod replay;
 pub mod request;
+pub(crate) mod sync;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod update;
+#[cfg(feature = "async")]
+pub mod watch;
+
+pub use drift::check_drift;
+pub use logging::init_logging;
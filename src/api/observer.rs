@@ -0,0 +1,29 @@
+// Copyright 2025 Amon Rayfa.
+// SPDX-License-Identifier: Apache-2.0.
+
+//! This module contains the trait implemented by the observers of a [`Client`](wapi::Client), which let an embedding
+//! application react to the events of its operations without parsing the logs.
+
+use crate::api::ip::DetectionResult;
+use crate::api::update::{RecordReport, UpdateReport};
+use crate::error::api::Error;
+
+/// The trait implemented by the observers registered on a [`Client`](wapi::Client) (see
+/// [`ClientBuilder::observer`](wapi::api::client::ClientBuilder)). The methods are called synchronously on the thread running
+/// the operation, in the order of the events, and do nothing by default so that an observer only implements the events it is
+/// interested in. They should return quickly, since they delay the operation.
+pub trait WapiObserver: Send + Sync {
+    /// Called once the public IP addresses were detected, whether the detection succeeded or not.
+    fn on_detection(&self, _detection: &DetectionResult) {}
+
+    /// Called for the outcome of each record of an update run, in the order of the DNS providers and their records in the
+    /// cache.
+    fn on_record_outcome(&self, _record: &RecordReport) {}
+
+    /// Called once an update run is complete and the cache was saved.
+    fn on_run_complete(&self, _report: &UpdateReport) {}
+
+    /// Called when an operation of the client fails (e.g. because the cache cannot be loaded, or because the run was
+    /// aborted), before the error is returned.
+    fn on_error(&self, _error: &Error) {}
+}
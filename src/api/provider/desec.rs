@@ -0,0 +1,339 @@
+// Copyright 2025 Amon Rayfa.
+// SPDX-License-Identifier: Apache-2.0.
+
+//! This module contains the backend of [deSEC](https://desec.io), a free DNS hosting service with DNSSEC whose `dedyn.io`
+//! domains are commonly used for dynamic DNS. The records can be updated either through its dynamic DNS endpoint or through
+//! its REST API.
+
+use crate::api::cache::{RecordConfig, RecordType};
+use crate::api::provider::{self, Capabilities, Provider, UpdateStatus};
+use crate::api::request::{self, HttpConfig};
+use crate::error::api::{Error, ProviderError, Result};
+use reqwest::blocking::{Client, RequestBuilder};
+use serde::Deserialize;
+use std::net::IpAddr;
+use std::time::Duration;
+
+/// The dynamic DNS endpoint of deSEC.
+pub const UPDATE_URL: &str = "https://update.dedyn.io/";
+
+/// The base URL of the REST API of deSEC.
+pub const API_URL: &str = "https://desec.io/api/v1";
+
+/// The minimum time to live accepted by deSEC for the records, in seconds. The records created through the REST API with a
+/// lower time to live are created with this one instead.
+pub const MIN_TTL: u32 = 3600;
+
+/// The minimum amount of time between two updates sent to the dynamic DNS endpoint of deSEC, which throttles the clients that
+/// update the same domain more often.
+pub const MIN_UPDATE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The way the records are updated by the deSEC backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DesecMode {
+    /// The records are updated through the dynamic DNS endpoint (the default).
+    Update,
+    /// The records are updated (or created) through the REST API, which does not send an update when the record already
+    /// points to the address.
+    Rest,
+}
+
+/// The RRset returned by the REST API of deSEC (only the fields used by the backend are kept).
+#[derive(Debug, Deserialize)]
+struct RRset {
+    #[serde(default)]
+    records: Vec<String>,
+}
+
+/// The backend of deSEC. The token of the account is stored in the `api_key` of the DNS provider (the `secret_api_key` is not
+/// used), and the `mode` parameter of the DNS provider selects the dynamic DNS endpoint (`update`, the default) or the REST
+/// API (`rest`).
+pub struct DesecProvider {
+    token: String,
+    mode: DesecMode,
+    update_url: String,
+    api_url: String,
+    http: HttpConfig,
+    client: Client,
+}
+
+impl DesecProvider {
+    /// Creates a new deSEC backend. An error is returned if the mode is neither `update` nor `rest`, or if the HTTP client
+    /// cannot be built.
+    pub fn new(token: &str, mode: Option<&str>, http: &HttpConfig) -> Result<DesecProvider> {
+        let mode = match mode.map(|m| m.trim().to_lowercase()).as_deref() {
+            None | Some("update") => DesecMode::Update,
+            Some("rest") => DesecMode::Rest,
+            Some(m) => {
+                return Err(Error::Provider(
+                    String::from("build"),
+                    format!("The deSEC mode \"{}\" is not supported (expected \"update\" or \"rest\").", m).into(),
+                ))
+            }
+        };
+
+        Ok(DesecProvider {
+            token: token.to_string(),
+            mode,
+            update_url: UPDATE_URL.to_string(),
+            api_url: API_URL.to_string(),
+            http: http.clone(),
+            client: http.build_client()?,
+        })
+    }
+
+    /// Sends the requests to the origin (e.g. a replay server) instead of the ones of deSEC, if it is set.
+    pub(crate) fn with_origin(mut self, origin: Option<&str>) -> DesecProvider {
+        if let Some(origin) = origin {
+            self.update_url = request::rebase(&self.update_url, origin);
+            self.api_url = request::rebase(&self.api_url, origin);
+        }
+        self
+    }
+
+    /// Returns the way the records are updated by the backend.
+    pub fn mode(&self) -> DesecMode {
+        self.mode
+    }
+
+    /// Sends the request with the token of the account, and returns the status and the body of the response.
+    fn send(&self, request: RequestBuilder) -> std::result::Result<(u16, String), ProviderError> {
+        let response = self
+            .http
+            .apply_headers(request)
+            .header("Authorization", format!("Token {}", self.token))
+            .send()
+            .map_err(|err| ProviderError::Http(format!("{:?}", self.http.map_error(err))))?;
+
+        provider::read_response(self.id(), response, &self.http, &[&self.token])
+    }
+
+    /// Updates the record through the dynamic DNS endpoint. The address of the other family is preserved.
+    fn update_via_endpoint(&self, record: &RecordConfig, address: IpAddr) -> std::result::Result<UpdateStatus, ProviderError> {
+        let hostname = record.fqdn();
+        let (status, body) = self.send(self.client.get(update_url(&self.update_url, &hostname, address)))?;
+        check_status(&hostname, status, &body)?;
+
+        match body.split_whitespace().next().unwrap_or_default() {
+            "good" => Ok(UpdateStatus::Updated),
+            "nochg" => Ok(UpdateStatus::Unchanged),
+            _ => Err(ProviderError::Api(format!("The server refused to update \"{}\" (\"{}\").", hostname, body.trim()))),
+        }
+    }
+
+    /// Updates the record through the REST API so that its RRset holds exactly the addresses, creating the RRset if it does
+    /// not exist yet. Nothing is sent if the RRset already holds them.
+    fn update_via_api(&self, record: &RecordConfig, addresses: &[IpAddr]) -> std::result::Result<UpdateStatus, ProviderError> {
+        let hostname = record.fqdn();
+        let url = rrset_url(&self.api_url, record);
+        let values: Vec<String> = addresses.iter().map(IpAddr::to_string).collect();
+        let (status, body) = self.send(self.client.get(&url))?;
+
+        // Creates the RRset if it does not exist.
+        if status == 404 {
+            if record.ttl < MIN_TTL {
+                tracing::debug!(record = %hostname, ttl = record.ttl, min_ttl = MIN_TTL, "The TTL was raised to its minimum.");
+            }
+            let rrset = serde_json::json!({
+                "subname": record.subdomain,
+                "type": record.record_type.to_string(),
+                "ttl": record.ttl.max(MIN_TTL),
+                "records": values,
+            });
+            let (status, body) =
+                self.send(self.client.post(format!("{}/domains/{}/rrsets/", self.api_url, record.domain)).json(&rrset))?;
+            check_status(&hostname, status, &body)?;
+            return Ok(UpdateStatus::Updated);
+        }
+        check_status(&hostname, status, &body)?;
+
+        // Replaces the records of the RRset if they do not hold the addresses.
+        let rrset: RRset = serde_json::from_str(&body).map_err(|err| {
+            ProviderError::Api(format!("The server returned an invalid RRset for \"{}\" ({}).", hostname, err))
+        })?;
+        if points_to(&rrset.records, addresses) {
+            return Ok(UpdateStatus::Unchanged);
+        }
+        let (status, body) = self.send(self.client.patch(&url).json(&serde_json::json!({ "records": values })))?;
+        check_status(&hostname, status, &body)?;
+
+        Ok(UpdateStatus::Updated)
+    }
+}
+
+impl Provider for DesecProvider {
+    fn id(&self) -> &str {
+        "desec"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        match self.mode {
+            DesecMode::Update => Capabilities { min_update_interval: Some(MIN_UPDATE_INTERVAL) },
+            DesecMode::Rest => Capabilities::default(),
+        }
+    }
+
+    fn update_record(&self, record: &RecordConfig, address: IpAddr) -> std::result::Result<UpdateStatus, ProviderError> {
+        match self.mode {
+            DesecMode::Update => self.update_via_endpoint(record, address),
+            DesecMode::Rest => self.update_via_api(record, &[address]),
+        }
+    }
+
+    fn update_record_set(
+        &self,
+        record: &RecordConfig,
+        addresses: &[IpAddr],
+    ) -> std::result::Result<UpdateStatus, ProviderError> {
+        match (self.mode, addresses) {
+            (DesecMode::Update, [address]) => self.update_via_endpoint(record, *address),
+            (DesecMode::Update, _) => Err(ProviderError::Api(format!(
+                "The dynamic DNS endpoint of deSEC cannot set several addresses for \"{}\" (use the \"rest\" mode instead).",
+                record.fqdn()
+            ))),
+            (DesecMode::Rest, _) => self.update_via_api(record, addresses),
+        }
+    }
+}
+
+/// Returns the URL of the dynamic DNS endpoint updating the hostname to the address, and preserving the address of the other
+/// family.
+fn update_url(base_url: &str, hostname: &str, address: IpAddr) -> String {
+    match address {
+        IpAddr::V4(a) => format!("{}?hostname={}&myipv4={}&myipv6=preserve", base_url, hostname, a),
+        IpAddr::V6(a) => format!("{}?hostname={}&myipv4=preserve&myipv6={}", base_url, hostname, a),
+    }
+}
+
+/// Returns the URL of the RRset of the record in the REST API. The zone apex is addressed with the `@` subname.
+fn rrset_url(base_url: &str, record: &RecordConfig) -> String {
+    let subname = match record.subdomain.is_empty() {
+        true => "@",
+        false => record.subdomain.as_str(),
+    };
+    let record_type = match record.record_type {
+        RecordType::A => "A",
+        RecordType::Aaaa => "AAAA",
+        RecordType::Txt => "TXT",
+        RecordType::Cname => "CNAME",
+    };
+
+    format!("{}/domains/{}/rrsets/{}/{}/", base_url, record.domain, subname, record_type)
+}
+
+/// Returns `true` if the records of an RRset point to all the addresses and to nothing else.
+fn points_to(records: &[String], addresses: &[IpAddr]) -> bool {
+    let parsed: Vec<Option<IpAddr>> = records.iter().map(|r| r.parse::<IpAddr>().ok()).collect();
+    !records.is_empty()
+        && parsed.iter().all(|a| a.is_some_and(|a| addresses.contains(&a)))
+        && addresses.iter().all(|a| parsed.contains(&Some(*a)))
+}
+
+/// Converts the failure statuses returned by deSEC into errors.
+fn check_status(hostname: &str, status: u16, body: &str) -> std::result::Result<(), ProviderError> {
+    match status {
+        200..=299 => Ok(()),
+        401 | 403 => Err(ProviderError::Auth(format!(
+            "The server rejected the token used to update \"{}\" (HTTP {}: \"{}\").",
+            hostname,
+            status,
+            body.trim()
+        ))),
+        404 => Err(ProviderError::NoHost(format!(
+            "The server does not manage \"{}\" for this account (HTTP {}: \"{}\").",
+            hostname,
+            status,
+            body.trim()
+        ))),
+        429 => Err(ProviderError::Api(format!(
+            "The server throttled the update of \"{}\" (HTTP {}: \"{}\").",
+            hostname,
+            status,
+            body.trim()
+        ))),
+        _ => Err(ProviderError::Api(format!(
+            "The server refused to update \"{}\" (HTTP {}: \"{}\").",
+            hostname,
+            status,
+            body.trim()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::testing;
+
+    #[test]
+    fn test_desec() {
+        let http = HttpConfig::default();
+        let provider = DesecProvider::new("SOME_TOKEN", None, &http).unwrap();
+        assert_eq!(provider.id(), "desec");
+        assert_eq!(provider.mode(), DesecMode::Update);
+        assert_eq!(provider.capabilities().min_update_interval, Some(Duration::from_secs(60)));
+        let provider = DesecProvider::new("SOME_TOKEN", Some(" REST "), &http).unwrap();
+        assert_eq!(provider.mode(), DesecMode::Rest);
+        assert_eq!(provider.capabilities().min_update_interval, None);
+        assert!(DesecProvider::new("SOME_TOKEN", Some("soap"), &http).is_err());
+
+        assert_eq!(
+            update_url(UPDATE_URL, "home.dedyn.io", "203.0.113.7".parse().unwrap()),
+            "https://update.dedyn.io/?hostname=home.dedyn.io&myipv4=203.0.113.7&myipv6=preserve"
+        );
+        assert_eq!(
+            update_url(UPDATE_URL, "home.dedyn.io", "2001:db8::1".parse().unwrap()),
+            "https://update.dedyn.io/?hostname=home.dedyn.io&myipv4=preserve&myipv6=2001:db8::1"
+        );
+        assert_eq!(
+            rrset_url(API_URL, &RecordConfig::new("example.dedyn.io", "nas", RecordType::Aaaa, 3600)),
+            "https://desec.io/api/v1/domains/example.dedyn.io/rrsets/nas/AAAA/"
+        );
+        assert_eq!(
+            rrset_url(API_URL, &RecordConfig::new("example.dedyn.io", "", RecordType::A, 3600)),
+            "https://desec.io/api/v1/domains/example.dedyn.io/rrsets/@/A/"
+        );
+
+        let address = "203.0.113.7".parse().unwrap();
+        assert!(points_to(&[String::from("203.0.113.7")], &[address]));
+        assert!(!points_to(&[String::from("203.0.113.7"), String::from("198.51.100.1")], &[address]));
+        assert!(!points_to(&[], &[address]));
+        let addresses = [address, "198.51.100.1".parse().unwrap()];
+        assert!(points_to(&[String::from("198.51.100.1"), String::from("203.0.113.7")], &addresses));
+        assert!(!points_to(&[String::from("203.0.113.7")], &addresses));
+
+        // Ensures that the dynamic DNS endpoint refuses to set several addresses before sending anything.
+        let record = RecordConfig::new("example.dedyn.io", "", RecordType::A, 3600);
+        let provider = DesecProvider::new("SOME_TOKEN", None, &http).unwrap();
+        assert!(matches!(provider.update_record_set(&record, &addresses), Err(ProviderError::Api(_))));
+
+        assert!(check_status("a.dedyn.io", 200, "good").is_ok());
+        assert!(check_status("a.dedyn.io", 201, "").is_ok());
+        assert!(matches!(check_status("a.dedyn.io", 401, "Invalid token."), Err(ProviderError::Auth(_))));
+        assert!(matches!(check_status("a.dedyn.io", 404, "Not found."), Err(ProviderError::NoHost(_))));
+        assert!(matches!(check_status("a.dedyn.io", 429, "Throttled."), Err(ProviderError::Api(_))));
+        assert!(matches!(check_status("a.dedyn.io", 500, ""), Err(ProviderError::Api(_))));
+    }
+
+    #[test]
+    fn test_retry_after() {
+        // Serves a throttling failure, with a `Retry-After` header only for the requests updating the IPv4 address.
+        let url = testing::serve(|request| match request.contains("myipv4=203.0.113.7") {
+            true => ("429 Too Many Requests\r\nRetry-After: 120", "Throttled for SOME_TOKEN."),
+            false => ("429 Too Many Requests", "Throttled for SOME_TOKEN."),
+        });
+
+        // Ensures that the delay requested by deSEC reaches the callers, and that the token is scrubbed from the failure.
+        let provider = DesecProvider::new("SOME_TOKEN", None, &HttpConfig::default()).unwrap().with_origin(Some(&url));
+        let record = RecordConfig::new("example.dedyn.io", "home", RecordType::A, 3600);
+        let err = Error::from(provider.update_record(&record, "203.0.113.7".parse().unwrap()).unwrap_err());
+        assert!(err.is_retryable());
+        assert_eq!(err.retry_after(), Some(Duration::from_secs(120)));
+        assert!(matches!(&err, Error::ProviderResponse { status: Some(429), body, .. } if !body.contains("SOME_TOKEN")));
+
+        // Ensures that a throttling failure without the header is mapped by the backend, with no requested delay.
+        let err = provider.update_record(&record, "2001:db8::1".parse().unwrap()).unwrap_err();
+        assert!(matches!(err, ProviderError::Api(_)));
+        assert_eq!(Error::from(err).retry_after(), None);
+    }
+}
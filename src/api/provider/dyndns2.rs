@@ -0,0 +1,139 @@
+// Copyright 2025 Amon Rayfa.
+// SPDX-License-Identifier: Apache-2.0.
+
+//! This module contains the backend of the generic DynDNS2 protocol (`GET /nic/update?hostname=&myip=` with HTTP Basic
+//! authentication), which is implemented by many DDNS services and routers.
+
+use crate::api::cache::RecordConfig;
+use crate::api::provider::{self, Capabilities, Provider, UpdateStatus};
+use crate::api::request::{self, HttpConfig};
+use crate::error::api::{ProviderError, Result};
+use reqwest::blocking::Client;
+use std::net::IpAddr;
+use std::time::Duration;
+
+/// The update URL used when none is configured. The `{hostname}` and `{ip}` placeholders are replaced with the fully qualified
+/// domain name of the record and the new address respectively.
+pub const DEFAULT_UPDATE_URL: &str = "https://members.dyndns.org/nic/update?hostname={hostname}&myip={ip}";
+
+/// The minimum amount of time between two updates sent to a DynDNS2 server. The DynDNS2 services block the clients that send
+/// repeated updates (especially `nochg` ones) in a short period of time.
+pub const MIN_UPDATE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// The backend of the generic DynDNS2 protocol. The `api_key` and `secret_api_key` of the DNS provider are used as the
+/// username and the password of the HTTP Basic authentication, and the update URL can be set with the `update_url` parameter
+/// of the DNS provider.
+pub struct DynDns2Provider {
+    username: String,
+    password: String,
+    update_url: String,
+    http: HttpConfig,
+    client: Client,
+}
+
+impl DynDns2Provider {
+    /// Creates a new DynDNS2 backend. If no update URL is given, [`DEFAULT_UPDATE_URL`](wapi::api::provider::dyndns2) is used.
+    /// An error is returned if the HTTP client cannot be built.
+    pub fn new(username: &str, password: &str, update_url: Option<&str>, http: &HttpConfig) -> Result<DynDns2Provider> {
+        Ok(DynDns2Provider {
+            username: username.to_string(),
+            password: password.to_string(),
+            update_url: update_url.unwrap_or(DEFAULT_UPDATE_URL).to_string(),
+            http: http.clone(),
+            client: http.build_client()?,
+        })
+    }
+
+    /// Sends the requests to the origin (e.g. a replay server) instead of the one of the update URL, if it is set.
+    pub(crate) fn with_origin(mut self, origin: Option<&str>) -> DynDns2Provider {
+        if let Some(origin) = origin {
+            self.update_url = request::rebase(&self.update_url, origin);
+        }
+        self
+    }
+
+    /// Returns the update URL with its placeholders replaced by the hostname and the address.
+    fn url(&self, hostname: &str, address: IpAddr) -> String {
+        self.update_url.replace("{hostname}", hostname).replace("{ip}", &address.to_string())
+    }
+}
+
+impl Provider for DynDns2Provider {
+    fn id(&self) -> &str {
+        "dyndns2"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { min_update_interval: Some(MIN_UPDATE_INTERVAL) }
+    }
+
+    fn update_record(&self, record: &RecordConfig, address: IpAddr) -> std::result::Result<UpdateStatus, ProviderError> {
+        let hostname = record.fqdn();
+
+        // Sends the update request and returns an error if the server cannot be reached.
+        let request = self.client.get(self.url(&hostname, address)).basic_auth(&self.username, Some(&self.password));
+        let response = self
+            .http
+            .apply_headers(request)
+            .send()
+            .map_err(|err| ProviderError::Http(format!("{:?}", self.http.map_error(err))))?;
+        let (_, body) = provider::read_response(self.id(), response, &self.http, &[&self.username, &self.password])?;
+
+        parse_response(&hostname, &body)
+    }
+}
+
+/// Parses the response of a DynDNS2 server into the outcome of the update. The response starts with a return code (`good`,
+/// `nochg`, `badauth`, `nohost`, ...), which may be followed by the address of the record.
+fn parse_response(hostname: &str, response: &str) -> std::result::Result<UpdateStatus, ProviderError> {
+    match response.split_whitespace().next().unwrap_or_default() {
+        "good" => Ok(UpdateStatus::Updated),
+        "nochg" => Ok(UpdateStatus::Unchanged),
+        "badauth" | "!donator" => Err(ProviderError::Auth(format!(
+            "The server rejected the credentials used to update \"{}\" (\"{}\").",
+            hostname,
+            response.trim()
+        ))),
+        "nohost" | "notfqdn" | "!yours" => Err(ProviderError::NoHost(format!(
+            "The server does not manage \"{}\" for this account (\"{}\").",
+            hostname,
+            response.trim()
+        ))),
+        _ => Err(ProviderError::Api(format!("The server refused to update \"{}\" (\"{}\").", hostname, response.trim()))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::cache::RecordType;
+
+    #[test]
+    fn test_dyndns2() {
+        let http = HttpConfig::default();
+        let provider = DynDns2Provider::new("SOME_USERNAME", "SOME_PASSWORD", None, &http).unwrap();
+        assert_eq!(provider.id(), "dyndns2");
+        assert_eq!(provider.capabilities().min_update_interval, Some(Duration::from_secs(600)));
+        assert!(provider.safety_warnings(&RecordConfig::new("example.com", "", RecordType::A, 300)).is_empty());
+        assert_eq!(
+            provider.url("home.example.com", "203.0.113.7".parse().unwrap()),
+            "https://members.dyndns.org/nic/update?hostname=home.example.com&myip=203.0.113.7"
+        );
+
+        let provider =
+            DynDns2Provider::new("u", "p", Some("https://dyn.example.net/update?h={hostname}&a={ip}"), &http).unwrap();
+        let record = RecordConfig::new("example.com", "nas", RecordType::Aaaa, 300);
+        assert_eq!(
+            provider.url(&record.fqdn(), "2001:db8::1".parse().unwrap()),
+            "https://dyn.example.net/update?h=nas.example.com&a=2001:db8::1"
+        );
+
+        assert!(matches!(parse_response("a.example.com", "good 203.0.113.7\n"), Ok(UpdateStatus::Updated)));
+        assert!(matches!(parse_response("a.example.com", "nochg 203.0.113.7"), Ok(UpdateStatus::Unchanged)));
+        assert!(matches!(parse_response("a.example.com", "badauth"), Err(ProviderError::Auth(_))));
+        assert!(matches!(parse_response("a.example.com", "nohost"), Err(ProviderError::NoHost(_))));
+        assert!(matches!(parse_response("a.example.com", "notfqdn"), Err(ProviderError::NoHost(_))));
+        assert!(matches!(parse_response("a.example.com", "abuse"), Err(ProviderError::Api(_))));
+        assert!(matches!(parse_response("a.example.com", ""), Err(ProviderError::Api(_))));
+    }
+}
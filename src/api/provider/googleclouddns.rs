@@ -0,0 +1,597 @@
+// Copyright 2025 Amon Rayfa.
+// SPDX-License-Identifier: Apache-2.0.
+
+//! This module contains the backend of [Google Cloud DNS](https://cloud.google.com/dns), whose managed zones are managed
+//! through the record sets and the changes of its REST API, with the OAuth2 access tokens minted from the key of a service
+//! account.
+
+use crate::api::cache::RecordConfig;
+use crate::api::provider::{self, Provider, UpdateStatus};
+use crate::api::request::{self, HttpConfig};
+use crate::api::sync::lock;
+use crate::error::api::{Error, ProviderError, Result};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use reqwest::blocking::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// The base URL of the REST API of Google Cloud DNS.
+pub const API_URL: &str = "https://dns.googleapis.com/dns/v1";
+
+/// The URL of the OAuth2 token exchange of Google, used when the key of the service account does not set its own.
+pub const TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+
+/// The OAuth2 scope of the access tokens, which grants the read and write access to the managed zones.
+pub const SCOPE: &str = "https://www.googleapis.com/auth/ndev.clouddns.readwrite";
+
+/// The lifetime requested for the access tokens (the maximum allowed by Google).
+const TOKEN_LIFETIME: Duration = Duration::from_secs(3600);
+
+/// The margin before the expiration of an access token after which a new one is minted, so that a token never expires
+/// during an update.
+const TOKEN_MARGIN: Duration = Duration::from_secs(60);
+
+/// The key of a service account, as downloaded from the Google Cloud console (only the fields used by the backend are kept).
+#[derive(Debug, Clone, Deserialize)]
+struct ServiceAccountKey {
+    client_email: String,
+    private_key: String,
+    #[serde(default)]
+    project_id: String,
+    token_uri: Option<String>,
+}
+
+/// The credentials the access tokens of the backend come from.
+enum Credentials {
+    /// The key of a service account, from which the access tokens are minted.
+    Key(ServiceAccountKey),
+    /// An access token obtained elsewhere, which is sent as is.
+    Token(String),
+}
+
+/// The claims of the JWT exchanged for an access token.
+#[derive(Debug, Serialize)]
+struct Claims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: u64,
+    exp: u64,
+}
+
+/// The access token returned by the OAuth2 token exchange of Google.
+#[derive(Debug, Deserialize)]
+struct AccessToken {
+    access_token: String,
+    expires_in: Option<u64>,
+}
+
+/// The list of managed zones returned by the REST API of Google Cloud DNS.
+#[derive(Debug, Deserialize)]
+struct ManagedZones {
+    #[serde(default = "Vec::new", rename = "managedZones")]
+    managed_zones: Vec<ManagedZone>,
+}
+
+/// A managed zone returned by the REST API of Google Cloud DNS (only the fields used by the backend are kept).
+#[derive(Debug, Deserialize)]
+struct ManagedZone {
+    name: String,
+    #[serde(rename = "dnsName")]
+    dns_name: String,
+    #[serde(default)]
+    visibility: String,
+}
+
+/// The list of record sets of a managed zone returned by the REST API of Google Cloud DNS.
+#[derive(Debug, Deserialize)]
+struct ResourceRecordSets {
+    #[serde(default = "Vec::new")]
+    rrsets: Vec<ResourceRecordSet>,
+}
+
+/// A record set of a managed zone, as returned and accepted by the REST API of Google Cloud DNS.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ResourceRecordSet {
+    name: String,
+    #[serde(rename = "type")]
+    record_type: String,
+    ttl: u32,
+    #[serde(default)]
+    rrdatas: Vec<String>,
+}
+
+/// The body of the failures returned by Google, which is either `{"error": {"code": ..., "message": ..., "status": ...}}` for
+/// the REST API or `{"error": ..., "error_description": ...}` for the OAuth2 token exchange.
+#[derive(Debug, Deserialize)]
+struct Failure {
+    error: FailureError,
+    error_description: Option<String>,
+}
+
+/// The error of a [`Failure`].
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum FailureError {
+    Status {
+        #[serde(default)]
+        message: String,
+        #[serde(default)]
+        status: String,
+    },
+    Code(String),
+}
+
+/// The backend of Google Cloud DNS. The key of the service account (with the DNS Administrator role on the project) is stored
+/// in the `api_key` of the DNS provider, either as the path of its JSON file or as its JSON content (the `secret_api_key` is
+/// not used). The records are updated in the project of the key, unless the `project` parameter of the DNS provider is set,
+/// and must already exist in their managed zone, since the backend only updates them. If the `access_token` parameter is set
+/// (e.g. to a token printed by `gcloud auth print-access-token`), it is sent instead of the tokens minted from the key, which
+/// is then not read (so the `project` parameter is required).
+pub struct GoogleCloudDnsProvider {
+    credentials: Credentials,
+    project: String,
+    api_url: String,
+    token_url: String,
+    token: Mutex<Option<(String, Instant)>>,
+    zones: Mutex<BTreeMap<String, Option<String>>>,
+    http: HttpConfig,
+    client: Client,
+}
+
+impl GoogleCloudDnsProvider {
+    /// Creates a new Google Cloud DNS backend from the key of a service account (its path or its JSON content), or from the
+    /// access token if it is set, in the project if it is set or in the one of the key otherwise. An error is returned if the
+    /// key cannot be read, if no project is known, or if the HTTP client cannot be built.
+    pub fn new(
+        credential: &str,
+        project: Option<&str>,
+        access_token: Option<&str>,
+        http: &HttpConfig,
+    ) -> Result<GoogleCloudDnsProvider> {
+        let credentials = match access_token.map(str::trim).filter(|t| !t.is_empty()) {
+            Some(token) => Credentials::Token(token.to_string()),
+            None => Credentials::Key(read_key(credential)?),
+        };
+        let project = match (project, &credentials) {
+            (Some(project), _) => project.trim().to_string(),
+            (None, Credentials::Key(key)) => key.project_id.clone(),
+            (None, Credentials::Token(_)) => String::new(),
+        };
+        if project.is_empty() {
+            return Err(Error::Provider(
+                String::from("build"),
+                "No project is known (the key has no `project_id` or is not read), so the `project` parameter must be set."
+                    .into(),
+            ));
+        }
+
+        Ok(GoogleCloudDnsProvider {
+            token_url: match &credentials {
+                Credentials::Key(key) => key.token_uri.clone().unwrap_or_else(|| TOKEN_URL.to_string()),
+                Credentials::Token(_) => TOKEN_URL.to_string(),
+            },
+            credentials,
+            project,
+            api_url: API_URL.to_string(),
+            token: Mutex::new(None),
+            zones: Mutex::new(BTreeMap::new()),
+            http: http.clone(),
+            client: http.build_client()?,
+        })
+    }
+
+    /// Sends the requests to the origin (e.g. a replay server) instead of the API of Google, if it is set. The access tokens
+    /// are still minted by the OAuth2 server of Google, since the replayed fixtures use the `access_token` parameter instead.
+    pub(crate) fn with_origin(mut self, origin: Option<&str>) -> GoogleCloudDnsProvider {
+        if let Some(origin) = origin {
+            self.api_url = request::rebase(&self.api_url, origin);
+        }
+        self
+    }
+
+    /// Returns an access token, which is either the one of the `access_token` parameter, or one minted from the key of the
+    /// service account and reused until it is about to expire. A new one is minted by signing a JWT with the private key of
+    /// the service account and exchanging it with Google. An error is returned if the key or the exchange is rejected.
+    fn access_token(&self, hostname: &str) -> std::result::Result<String, ProviderError> {
+        let key = match &self.credentials {
+            Credentials::Key(key) => key,
+            Credentials::Token(token) => return Ok(token.clone()),
+        };
+        let mut token = lock(&self.token);
+        if let Some((value, expiration)) = token.as_ref() {
+            if Instant::now() + TOKEN_MARGIN < *expiration {
+                return Ok(value.clone());
+            }
+        }
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let claims = Claims {
+            iss: &key.client_email,
+            scope: SCOPE,
+            aud: key.token_uri.as_deref().unwrap_or(TOKEN_URL),
+            iat: now,
+            exp: now + TOKEN_LIFETIME.as_secs(),
+        };
+        let assertion = EncodingKey::from_rsa_pem(key.private_key.as_bytes())
+            .and_then(|key| jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &key))
+            .map_err(|err| {
+                ProviderError::Auth(format!(
+                    "The private key of the service account \"{}\" cannot sign the requests for \"{}\" ({}).",
+                    key.client_email, hostname, err
+                ))
+            })?;
+
+        let form = [("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"), ("assertion", assertion.as_str())];
+        let (status, body) = self.send(self.client.post(&self.token_url).form(&form))?;
+        if !(200..=299).contains(&status) {
+            return Err(ProviderError::Auth(format!(
+                "The server refused to mint an access token for the service account \"{}\" (HTTP {}: \"{}\").",
+                key.client_email,
+                status,
+                failure_message(&body)
+            )));
+        }
+        let minted: AccessToken = serde_json::from_str(&body).map_err(|err| {
+            ProviderError::Auth(format!("The server returned an invalid access token for \"{}\" ({}).", hostname, err))
+        })?;
+
+        let lifetime = minted.expires_in.map_or(TOKEN_LIFETIME, Duration::from_secs);
+        *token = Some((minted.access_token.clone(), Instant::now() + lifetime));
+        Ok(minted.access_token)
+    }
+
+    /// Sends the request, and returns the status and the body of the response.
+    fn send(&self, request: RequestBuilder) -> std::result::Result<(u16, String), ProviderError> {
+        let response = self
+            .http
+            .apply_headers(request)
+            .send()
+            .map_err(|err| ProviderError::Http(format!("{:?}", self.http.map_error(err))))?;
+
+        provider::read_response(self.id(), response, &self.http, &[])
+    }
+
+    /// Sends the request to the REST API with an access token, and returns the body of the response. An error is returned if
+    /// the request fails.
+    fn call(&self, request: RequestBuilder, hostname: &str) -> std::result::Result<String, ProviderError> {
+        let (status, body) = self.send(request.bearer_auth(self.access_token(hostname)?))?;
+        check_status(hostname, status, &body)?;
+
+        Ok(body)
+    }
+
+    /// Looks up the name of the public managed zone the record belongs to, and remembers it (see
+    /// [`Provider::zone_ids`](wapi::api::provider::Provider)). An error is returned if the project does not have one.
+    fn find_zone(&self, record: &RecordConfig) -> std::result::Result<String, ProviderError> {
+        let hostname = record.fqdn();
+        let dns_name = absolute_name(&record.domain);
+        let url = format!("{}/projects/{}/managedZones", self.api_url, self.project);
+        let body = self.call(self.client.get(url).query(&[("dnsName", dns_name.as_str())]), &hostname)?;
+        let zones: ManagedZones = serde_json::from_str(&body).map_err(|err| {
+            ProviderError::Api(format!("The server returned an invalid list of managed zones for \"{}\" ({}).", hostname, err))
+        })?;
+
+        let zone =
+            zones.managed_zones.into_iter().find(|z| z.dns_name.eq_ignore_ascii_case(&dns_name) && z.visibility != "private");
+        lock(&self.zones).insert(record.domain.clone(), zone.as_ref().map(|z| z.name.clone()));
+        zone.map(|z| z.name).ok_or_else(|| {
+            ProviderError::NoHost(format!(
+                "The domain \"{}\" of \"{}\" has no public managed zone in the project \"{}\".",
+                record.domain, hostname, self.project
+            ))
+        })
+    }
+
+    /// Returns the record sets of the record in the managed zone, or `None` if the managed zone does not exist. An error is
+    /// returned if the request fails.
+    fn record_sets(
+        &self,
+        zone: &str,
+        record: &RecordConfig,
+    ) -> std::result::Result<Option<Vec<ResourceRecordSet>>, ProviderError> {
+        let hostname = record.fqdn();
+        let name = absolute_name(&hostname);
+        let record_type = record.record_type.to_string();
+        let url = format!("{}/projects/{}/managedZones/{}/rrsets", self.api_url, self.project, zone);
+        let request = self.client.get(url).query(&[("name", name.as_str()), ("type", record_type.as_str())]);
+        let (status, body) = self.send(request.bearer_auth(self.access_token(&hostname)?))?;
+        if status == 404 {
+            return Ok(None);
+        }
+        check_status(&hostname, status, &body)?;
+
+        let rrsets: ResourceRecordSets = serde_json::from_str(&body).map_err(|err| {
+            ProviderError::Api(format!("The server returned an invalid list of record sets for \"{}\" ({}).", hostname, err))
+        })?;
+        Ok(Some(rrsets.rrsets))
+    }
+}
+
+impl Provider for GoogleCloudDnsProvider {
+    fn id(&self) -> &str {
+        "googleclouddns"
+    }
+
+    fn update_record(&self, record: &RecordConfig, address: IpAddr) -> std::result::Result<UpdateStatus, ProviderError> {
+        self.update_record_set(record, &[address])
+    }
+
+    fn update_record_set(
+        &self,
+        record: &RecordConfig,
+        addresses: &[IpAddr],
+    ) -> std::result::Result<UpdateStatus, ProviderError> {
+        let hostname = record.fqdn();
+        let name = absolute_name(&hostname);
+        let record_type = record.record_type.to_string();
+
+        // Uses the managed zone cached in the record if any, and looks it up again if Google reports it as unknown (e.g. if it
+        // was recreated under another name).
+        let mut found = None;
+        if let Some(zone) = record.zone_id.as_ref().filter(|z| !z.is_empty()) {
+            found = self.record_sets(zone, record)?.map(|rrsets| (zone.clone(), rrsets));
+            if found.is_none() {
+                lock(&self.zones).insert(record.domain.clone(), None);
+            }
+        }
+        let (zone, rrsets) = match found {
+            Some(found) => found,
+            None => {
+                let zone = self.find_zone(record)?;
+                let rrsets = self.record_sets(&zone, record)?.ok_or_else(|| {
+                    ProviderError::NoHost(format!("The managed zone \"{}\" of \"{}\" does not exist.", zone, hostname))
+                })?;
+                (zone, rrsets)
+            }
+        };
+        let zone_url = format!("{}/projects/{}/managedZones/{}", self.api_url, self.project, zone);
+        let existing = rrsets
+            .into_iter()
+            .find(|r| r.record_type == record_type && r.name.eq_ignore_ascii_case(&name))
+            .ok_or_else(|| {
+                ProviderError::NoHost(format!(
+                    "No {} record of \"{}\" exists in the managed zone \"{}\" (it must be created first).",
+                    record_type, hostname, zone
+                ))
+            })?;
+
+        // Updates the record set only if it does not already hold exactly the addresses with the configured TTL.
+        if holds(&existing, addresses) && existing.ttl == record.ttl {
+            return Ok(UpdateStatus::Unchanged);
+        }
+
+        // Replaces the record set in a single change, which Google applies atomically.
+        let addition = ResourceRecordSet {
+            rrdatas: addresses.iter().map(|a| a.to_string()).collect(),
+            ttl: record.ttl,
+            ..existing.clone()
+        };
+        let change = serde_json::json!({ "deletions": [existing], "additions": [addition] });
+        self.call(self.client.post(format!("{}/changes", zone_url)).json(&change), &hostname)?;
+
+        Ok(UpdateStatus::Updated)
+    }
+
+    fn zone_ids(&self) -> Vec<(String, Option<String>)> {
+        lock(&self.zones).iter().map(|(d, z)| (d.clone(), z.clone())).collect()
+    }
+}
+
+/// Reads the key of a service account, which is either its JSON content or the path of its JSON file.
+fn read_key(credential: &str) -> Result<ServiceAccountKey> {
+    let credential = credential.trim();
+    let content = match credential.starts_with('{') {
+        true => credential.to_string(),
+        false => std::fs::read_to_string(Path::new(credential)).map_err(|err| {
+            Error::Provider(
+                String::from("credential"),
+                format!("The key of the service account cannot be read from {:?} ({}).", credential, err).into(),
+            )
+        })?,
+    };
+
+    serde_json::from_str(&content).map_err(|err| {
+        Error::Provider(String::from("credential"), format!("The key of the service account is invalid ({}).", err).into())
+    })
+}
+
+/// Returns the name with the trailing dot used by Google Cloud DNS for the absolute names.
+fn absolute_name(name: &str) -> String {
+    format!("{}.", name.trim_end_matches('.'))
+}
+
+/// Returns `true` if the record set holds exactly the addresses, in any order.
+fn holds(rrset: &ResourceRecordSet, addresses: &[IpAddr]) -> bool {
+    let mut current: Vec<IpAddr> = rrset.rrdatas.iter().filter_map(|d| d.parse().ok()).collect();
+    let mut expected = addresses.to_vec();
+    current.sort();
+    expected.sort();
+    current.len() == rrset.rrdatas.len() && current == expected
+}
+
+/// Returns the message of a failure returned by Google, or the raw body if it cannot be parsed.
+fn failure_message(body: &str) -> String {
+    match serde_json::from_str::<Failure>(body) {
+        Ok(Failure { error: FailureError::Status { message, status }, .. }) if !status.is_empty() => {
+            format!("{}: {}", status, message)
+        }
+        Ok(Failure { error: FailureError::Status { message, .. }, .. }) => message,
+        Ok(Failure { error: FailureError::Code(code), error_description: Some(description) }) => {
+            format!("{}: {}", code, description)
+        }
+        Ok(Failure { error: FailureError::Code(code), .. }) => code,
+        _ => body.trim().to_string(),
+    }
+}
+
+/// Converts the failure statuses returned by the REST API of Google Cloud DNS into errors. The rejected access tokens are
+/// reported as authentication failures, and the other failures as refusals along with the structured error of Google.
+fn check_status(hostname: &str, status: u16, body: &str) -> std::result::Result<(), ProviderError> {
+    match status {
+        200..=299 => Ok(()),
+        401 => Err(ProviderError::Auth(format!(
+            "The server rejected the access token used to update \"{}\" (HTTP {}: \"{}\").",
+            hostname,
+            status,
+            failure_message(body)
+        ))),
+        _ => Err(ProviderError::Api(format!(
+            "The server refused to update \"{}\" (HTTP {}: \"{}\").",
+            hostname,
+            status,
+            failure_message(body)
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::cache::RecordType;
+    use crate::api::testing;
+
+    /// The key of a service account whose private key is not a valid PEM, so that it can be read but not sign any request.
+    const KEY: &str = r#"{
+        "type": "service_account",
+        "project_id": "some-project",
+        "private_key": "SOME_PRIVATE_KEY",
+        "client_email": "wapi@some-project.iam.gserviceaccount.com",
+        "token_uri": "https://oauth2.googleapis.com/token"
+    }"#;
+
+    /// Serves a simulated REST API of Google Cloud DNS on a local port, managing `example.com` (with the managed zone
+    /// `example-com` of the project `some-project`, which replaced `old-example-com`) whose `home` A record points to
+    /// 203.0.113.7 with a TTL of 300, and returns its URL.
+    fn serve_api() -> String {
+        testing::serve(|request| {
+            let zone = "/projects/some-project/managedZones";
+            if !request.contains("Bearer SOME_TOKEN") {
+                (
+                    "401 Unauthorized",
+                    r#"{"error": {"code": 401, "message": "Invalid Credentials", "status": "UNAUTHENTICATED"}}"#,
+                )
+            } else if request.starts_with(&format!("GET {}?dnsName=example.com. ", zone)) {
+                (
+                    "200 OK",
+                    r#"{"managedZones": [
+                        {"name": "example-com-private", "dnsName": "example.com.", "visibility": "private"},
+                        {"name": "example-com", "dnsName": "example.com.", "visibility": "public"}
+                    ]}"#,
+                )
+            } else if request.starts_with(&format!("GET {}?", zone)) {
+                ("200 OK", r#"{"managedZones": []}"#)
+            } else if request.starts_with(&format!("GET {}/example-com/rrsets?name=home.example.com.&type=A ", zone)) {
+                (
+                    "200 OK",
+                    r#"{"rrsets": [{"name": "home.example.com.", "type": "A", "ttl": 300, "rrdatas": ["203.0.113.7"]}]}"#,
+                )
+            } else if request.starts_with(&format!("GET {}/example-com/rrsets?", zone)) {
+                ("200 OK", r#"{"rrsets": []}"#)
+            } else if request.starts_with(&format!("GET {}/old-example-com/", zone)) {
+                (
+                    "404 Not Found",
+                    r#"{"error": {"code": 404, "message": "The managed zone does not exist.", "status": "NOT_FOUND"}}"#,
+                )
+            } else if request.starts_with(&format!("POST {}/example-com/changes ", zone)) {
+                ("200 OK", r#"{"id": "1", "status": "pending"}"#)
+            } else {
+                ("403 Forbidden", r#"{"error": {"code": 403, "message": "Forbidden", "status": "PERMISSION_DENIED"}}"#)
+            }
+        })
+    }
+
+    #[test]
+    fn test_googleclouddns() {
+        let http = HttpConfig::default();
+        let mut provider = GoogleCloudDnsProvider::new(KEY, None, None, &http).unwrap();
+        assert_eq!(provider.id(), "googleclouddns");
+        assert_eq!((provider.project.as_str(), provider.token_url.as_str()), ("some-project", TOKEN_URL));
+        assert_eq!(GoogleCloudDnsProvider::new(KEY, Some("other-project"), None, &http).unwrap().project, "other-project");
+
+        // Ensures that the key is read from its path as well, and that the invalid keys are rejected.
+        let path = std::env::temp_dir().join(format!("wapi-googleclouddns-{}.json", std::process::id()));
+        std::fs::write(&path, KEY).unwrap();
+        assert!(GoogleCloudDnsProvider::new(&path.to_string_lossy(), None, None, &http).is_ok());
+        std::fs::remove_file(&path).unwrap();
+        assert!(GoogleCloudDnsProvider::new(&path.to_string_lossy(), None, None, &http).is_err());
+        assert!(GoogleCloudDnsProvider::new(r#"{"client_email": "wapi@some-project.iam"}"#, None, None, &http).is_err());
+        let no_project = r#"{"client_email": "wapi@some-project.iam", "private_key": "SOME_PRIVATE_KEY"}"#;
+        assert!(GoogleCloudDnsProvider::new(no_project, None, None, &http).is_err());
+        assert!(GoogleCloudDnsProvider::new(no_project, Some("some-project"), None, &http).is_ok());
+        assert!(GoogleCloudDnsProvider::new("<api_key>", Some("some-project"), Some("SOME_TOKEN"), &http).is_ok());
+        assert!(GoogleCloudDnsProvider::new("<api_key>", None, Some("SOME_TOKEN"), &http).is_err());
+
+        assert_eq!(absolute_name("example.com"), "example.com.");
+        assert_eq!(absolute_name("example.com."), "example.com.");
+        let rrset = ResourceRecordSet {
+            name: String::from("home.example.com."),
+            record_type: String::from("A"),
+            ttl: 300,
+            rrdatas: vec![String::from("203.0.113.7"), String::from("203.0.113.8")],
+        };
+        assert!(holds(&rrset, &["203.0.113.8".parse().unwrap(), "203.0.113.7".parse().unwrap()]));
+        assert!(!holds(&rrset, &["203.0.113.7".parse().unwrap()]));
+
+        assert_eq!(
+            failure_message(
+                r#"{"error": {"code": 404, "message": "The managed zone does not exist.", "status": "NOT_FOUND"}}"#
+            ),
+            "NOT_FOUND: The managed zone does not exist."
+        );
+        assert_eq!(
+            failure_message(r#"{"error": "invalid_grant", "error_description": "Invalid JWT Signature."}"#),
+            "invalid_grant: Invalid JWT Signature."
+        );
+        assert_eq!(failure_message(" Bad Gateway "), "Bad Gateway");
+        assert!(check_status("home.example.com", 200, "{}").is_ok());
+        assert!(matches!(check_status("home.example.com", 401, ""), Err(ProviderError::Auth(_))));
+        assert!(matches!(check_status("home.example.com", 403, ""), Err(ProviderError::Api(_))));
+        assert!(matches!(check_status("home.example.com", 412, ""), Err(ProviderError::Api(_))));
+
+        // Ensures that the private key must be valid to mint an access token.
+        let home = RecordConfig::new("example.com", "home", RecordType::A, 300);
+        assert!(matches!(provider.update_record(&home, "203.0.113.8".parse().unwrap()), Err(ProviderError::Auth(_))));
+
+        // Ensures that the record sets are looked up and only changed if they do not hold the addresses with the TTL.
+        provider.api_url = serve_api();
+        *provider.token.lock().unwrap() = Some((String::from("SOME_TOKEN"), Instant::now() + TOKEN_LIFETIME));
+        assert!(matches!(provider.update_record(&home, "203.0.113.7".parse().unwrap()), Ok(UpdateStatus::Unchanged)));
+        assert!(matches!(provider.update_record(&home, "203.0.113.8".parse().unwrap()), Ok(UpdateStatus::Updated)));
+        let addresses = ["203.0.113.7".parse().unwrap(), "203.0.113.9".parse().unwrap()];
+        assert!(matches!(provider.update_record_set(&home, &addresses), Ok(UpdateStatus::Updated)));
+        let home_ttl = RecordConfig::new("example.com", "home", RecordType::A, 600);
+        assert!(matches!(provider.update_record(&home_ttl, "203.0.113.7".parse().unwrap()), Ok(UpdateStatus::Updated)));
+        let nas = RecordConfig::new("example.com", "nas", RecordType::A, 300);
+        assert!(matches!(provider.update_record(&nas, "203.0.113.8".parse().unwrap()), Err(ProviderError::NoHost(_))));
+        let other = RecordConfig::new("example.org", "home", RecordType::A, 300);
+        assert!(matches!(provider.update_record(&other, "203.0.113.8".parse().unwrap()), Err(ProviderError::NoHost(_))));
+        assert_eq!(
+            provider.zone_ids(),
+            vec![(String::from("example.com"), Some(String::from("example-com"))), (String::from("example.org"), None)]
+        );
+
+        // Ensures that the cached managed zone is used, and that it is looked up again if Google reports it as unknown.
+        provider.zones.lock().unwrap().clear();
+        let cached_zone = RecordConfig { zone_id: Some(String::from("example-com")), ..home.clone() };
+        assert!(matches!(provider.update_record(&cached_zone, "203.0.113.7".parse().unwrap()), Ok(UpdateStatus::Unchanged)));
+        assert!(provider.zone_ids().is_empty());
+        let old_zone = RecordConfig { zone_id: Some(String::from("old-example-com")), ..home.clone() };
+        assert!(matches!(provider.update_record(&old_zone, "203.0.113.7".parse().unwrap()), Ok(UpdateStatus::Unchanged)));
+        assert_eq!(provider.zone_ids(), vec![(String::from("example.com"), Some(String::from("example-com")))]);
+        let unknown_zone = RecordConfig { zone_id: Some(String::from("some-zone")), ..home.clone() };
+        let result = provider.update_record(&unknown_zone, "203.0.113.8".parse().unwrap());
+        assert!(matches!(result, Err(ProviderError::Api(e)) if e.contains("PERMISSION_DENIED")));
+        *provider.token.lock().unwrap() = Some((String::from("SOME_OTHER_TOKEN"), Instant::now() + TOKEN_LIFETIME));
+        assert!(matches!(provider.update_record(&home, "203.0.113.8".parse().unwrap()), Err(ProviderError::Auth(_))));
+
+        // Ensures that the access token of the parameter is sent instead of the minted ones.
+        let mut provider = GoogleCloudDnsProvider::new("<api_key>", Some("some-project"), Some("SOME_TOKEN"), &http).unwrap();
+        provider.api_url = serve_api();
+        assert!(matches!(provider.update_record(&home, "203.0.113.7".parse().unwrap()), Ok(UpdateStatus::Unchanged)));
+    }
+}
@@ -0,0 +1,418 @@
+// Copyright 2025 Amon Rayfa.
+// SPDX-License-Identifier: Apache-2.0.
+
+//! This module contains the backend of [Hetzner](https://www.hetzner.com), whose DNS Console is managed through the zones and
+//! the records of its REST API with an API token.
+
+use crate::api::cache::{RecordConfig, RecordType};
+use crate::api::provider::{self, Provider, UpdateStatus};
+use crate::api::request::{self, HttpConfig};
+use crate::api::sync::lock;
+use crate::error::api::{ProviderError, Result};
+use reqwest::blocking::{Client, RequestBuilder};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+
+/// The base URL of the REST API of the Hetzner DNS Console.
+pub const API_URL: &str = "https://dns.hetzner.com/api/v1";
+
+/// The list of zones returned by the REST API of Hetzner.
+#[derive(Debug, Deserialize)]
+struct Zones {
+    #[serde(default = "Vec::new")]
+    zones: Vec<Zone>,
+}
+
+/// A zone returned by the REST API of Hetzner (only the fields used by the backend are kept).
+#[derive(Debug, Deserialize)]
+struct Zone {
+    id: String,
+    name: String,
+}
+
+/// The list of records of a zone returned by the REST API of Hetzner.
+#[derive(Debug, Deserialize)]
+struct Records {
+    #[serde(default = "Vec::new")]
+    records: Vec<ZoneRecord>,
+}
+
+/// A record of a zone returned by the REST API of Hetzner (only the fields used by the backend are kept).
+#[derive(Debug, Deserialize)]
+struct ZoneRecord {
+    id: String,
+    #[serde(rename = "type")]
+    record_type: String,
+    name: String,
+    #[serde(default)]
+    value: String,
+    ttl: Option<u32>,
+}
+
+/// The body of the failures returned by the REST API of Hetzner, which is either `{"error": {"message": ...}}` or
+/// `{"message": ...}` depending on the endpoint.
+#[derive(Debug, Deserialize)]
+struct Failure {
+    error: Option<FailureError>,
+    message: Option<String>,
+}
+
+/// The error of a [`Failure`].
+#[derive(Debug, Deserialize)]
+struct FailureError {
+    #[serde(default)]
+    message: String,
+}
+
+/// The backend of Hetzner. The API token of the DNS Console is stored in the `api_key` of the DNS provider (the
+/// `secret_api_key` is not used). The records must already exist in the DNS Console, since the backend only updates them.
+pub struct HetznerProvider {
+    token: String,
+    api_url: String,
+    zones: Mutex<BTreeMap<String, Option<String>>>,
+    inspected: Mutex<Option<(RecordConfig, String, Vec<ZoneRecord>)>>,
+    http: HttpConfig,
+    client: Client,
+}
+
+impl HetznerProvider {
+    /// Creates a new Hetzner backend. An error is returned if the HTTP client cannot be built.
+    pub fn new(token: &str, http: &HttpConfig) -> Result<HetznerProvider> {
+        Ok(HetznerProvider {
+            token: token.to_string(),
+            api_url: API_URL.to_string(),
+            zones: Mutex::new(BTreeMap::new()),
+            inspected: Mutex::new(None),
+            http: http.clone(),
+            client: http.build_client()?,
+        })
+    }
+
+    /// Sends the requests to the origin (e.g. a replay server) instead of the one of Hetzner, if it is set.
+    pub(crate) fn with_origin(mut self, origin: Option<&str>) -> HetznerProvider {
+        if let Some(origin) = origin {
+            self.api_url = request::rebase(&self.api_url, origin);
+        }
+        self
+    }
+
+    /// Sends the request with the API token, and returns the status and the body of the response.
+    fn send(&self, request: RequestBuilder) -> std::result::Result<(u16, String), ProviderError> {
+        let response = self
+            .http
+            .apply_headers(request)
+            .header("Auth-API-Token", &self.token)
+            .send()
+            .map_err(|err| ProviderError::Http(format!("{:?}", self.http.map_error(err))))?;
+
+        provider::read_response(self.id(), response, &self.http, &[&self.token])
+    }
+
+    /// Looks up the ID of the zone the record belongs to, and remembers it (see
+    /// [`Provider::zone_ids`](wapi::api::provider::Provider)). An error is returned if the account does not manage it.
+    fn find_zone(&self, record: &RecordConfig) -> std::result::Result<String, ProviderError> {
+        let hostname = record.fqdn();
+        let request = self.client.get(format!("{}/zones", self.api_url)).query(&[("name", record.domain.as_str())]);
+        let (status, body) = self.send(request)?;
+        if status == 404 {
+            // Remembers that the zone is unknown, so that a cached zone ID of the domain is cleared.
+            lock(&self.zones).insert(record.domain.clone(), None);
+        }
+        check_status(&hostname, status, &body)?;
+        let zones: Zones = serde_json::from_str(&body).map_err(|err| {
+            ProviderError::Api(format!("The server returned an invalid list of zones for \"{}\" ({}).", hostname, err))
+        })?;
+
+        let zone_id = zones.zones.into_iter().find(|z| z.name.eq_ignore_ascii_case(&record.domain)).map(|z| z.id);
+        lock(&self.zones).insert(record.domain.clone(), zone_id.clone());
+        zone_id.ok_or_else(|| {
+            ProviderError::NoHost(format!(
+                "The zone \"{}\" of \"{}\" is not managed by this Hetzner account.",
+                record.domain, hostname
+            ))
+        })
+    }
+
+    /// Returns the records of the zone, or `None` if the zone does not exist. An error is returned if the request fails.
+    fn records(&self, zone_id: &str, record: &RecordConfig) -> std::result::Result<Option<Vec<ZoneRecord>>, ProviderError> {
+        let hostname = record.fqdn();
+        let request = self.client.get(format!("{}/records", self.api_url)).query(&[("zone_id", zone_id)]);
+        let (status, body) = self.send(request)?;
+        if status == 404 {
+            return Ok(None);
+        }
+        check_status(&hostname, status, &body)?;
+        let records: Records = serde_json::from_str(&body).map_err(|err| {
+            ProviderError::Api(format!("The server returned an invalid list of records for \"{}\" ({}).", hostname, err))
+        })?;
+
+        Ok(Some(records.records))
+    }
+
+    /// Returns the ID and the records of the zone the record belongs to. The zone ID cached in the record is used if any, and
+    /// looked up again if Hetzner reports it as unknown (e.g. if the zone was recreated).
+    fn zone_records(&self, record: &RecordConfig) -> std::result::Result<(String, Vec<ZoneRecord>), ProviderError> {
+        if let Some(zone_id) = record.zone_id.as_ref().filter(|z| !z.is_empty()) {
+            match self.records(zone_id, record)? {
+                Some(records) => return Ok((zone_id.clone(), records)),
+                None => {
+                    lock(&self.zones).insert(record.domain.clone(), None);
+                }
+            }
+        }
+        let zone_id = self.find_zone(record)?;
+        let records = self.records(&zone_id, record)?.ok_or_else(|| {
+            ProviderError::NoHost(format!("The zone \"{}\" of \"{}\" does not exist.", record.domain, record.fqdn()))
+        })?;
+
+        Ok((zone_id, records))
+    }
+}
+
+impl Provider for HetznerProvider {
+    fn id(&self) -> &str {
+        "hetzner"
+    }
+
+    fn safety_warnings(&self, record: &RecordConfig) -> Vec<String> {
+        // The zone is inspected on a best-effort basis, since the update reports the errors of the DNS provider itself.
+        let (zone_id, records) = match self.zone_records(record) {
+            Ok(zone) => zone,
+            Err(e) => {
+                tracing::debug!(record = %record.fqdn(), error = ?e, "The zone of the record could not be inspected.");
+                return Vec::new();
+            }
+        };
+        let warnings = conflicts(&records, record)
+            .iter()
+            .map(|r| {
+                format!(
+                    "The {} record of \"{}\" conflicts with its {} record (\"{}\"), since a CNAME record cannot coexist with \
+                     other records of the same name.",
+                    record.record_type,
+                    record.fqdn(),
+                    r.record_type,
+                    r.value
+                )
+            })
+            .collect();
+
+        // Keeps the records of the zone for the update that follows the inspection, so that they are not fetched twice.
+        *lock(&self.inspected) = Some((record.clone(), zone_id, records));
+        warnings
+    }
+
+    fn update_record(&self, record: &RecordConfig, address: IpAddr) -> std::result::Result<UpdateStatus, ProviderError> {
+        let hostname = record.fqdn();
+        let (zone_id, records) = match lock(&self.inspected).take() {
+            Some((inspected, zone_id, records)) if inspected == *record => (zone_id, records),
+            _ => self.zone_records(record)?,
+        };
+        let existing = find_record(&records, record).ok_or_else(|| {
+            ProviderError::NoHost(format!(
+                "No {} record of \"{}\" exists in the Hetzner DNS Console (it must be created first).",
+                record.record_type, hostname
+            ))
+        })?;
+
+        // Updates the record only if it does not already point to the address with the configured TTL.
+        if existing.value.parse::<IpAddr>().is_ok_and(|a| a == address) && existing.ttl == Some(record.ttl) {
+            return Ok(UpdateStatus::Unchanged);
+        }
+        let url = format!("{}/records/{}", self.api_url, existing.id);
+        let update = serde_json::json!({
+            "zone_id": zone_id,
+            "type": existing.record_type,
+            "name": existing.name,
+            "value": address.to_string(),
+            "ttl": record.ttl,
+        });
+        let (status, body) = self.send(self.client.put(url).json(&update))?;
+        check_status(&hostname, status, &body)?;
+
+        Ok(UpdateStatus::Updated)
+    }
+
+    fn zone_ids(&self) -> Vec<(String, Option<String>)> {
+        lock(&self.zones).iter().map(|(d, z)| (d.clone(), z.clone())).collect()
+    }
+}
+
+/// Returns the record of the zone matching the subdomain and the type of the record configuration. The apex of the zone is
+/// named `@` by Hetzner.
+fn find_record<'a>(records: &'a [ZoneRecord], record: &RecordConfig) -> Option<&'a ZoneRecord> {
+    let name = if record.subdomain.is_empty() { "@" } else { record.subdomain.as_str() };
+    records.iter().find(|r| r.record_type == record.record_type.to_string() && r.name.eq_ignore_ascii_case(name))
+}
+
+/// Returns the records of the zone that cannot coexist with the record configuration: a CNAME record of the same name, or any
+/// other record of the same name if the record configuration is itself a CNAME record.
+fn conflicts<'a>(records: &'a [ZoneRecord], record: &RecordConfig) -> Vec<&'a ZoneRecord> {
+    let name = if record.subdomain.is_empty() { "@" } else { record.subdomain.as_str() };
+    let record_type = record.record_type.to_string();
+    records
+        .iter()
+        .filter(|r| r.name.eq_ignore_ascii_case(name) && r.record_type != record_type)
+        .filter(|r| record.record_type == RecordType::Cname || r.record_type == "CNAME")
+        .collect()
+}
+
+/// Returns the message of a failure returned by Hetzner, or the raw body if it cannot be parsed.
+fn failure_message(body: &str) -> String {
+    match serde_json::from_str::<Failure>(body) {
+        Ok(Failure { error: Some(error), .. }) if !error.message.is_empty() => error.message,
+        Ok(Failure { message: Some(message), .. }) => message,
+        _ => body.trim().to_string(),
+    }
+}
+
+/// Converts the failure statuses returned by Hetzner into errors.
+fn check_status(hostname: &str, status: u16, body: &str) -> std::result::Result<(), ProviderError> {
+    match status {
+        200..=299 => Ok(()),
+        401 | 403 => Err(ProviderError::Auth(format!(
+            "The server rejected the API token used to update \"{}\" (HTTP {}: \"{}\").",
+            hostname,
+            status,
+            failure_message(body)
+        ))),
+        404 => Err(ProviderError::NoHost(format!(
+            "The server does not manage \"{}\" for this account (HTTP {}: \"{}\").",
+            hostname,
+            status,
+            failure_message(body)
+        ))),
+        _ => Err(ProviderError::Api(format!(
+            "The server refused to update \"{}\" (HTTP {}: \"{}\").",
+            hostname,
+            status,
+            failure_message(body)
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::testing;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// The number of lists of records served by [`serve_api`].
+    static RECORD_LISTS: AtomicUsize = AtomicUsize::new(0);
+
+    /// Serves a simulated REST API of Hetzner on a local port, managing `example.com` (with the ID `z1`) whose `home` A record
+    /// (with the ID `r7`) points to 203.0.113.7 with a TTL of 300, whose `www` name is a CNAME record, and whose apex has a CAA
+    /// record, and returns its URL.
+    fn serve_api() -> String {
+        testing::serve(|request| {
+            if !request.to_ascii_lowercase().contains("auth-api-token: some_token") {
+                ("401 Unauthorized", r#"{"message": "Invalid authentication credentials"}"#)
+            } else if request.starts_with("GET /zones?name=example.com ") {
+                ("200 OK", r#"{"zones": [{"id": "z1", "name": "example.com"}]}"#)
+            } else if request.starts_with("GET /records?zone_id=z1 ") {
+                RECORD_LISTS.fetch_add(1, Ordering::SeqCst);
+                (
+                    "200 OK",
+                    concat!(
+                        r#"{"records": [{"id": "r7", "type": "A", "name": "home", "value": "203.0.113.7", "ttl": 300}, "#,
+                        r#"{"id": "r8", "type": "CAA", "name": "@", "value": "0 issue \"letsencrypt.org\"", "#,
+                        r#""ttl": 3600}, {"id": "r9", "type": "CNAME", "name": "www", "value": "home", "ttl": 300}]}"#,
+                    ),
+                )
+            } else if request.starts_with("PUT /records/r7 ") {
+                ("200 OK", r#"{"record": {"id": "r7"}}"#)
+            } else {
+                ("404 Not Found", r#"{"error": {"message": "zone not found", "code": 404}}"#)
+            }
+        })
+    }
+
+    #[test]
+    fn test_hetzner() {
+        let http = HttpConfig::default();
+        let mut provider = HetznerProvider::new("SOME_TOKEN", &http).unwrap();
+        assert_eq!(provider.id(), "hetzner");
+        assert_eq!(provider.capabilities().min_update_interval, None);
+
+        let records = vec![
+            ZoneRecord {
+                id: String::from("r1"),
+                record_type: String::from("A"),
+                name: String::from("@"),
+                value: String::from("203.0.113.7"),
+                ttl: None,
+            },
+            ZoneRecord {
+                id: String::from("r2"),
+                record_type: String::from("AAAA"),
+                name: String::from("Home"),
+                value: String::new(),
+                ttl: Some(300),
+            },
+        ];
+        let apex = RecordConfig::new("example.com", "", RecordType::A, 300);
+        assert_eq!(find_record(&records, &apex).map(|r| r.id.as_str()), Some("r1"));
+        let home = RecordConfig::new("example.com", "home", RecordType::Aaaa, 300);
+        assert_eq!(find_record(&records, &home).map(|r| r.id.as_str()), Some("r2"));
+        assert!(find_record(&records, &RecordConfig::new("example.com", "home", RecordType::A, 300)).is_none());
+        assert!(conflicts(&records, &apex).is_empty() && conflicts(&records, &home).is_empty());
+        let home_alias = RecordConfig::new("example.com", "home", RecordType::Cname, 300);
+        assert_eq!(conflicts(&records, &home_alias).iter().map(|r| r.id.as_str()).collect::<Vec<_>>(), vec!["r2"]);
+
+        assert_eq!(failure_message(r#"{"error": {"message": "zone not found", "code": 404}}"#), "zone not found");
+        assert_eq!(
+            failure_message(r#"{"message": "Invalid authentication credentials"}"#),
+            "Invalid authentication credentials"
+        );
+        assert_eq!(failure_message(" Bad Gateway "), "Bad Gateway");
+        assert!(check_status("home.example.com", 200, "{}").is_ok());
+        assert!(matches!(check_status("home.example.com", 401, ""), Err(ProviderError::Auth(_))));
+        assert!(matches!(check_status("home.example.com", 404, ""), Err(ProviderError::NoHost(e)) if e.contains("HTTP 404")));
+        assert!(matches!(check_status("home.example.com", 422, ""), Err(ProviderError::Api(_))));
+
+        // Ensures that the records are looked up and only updated if they do not point to the address with the TTL.
+        provider.api_url = serve_api();
+        let home = RecordConfig::new("example.com", "home", RecordType::A, 300);
+        assert!(matches!(provider.update_record(&home, "203.0.113.7".parse().unwrap()), Ok(UpdateStatus::Unchanged)));
+        assert!(matches!(provider.update_record(&home, "203.0.113.8".parse().unwrap()), Ok(UpdateStatus::Updated)));
+        let home_ttl = RecordConfig::new("example.com", "home", RecordType::A, 600);
+        assert!(matches!(provider.update_record(&home_ttl, "203.0.113.7".parse().unwrap()), Ok(UpdateStatus::Updated)));
+        let nas = RecordConfig::new("example.com", "nas", RecordType::A, 300);
+        assert!(matches!(provider.update_record(&nas, "203.0.113.8".parse().unwrap()), Err(ProviderError::NoHost(_))));
+        let other = RecordConfig::new("example.org", "home", RecordType::A, 300);
+        assert!(matches!(provider.update_record(&other, "203.0.113.8".parse().unwrap()), Err(ProviderError::NoHost(_))));
+        assert_eq!(
+            provider.zone_ids(),
+            vec![(String::from("example.com"), Some(String::from("z1"))), (String::from("example.org"), None)]
+        );
+
+        // Ensures that the cached zone ID is used, and that the zone is looked up again if Hetzner reports it as unknown.
+        provider.zones.lock().unwrap().clear();
+        let cached_zone = RecordConfig { zone_id: Some(String::from("z1")), ..home.clone() };
+        assert!(matches!(provider.update_record(&cached_zone, "203.0.113.7".parse().unwrap()), Ok(UpdateStatus::Unchanged)));
+        assert!(provider.zone_ids().is_empty());
+        let old_zone = RecordConfig { zone_id: Some(String::from("z0")), ..home.clone() };
+        assert!(matches!(provider.update_record(&old_zone, "203.0.113.7".parse().unwrap()), Ok(UpdateStatus::Unchanged)));
+        assert_eq!(provider.zone_ids(), vec![(String::from("example.com"), Some(String::from("z1")))]);
+
+        // Ensures that the records of the zone fetched by the inspection are reused by the update of the same record.
+        assert!(provider.safety_warnings(&home).is_empty());
+        let lists = RECORD_LISTS.load(Ordering::SeqCst);
+        assert!(matches!(provider.update_record(&home, "203.0.113.7".parse().unwrap()), Ok(UpdateStatus::Unchanged)));
+        assert_eq!(RECORD_LISTS.load(Ordering::SeqCst), lists);
+        assert!(matches!(provider.update_record(&home, "203.0.113.7".parse().unwrap()), Ok(UpdateStatus::Unchanged)));
+        assert_eq!(RECORD_LISTS.load(Ordering::SeqCst), lists + 1);
+
+        // Ensures that only the records conflicting with the record are reported, and that no warning is returned if the zone
+        // cannot be inspected.
+        let www = RecordConfig::new("example.com", "www", RecordType::A, 300);
+        let warnings = provider.safety_warnings(&www);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("CNAME record (\"home\")") && warnings[0].contains("www.example.com"));
+        assert!(provider.safety_warnings(&other).is_empty());
+        provider.token = String::from("SOME_OTHER_TOKEN");
+        assert!(matches!(provider.update_record(&home, "203.0.113.8".parse().unwrap()), Err(ProviderError::Auth(_))));
+    }
+}
@@ -0,0 +1,339 @@
+// Copyright 2025 Amon Rayfa.
+// SPDX-License-Identifier: Apache-2.0.
+
+//! This module contains the backend of [Linode](https://www.linode.com) (now part of Akamai), whose DNS Manager is managed
+//! through the domains of its REST API with a personal access token.
+
+use crate::api::cache::RecordConfig;
+use crate::api::provider::{self, Provider, UpdateStatus};
+use crate::api::request::{self, HttpConfig};
+use crate::api::sync::lock;
+use crate::error::api::{ProviderError, Result};
+use reqwest::blocking::{Client, RequestBuilder};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+
+/// The base URL of the REST API of Linode.
+pub const API_URL: &str = "https://api.linode.com/v4";
+
+/// The number of items requested for each page of the lists of the REST API (the maximum allowed by Linode).
+const PAGE_SIZE: u32 = 500;
+
+/// A page of a list returned by the REST API of Linode.
+#[derive(Debug, Deserialize)]
+struct Page<T> {
+    #[serde(default = "Vec::new")]
+    data: Vec<T>,
+    #[serde(default)]
+    pages: u32,
+}
+
+/// A domain returned by the REST API of Linode (only the fields used by the backend are kept).
+#[derive(Debug, Deserialize)]
+struct Domain {
+    id: u64,
+    domain: String,
+}
+
+/// A record of a domain returned by the REST API of Linode (only the fields used by the backend are kept).
+#[derive(Debug, Deserialize)]
+struct DomainRecord {
+    id: u64,
+    #[serde(rename = "type")]
+    record_type: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    target: String,
+    #[serde(default)]
+    ttl_sec: u32,
+}
+
+/// The backend of Linode. The personal access token of the account (with the `domains:read_write` scope) is stored in the
+/// `api_key` of the DNS provider (the `secret_api_key` is not used). The records must already exist in the DNS Manager, since
+/// the backend only updates them.
+pub struct LinodeProvider {
+    token: String,
+    api_url: String,
+    domains: Mutex<BTreeMap<String, Option<String>>>,
+    http: HttpConfig,
+    client: Client,
+}
+
+impl LinodeProvider {
+    /// Creates a new Linode backend. An error is returned if the HTTP client cannot be built.
+    pub fn new(token: &str, http: &HttpConfig) -> Result<LinodeProvider> {
+        Ok(LinodeProvider {
+            token: token.to_string(),
+            api_url: API_URL.to_string(),
+            domains: Mutex::new(BTreeMap::new()),
+            http: http.clone(),
+            client: http.build_client()?,
+        })
+    }
+
+    /// Sends the requests to the origin (e.g. a replay server) instead of the one of Linode, if it is set.
+    pub(crate) fn with_origin(mut self, origin: Option<&str>) -> LinodeProvider {
+        if let Some(origin) = origin {
+            self.api_url = request::rebase(&self.api_url, origin);
+        }
+        self
+    }
+
+    /// Sends the request with the token of the account, and returns the status and the body of the response.
+    fn send(&self, request: RequestBuilder) -> std::result::Result<(u16, String), ProviderError> {
+        let response = self
+            .http
+            .apply_headers(request)
+            .bearer_auth(&self.token)
+            .send()
+            .map_err(|err| ProviderError::Http(format!("{:?}", self.http.map_error(err))))?;
+
+        provider::read_response(self.id(), response, &self.http, &[&self.token])
+    }
+
+    /// Fetches every page of a list of the REST API, applying the filter (a JSON object sent in the `X-Filter` header) if any.
+    /// `None` is returned if the list does not exist (e.g. the records of an unknown domain).
+    fn list<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        filter: Option<&str>,
+        hostname: &str,
+    ) -> std::result::Result<Option<Vec<T>>, ProviderError> {
+        let mut items = Vec::new();
+        let mut page = 1;
+        loop {
+            let mut request = self.client.get(paged_url(url, page));
+            if let Some(filter) = filter {
+                request = request.header("X-Filter", filter);
+            }
+            let (status, body) = self.send(request)?;
+            if status == 404 {
+                return Ok(None);
+            }
+            check_status(hostname, status, &body)?;
+
+            let list: Page<T> = serde_json::from_str(&body).map_err(|err| {
+                ProviderError::Api(format!("The server returned an invalid list for \"{}\" ({}).", hostname, err))
+            })?;
+            items.extend(list.data);
+            if page >= list.pages {
+                return Ok(Some(items));
+            }
+            page += 1;
+        }
+    }
+
+    /// Looks up the ID of the domain the record belongs to, and remembers it (see
+    /// [`Provider::zone_ids`](wapi::api::provider::Provider)). An error is returned if the account does not manage it.
+    fn find_domain(&self, record: &RecordConfig) -> std::result::Result<String, ProviderError> {
+        let hostname = record.fqdn();
+        let filter = serde_json::json!({ "domain": record.domain }).to_string();
+        let domains: Vec<Domain> =
+            self.list(&format!("{}/domains", self.api_url), Some(&filter), &hostname)?.unwrap_or_default();
+
+        let domain_id =
+            domains.iter().find(|d| d.domain.eq_ignore_ascii_case(&record.domain)).map(|d| d.id.to_string());
+        lock(&self.domains).insert(record.domain.clone(), domain_id.clone());
+        domain_id.ok_or_else(|| {
+            ProviderError::NoHost(format!(
+                "The domain \"{}\" of \"{}\" is not managed by this Linode account.",
+                record.domain, hostname
+            ))
+        })
+    }
+
+    /// Returns the records of the domain, or `None` if the domain does not exist. An error is returned if the request fails.
+    fn records(
+        &self,
+        domain_id: &str,
+        record: &RecordConfig,
+    ) -> std::result::Result<Option<Vec<DomainRecord>>, ProviderError> {
+        self.list(&format!("{}/domains/{}/records", self.api_url, domain_id), None, &record.fqdn())
+    }
+
+    /// Returns the ID and the records of the domain the record belongs to. The domain ID cached in the record is used if any,
+    /// and looked up again if Linode reports it as unknown (e.g. if the domain was recreated).
+    fn domain_records(&self, record: &RecordConfig) -> std::result::Result<(String, Vec<DomainRecord>), ProviderError> {
+        if let Some(domain_id) = record.zone_id.as_ref().filter(|d| !d.is_empty()) {
+            match self.records(domain_id, record)? {
+                Some(records) => return Ok((domain_id.clone(), records)),
+                None => {
+                    lock(&self.domains).insert(record.domain.clone(), None);
+                }
+            }
+        }
+        let domain_id = self.find_domain(record)?;
+        let records = self.records(&domain_id, record)?.ok_or_else(|| {
+            ProviderError::NoHost(format!("The domain \"{}\" of \"{}\" does not exist.", record.domain, record.fqdn()))
+        })?;
+
+        Ok((domain_id, records))
+    }
+}
+
+impl Provider for LinodeProvider {
+    fn id(&self) -> &str {
+        "linode"
+    }
+
+    fn update_record(&self, record: &RecordConfig, address: IpAddr) -> std::result::Result<UpdateStatus, ProviderError> {
+        let hostname = record.fqdn();
+        let (domain_id, records) = self.domain_records(record)?;
+        let existing = find_record(&records, record).ok_or_else(|| {
+            ProviderError::NoHost(format!(
+                "No {} record of \"{}\" exists in the Linode DNS Manager (it must be created first).",
+                record.record_type, hostname
+            ))
+        })?;
+
+        // Updates the record only if it does not already point to the address with the configured TTL.
+        if existing.target.parse::<IpAddr>().is_ok_and(|a| a == address) && existing.ttl_sec == record.ttl {
+            return Ok(UpdateStatus::Unchanged);
+        }
+        let url = format!("{}/domains/{}/records/{}", self.api_url, domain_id, existing.id);
+        let update = serde_json::json!({ "target": address.to_string(), "ttl_sec": record.ttl });
+        let (status, body) = self.send(self.client.put(url).json(&update))?;
+        check_status(&hostname, status, &body)?;
+
+        Ok(UpdateStatus::Updated)
+    }
+
+    fn zone_ids(&self) -> Vec<(String, Option<String>)> {
+        lock(&self.domains).iter().map(|(d, z)| (d.clone(), z.clone())).collect()
+    }
+}
+
+/// Returns the URL of a page of a list of the REST API.
+fn paged_url(url: &str, page: u32) -> String {
+    format!("{}?page={}&page_size={}", url, page, PAGE_SIZE)
+}
+
+/// Returns the record of the domain matching the subdomain and the type of the record configuration. The apex of the domain
+/// is named with an empty string by Linode.
+fn find_record<'a>(records: &'a [DomainRecord], record: &RecordConfig) -> Option<&'a DomainRecord> {
+    records.iter().find(|r| r.record_type == record.record_type.to_string() && r.name.eq_ignore_ascii_case(&record.subdomain))
+}
+
+/// Converts the failure statuses returned by Linode into errors.
+fn check_status(hostname: &str, status: u16, body: &str) -> std::result::Result<(), ProviderError> {
+    match status {
+        200..=299 => Ok(()),
+        401 | 403 => Err(ProviderError::Auth(format!(
+            "The server rejected the token used to update \"{}\", which must be valid and have the `domains:read_write` scope \
+             (HTTP {}: \"{}\").",
+            hostname,
+            status,
+            body.trim()
+        ))),
+        404 => Err(ProviderError::NoHost(format!(
+            "The server does not manage \"{}\" for this account (HTTP {}: \"{}\").",
+            hostname,
+            status,
+            body.trim()
+        ))),
+        _ => Err(ProviderError::Api(format!(
+            "The server refused to update \"{}\" (HTTP {}: \"{}\").",
+            hostname,
+            status,
+            body.trim()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::cache::RecordType;
+    use crate::api::testing;
+
+    /// Serves a simulated REST API of Linode on a local port, managing `example.com` (with the ID 1) whose `home` A record
+    /// (with the ID 7) points to 203.0.113.7 with a TTL of 300, and returns its URL.
+    fn serve_api() -> String {
+        testing::serve(|request| {
+            if !request.contains("Bearer SOME_TOKEN") {
+                ("401 Unauthorized", r#"{"errors": [{"reason": "Invalid Token"}]}"#)
+            } else if request.starts_with("GET /domains?") && request.contains("example.com") {
+                ("200 OK", r#"{"data": [{"id": 1, "domain": "example.com"}], "page": 1, "pages": 1}"#)
+            } else if request.starts_with("GET /domains?") {
+                ("200 OK", r#"{"data": [], "page": 1, "pages": 1}"#)
+            } else if request.starts_with("GET /domains/1/records?") {
+                (
+                    "200 OK",
+                    r#"{"data": [{"id": 7, "type": "A", "name": "home", "target": "203.0.113.7", "ttl_sec": 300}], "pages": 1}"#,
+                )
+            } else if request.starts_with("PUT /domains/1/records/7 ") {
+                ("200 OK", r#"{"id": 7}"#)
+            } else {
+                ("404 Not Found", r#"{"errors": [{"reason": "Not found"}]}"#)
+            }
+        })
+    }
+
+    #[test]
+    fn test_linode() {
+        let http = HttpConfig::default();
+        let mut provider = LinodeProvider::new("SOME_TOKEN", &http).unwrap();
+        assert_eq!(provider.id(), "linode");
+        assert_eq!(provider.capabilities().min_update_interval, None);
+        assert_eq!(paged_url("https://api.linode.com/v4/domains", 2), "https://api.linode.com/v4/domains?page=2&page_size=500");
+
+        let records = vec![
+            DomainRecord {
+                id: 1,
+                record_type: String::from("A"),
+                name: String::new(),
+                target: String::from("203.0.113.7"),
+                ttl_sec: 0,
+            },
+            DomainRecord {
+                id: 2,
+                record_type: String::from("AAAA"),
+                name: String::from("Home"),
+                target: String::new(),
+                ttl_sec: 300,
+            },
+        ];
+        assert_eq!(find_record(&records, &RecordConfig::new("example.com", "", RecordType::A, 300)).map(|r| r.id), Some(1));
+        assert_eq!(
+            find_record(&records, &RecordConfig::new("example.com", "home", RecordType::Aaaa, 300)).map(|r| r.id),
+            Some(2)
+        );
+        assert!(find_record(&records, &RecordConfig::new("example.com", "home", RecordType::A, 300)).is_none());
+
+        assert!(check_status("home.example.com", 200, "{}").is_ok());
+        assert!(matches!(check_status("home.example.com", 401, "Invalid Token"), Err(ProviderError::Auth(_))));
+        assert!(matches!(check_status("home.example.com", 404, "Not found"), Err(ProviderError::NoHost(_))));
+        assert!(matches!(check_status("home.example.com", 500, ""), Err(ProviderError::Api(_))));
+
+        // Ensures that the records are looked up and only updated if they do not point to the address with the TTL.
+        provider.api_url = serve_api();
+        let home = RecordConfig::new("example.com", "home", RecordType::A, 300);
+        assert!(matches!(provider.update_record(&home, "203.0.113.7".parse().unwrap()), Ok(UpdateStatus::Unchanged)));
+        assert!(matches!(provider.update_record(&home, "203.0.113.8".parse().unwrap()), Ok(UpdateStatus::Updated)));
+        let home_ttl = RecordConfig::new("example.com", "home", RecordType::A, 600);
+        assert!(matches!(provider.update_record(&home_ttl, "203.0.113.7".parse().unwrap()), Ok(UpdateStatus::Updated)));
+        let nas = RecordConfig::new("example.com", "nas", RecordType::A, 300);
+        assert!(matches!(provider.update_record(&nas, "203.0.113.8".parse().unwrap()), Err(ProviderError::NoHost(_))));
+        let other = RecordConfig::new("example.org", "home", RecordType::A, 300);
+        assert!(matches!(provider.update_record(&other, "203.0.113.8".parse().unwrap()), Err(ProviderError::NoHost(_))));
+        assert_eq!(
+            provider.zone_ids(),
+            vec![(String::from("example.com"), Some(String::from("1"))), (String::from("example.org"), None)]
+        );
+
+        // Ensures that the cached domain ID is used, and that the domain is looked up again if Linode reports it as unknown.
+        provider.domains.lock().unwrap().clear();
+        let cached_domain = RecordConfig { zone_id: Some(String::from("1")), ..home.clone() };
+        assert!(matches!(provider.update_record(&cached_domain, "203.0.113.7".parse().unwrap()), Ok(UpdateStatus::Unchanged)));
+        assert!(provider.zone_ids().is_empty());
+        let old_domain = RecordConfig { zone_id: Some(String::from("0")), ..home.clone() };
+        assert!(matches!(provider.update_record(&old_domain, "203.0.113.7".parse().unwrap()), Ok(UpdateStatus::Unchanged)));
+        assert_eq!(provider.zone_ids(), vec![(String::from("example.com"), Some(String::from("1")))]);
+        provider.token = String::from("SOME_OTHER_TOKEN");
+        assert!(matches!(provider.update_record(&home, "203.0.113.8".parse().unwrap()), Err(ProviderError::Auth(_))));
+    }
+}
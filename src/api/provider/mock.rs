@@ -0,0 +1,328 @@
+// Copyright 2025 Amon Rayfa.
+// SPDX-License-Identifier: Apache-2.0.
+
+//! This module contains a simulated DNS provider backend, used to test the update runs without real credentials. It is only
+//! available in the tests and with the `testing` feature.
+
+use crate::api::cache::RecordConfig;
+use crate::api::provider::{Provider, UpdateStatus};
+use crate::api::sync::lock;
+use crate::error::api::{Error, ErrorKind, ProviderError, Result};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// The prefix of the IDs of the simulated DNS providers. The rest of the ID selects the
+/// [`MockBehavior`](wapi::api::provider::mock::MockBehavior) of the DNS provider (e.g. `mock:success` or `mock:fail`), unless
+/// a scripted DNS provider was registered with this ID (see [`MockProvider::register`]).
+pub const MOCK_PREFIX: &str = "mock:";
+
+/// The programmed response of a simulated DNS provider to every update.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockBehavior {
+    /// The records are updated (`mock:success`).
+    Success,
+    /// The records already point to the address (`mock:unchanged`).
+    Unchanged,
+    /// The DNS provider refuses the updates (`mock:fail`).
+    Fail,
+    /// The DNS provider rejects the credentials (`mock:auth`).
+    Auth,
+}
+
+/// The method of the [`Provider`](wapi::api::provider::Provider) trait a scripted response applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MockMethod {
+    /// [`Provider::update_record`](wapi::api::provider::Provider).
+    UpdateRecord,
+    /// [`Provider::update_record_set`](wapi::api::provider::Provider).
+    UpdateRecordSet,
+    /// [`Provider::update_value`](wapi::api::provider::Provider).
+    UpdateValue,
+    /// [`Provider::safety_warnings`](wapi::api::provider::Provider), which only returns a warning for the
+    /// [`MockResponse::Warn`] responses (and none once they are exhausted).
+    SafetyWarnings,
+}
+
+/// A scripted response of a simulated DNS provider (see [`MockProvider::respond_with`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockResponse {
+    /// The update succeeds with the given status.
+    Succeed(UpdateStatus),
+    /// The update fails with an error of the given kind.
+    Fail(ErrorKind),
+    /// The DNS provider reports the safety warning (see [`MockMethod::SafetyWarnings`]). The updates answered with it
+    /// succeed.
+    Warn(&'static str),
+    /// The DNS provider waits for the given time, then answers the call with the next response (e.g. to slow down a single
+    /// method, unlike [`MockProvider::with_delay`]).
+    Delay(Duration),
+}
+
+/// A simulated DNS provider backend, which records the updates it receives and answers them with its scripted responses, or
+/// according to its behavior once they are exhausted. Cloning it is cheap, and the clones share the same script and records,
+/// so that a test can keep a clone to inspect the updates received by the one used by an update run.
+#[derive(Clone)]
+pub struct MockProvider {
+    id: String,
+    behavior: MockBehavior,
+    state: Arc<MockState>,
+}
+
+/// The state shared by the clones of a [`MockProvider`].
+#[derive(Default)]
+struct MockState {
+    calls: Mutex<Vec<(String, IpAddr)>>,
+    values: Mutex<Vec<(String, String)>>,
+    script: Mutex<HashMap<MockMethod, VecDeque<MockResponse>>>,
+    delay: Mutex<Duration>,
+    zone_ids: Mutex<Vec<(String, Option<String>)>>,
+}
+
+/// The scripted DNS providers returned by [`build`](wapi::api::provider::build) instead of the ones named by their ID.
+static REGISTRY: Mutex<BTreeMap<String, MockProvider>> = Mutex::new(BTreeMap::new());
+
+impl MockProvider {
+    /// Creates a simulated DNS provider from its ID. An error is returned if the ID does not start with `mock:` or if the
+    /// behavior it names does not exist.
+    pub fn new(id: &str) -> Result<MockProvider> {
+        let behavior = match id.strip_prefix(MOCK_PREFIX) {
+            Some("success") => MockBehavior::Success,
+            Some("unchanged") => MockBehavior::Unchanged,
+            Some("fail") => MockBehavior::Fail,
+            Some("auth") => MockBehavior::Auth,
+            _ => {
+                return Err(Error::Provider(
+                    String::from("build"),
+                    format!("The ID \"{}\" does not name a simulated DNS provider.", id).into(),
+                ))
+            }
+        };
+
+        Ok(MockProvider { id: id.to_string(), behavior, state: Arc::default() })
+    }
+
+    /// Creates a scripted DNS provider, whose ID must start with `mock:` but can name anything (e.g. `mock:registrar-a`), so
+    /// that each test can register its own. It updates the records until responses are scripted. An error is returned if the
+    /// ID does not start with `mock:`.
+    pub fn scripted(id: &str) -> Result<MockProvider> {
+        match id.strip_prefix(MOCK_PREFIX) {
+            Some(name) if !name.is_empty() => {
+                Ok(MockProvider { id: id.to_string(), behavior: MockBehavior::Success, state: Arc::default() })
+            }
+            _ => Err(Error::Provider(
+                String::from("build"),
+                format!("The ID \"{}\" of a simulated DNS provider must start with `{}`.", id, MOCK_PREFIX).into(),
+            )),
+        }
+    }
+
+    /// Queues responses to the next calls of the method, which are answered in order before falling back to the behavior of
+    /// the DNS provider.
+    pub fn respond_with(self, method: MockMethod, responses: impl IntoIterator<Item = MockResponse>) -> MockProvider {
+        lock(&self.state.script).entry(method).or_default().extend(responses);
+        self
+    }
+
+    /// Sets the time taken by each update, whatever its method (e.g. to test the cancellation or the concurrency of a run).
+    /// Defaults to zero.
+    pub fn with_delay(self, delay: Duration) -> MockProvider {
+        *lock(&self.state.delay) = delay;
+        self
+    }
+
+    /// Sets the zone IDs reported by the DNS provider after its updates (see
+    /// [`Provider::zone_ids`](wapi::api::provider::Provider)), as `(domain, zone_id)` pairs. Defaults to none.
+    pub fn with_zone_ids(self, zone_ids: impl IntoIterator<Item = (String, Option<String>)>) -> MockProvider {
+        *lock(&self.state.zone_ids) = zone_ids.into_iter().collect();
+        self
+    }
+
+    /// Registers the DNS provider, so that [`build`](wapi::api::provider::build) returns a clone of it for its ID (e.g. in an
+    /// update run) instead of the one its ID names. It replaces the DNS provider previously registered with the same ID.
+    pub fn register(&self) {
+        lock(&REGISTRY).insert(self.id.clone(), self.clone());
+    }
+
+    /// Unregisters the DNS provider registered with the ID, if any.
+    pub fn unregister(id: &str) {
+        lock(&REGISTRY).remove(id);
+    }
+
+    /// Returns the behavior of the simulated DNS provider.
+    pub fn behavior(&self) -> MockBehavior {
+        self.behavior
+    }
+
+    /// Returns the updates received by the simulated DNS provider, as `(fqdn, address)` pairs in the order they were received
+    /// (an update setting several addresses yields one pair per address).
+    pub fn calls(&self) -> Vec<(String, IpAddr)> {
+        lock(&self.state.calls).clone()
+    }
+
+    /// Returns the values received by the simulated DNS provider for its TXT and CNAME records, as `(fqdn, value)` pairs in the
+    /// order they were received.
+    pub fn values(&self) -> Vec<(String, String)> {
+        lock(&self.state.values).clone()
+    }
+
+    /// Returns the next scripted response to a call of the method, if any, after waiting for the delays scripted before it.
+    fn next_response(&self, method: MockMethod) -> Option<MockResponse> {
+        loop {
+            match lock(&self.state.script).get_mut(&method).and_then(VecDeque::pop_front) {
+                Some(MockResponse::Delay(delay)) => std::thread::sleep(delay),
+                response => return response,
+            }
+        }
+    }
+
+    /// Returns the response of the simulated DNS provider to a call of the method for the record, which is the next scripted
+    /// response if any, or the one of its behavior otherwise.
+    fn respond(&self, method: MockMethod, record: &RecordConfig) -> std::result::Result<UpdateStatus, ProviderError> {
+        std::thread::sleep(*lock(&self.state.delay));
+        let response = self.next_response(method).unwrap_or(match self.behavior {
+            MockBehavior::Success => MockResponse::Succeed(UpdateStatus::Updated),
+            MockBehavior::Unchanged => MockResponse::Succeed(UpdateStatus::Unchanged),
+            MockBehavior::Fail => MockResponse::Fail(ErrorKind::Api),
+            MockBehavior::Auth => MockResponse::Fail(ErrorKind::Auth),
+        });
+
+        match response {
+            MockResponse::Succeed(status) => Ok(status),
+            MockResponse::Warn(_) | MockResponse::Delay(_) => Ok(UpdateStatus::Updated),
+            MockResponse::Fail(ErrorKind::Auth) => Err(ProviderError::Auth(String::from("The credentials were rejected."))),
+            MockResponse::Fail(ErrorKind::NoHost) => {
+                Err(ProviderError::NoHost(format!("The hostname \"{}\" is not managed.", record.fqdn())))
+            }
+            MockResponse::Fail(ErrorKind::Api) => {
+                Err(ProviderError::Api(format!("The update of \"{}\" was refused.", record.fqdn())))
+            }
+            MockResponse::Fail(ErrorKind::Http) => Err(ProviderError::Http(String::from("The server could not be reached."))),
+        }
+    }
+}
+
+impl Provider for MockProvider {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn safety_warnings(&self, _record: &RecordConfig) -> Vec<String> {
+        match self.next_response(MockMethod::SafetyWarnings) {
+            Some(MockResponse::Warn(warning)) => vec![warning.to_string()],
+            _ => Vec::new(),
+        }
+    }
+
+    fn update_record(&self, record: &RecordConfig, address: IpAddr) -> std::result::Result<UpdateStatus, ProviderError> {
+        lock(&self.state.calls).push((record.fqdn(), address));
+        self.respond(MockMethod::UpdateRecord, record)
+    }
+
+    fn update_record_set(
+        &self,
+        record: &RecordConfig,
+        addresses: &[IpAddr],
+    ) -> std::result::Result<UpdateStatus, ProviderError> {
+        lock(&self.state.calls).extend(addresses.iter().map(|address| (record.fqdn(), *address)));
+        self.respond(MockMethod::UpdateRecordSet, record)
+    }
+
+    fn update_value(&self, record: &RecordConfig, value: &str) -> std::result::Result<UpdateStatus, ProviderError> {
+        lock(&self.state.values).push((record.fqdn(), value.to_string()));
+        self.respond(MockMethod::UpdateValue, record)
+    }
+
+    fn zone_ids(&self) -> Vec<(String, Option<String>)> {
+        lock(&self.state.zone_ids).clone()
+    }
+}
+
+/// Returns the simulated DNS provider for the ID: the one registered with it if any, or the one its ID names otherwise.
+pub(crate) fn build(id: &str) -> Result<MockProvider> {
+    match lock(&REGISTRY).get(id) {
+        Some(provider) => Ok(provider.clone()),
+        None => MockProvider::new(id),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::cache::RecordType;
+
+    #[test]
+    fn test_mock_provider() {
+        let record = RecordConfig::new("example.com", "www", RecordType::A, 300);
+        let address: IpAddr = "203.0.113.7".parse().unwrap();
+
+        let provider = MockProvider::new("mock:success").unwrap();
+        assert_eq!(provider.behavior(), MockBehavior::Success);
+        assert!(matches!(provider.update_record(&record, address), Ok(UpdateStatus::Updated)));
+        assert!(matches!(provider.update_record(&record, address), Ok(UpdateStatus::Updated)));
+        assert_eq!(provider.calls(), vec![(String::from("www.example.com"), address); 2]);
+        let txt = RecordConfig::new("example.com", "", RecordType::Txt, 300).with_value_template("v=spf1 ip4:{ipv4} -all");
+        assert!(matches!(provider.update_value(&txt, "v=spf1 ip4:203.0.113.7 -all"), Ok(UpdateStatus::Updated)));
+        assert_eq!(provider.values(), vec![(String::from("example.com"), String::from("v=spf1 ip4:203.0.113.7 -all"))]);
+
+        let provider = MockProvider::new("mock:fail").unwrap();
+        assert!(matches!(provider.update_record(&record, address), Err(ProviderError::Api(_))));
+        let provider = MockProvider::new("mock:auth").unwrap();
+        assert!(matches!(provider.update_record(&record, address), Err(ProviderError::Auth(_))));
+
+        assert!(MockProvider::new("mock:unknown").is_err());
+        assert!(MockProvider::new("porkbun").is_err());
+    }
+
+    #[test]
+    fn test_scripted_provider() {
+        let record = RecordConfig::new("example.com", "www", RecordType::A, 300);
+        let address: IpAddr = "203.0.113.7".parse().unwrap();
+
+        // Ensures that the scripted responses are answered in order for their method, before falling back to the behavior.
+        let provider = MockProvider::scripted("mock:test-scripted")
+            .unwrap()
+            .respond_with(
+                MockMethod::UpdateRecord,
+                [MockResponse::Fail(ErrorKind::Http), MockResponse::Fail(ErrorKind::NoHost)],
+            )
+            .respond_with(MockMethod::UpdateRecordSet, [MockResponse::Succeed(UpdateStatus::Unchanged)])
+            .respond_with(MockMethod::SafetyWarnings, [MockResponse::Warn("The zone is signed.")])
+            .with_delay(Duration::from_millis(20));
+        let clone = provider.clone();
+        let start = std::time::Instant::now();
+        assert!(matches!(provider.update_record(&record, address), Err(ProviderError::Http(_))));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        assert!(matches!(provider.update_record_set(&record, &[address]), Ok(UpdateStatus::Unchanged)));
+        assert!(matches!(provider.update_record(&record, address), Err(ProviderError::NoHost(_))));
+        assert!(matches!(provider.update_record(&record, address), Ok(UpdateStatus::Updated)));
+        assert_eq!(clone.calls().len(), 4);
+        assert_eq!(provider.safety_warnings(&record), vec![String::from("The zone is signed.")]);
+        assert!(provider.safety_warnings(&record).is_empty());
+        assert!(MockProvider::scripted("mock:").is_err() && MockProvider::scripted("registrar").is_err());
+
+        // Ensures that the registered DNS providers are built instead of the ones named by their ID.
+        assert!(build("mock:test-scripted").is_err());
+        provider.register();
+        assert_eq!(build("mock:test-scripted").unwrap().calls().len(), 4);
+        MockProvider::unregister("mock:test-scripted");
+        assert!(build("mock:test-scripted").is_err());
+        assert_eq!(build("mock:success").unwrap().behavior(), MockBehavior::Success);
+
+        // Ensures that a scripted delay only slows down the method it is scripted for, and only once.
+        let delay = Duration::from_millis(200);
+        let provider = MockProvider::scripted("mock:test-delay")
+            .unwrap()
+            .respond_with(MockMethod::UpdateValue, [MockResponse::Delay(delay), MockResponse::Succeed(UpdateStatus::Unchanged)]);
+        let start = std::time::Instant::now();
+        assert!(matches!(provider.update_record(&record, address), Ok(UpdateStatus::Updated)));
+        assert!(provider.safety_warnings(&record).is_empty());
+        assert!(start.elapsed() < delay);
+        assert!(matches!(provider.update_value(&record, "203.0.113.7"), Ok(UpdateStatus::Unchanged)));
+        assert!(start.elapsed() >= delay);
+        let start = std::time::Instant::now();
+        assert!(matches!(provider.update_value(&record, "203.0.113.7"), Ok(UpdateStatus::Updated)));
+        assert!(start.elapsed() < delay);
+    }
+}
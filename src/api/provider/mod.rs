@@ -0,0 +1,446 @@
+// Copyright 2025 Amon Rayfa.
+// SPDX-License-Identifier: Apache-2.0.
+
+//! This module contains the list of the DNS providers supported by the program, the trait implemented by each of them, and
+//! the functions used to build and inspect them.
+
+#[cfg(feature = "provider-desec")]
+pub mod desec;
+#[cfg(feature = "provider-dyndns2")]
+pub mod dyndns2;
+#[cfg(feature = "provider-googleclouddns")]
+pub mod googleclouddns;
+#[cfg(feature = "provider-hetzner")]
+pub mod hetzner;
+#[cfg(feature = "provider-linode")]
+pub mod linode;
+#[cfg(any(test, feature = "testing"))]
+pub mod mock;
+
+use crate::api::cache::{self, Cache, RecordConfig};
+use crate::api::request::HttpConfig;
+use crate::error::api::{Error, ErrorKind, ProviderError, Result};
+#[cfg(feature = "provider-desec")]
+use desec::DesecProvider;
+#[cfg(feature = "provider-dyndns2")]
+use dyndns2::DynDns2Provider;
+#[cfg(feature = "provider-googleclouddns")]
+use googleclouddns::GoogleCloudDnsProvider;
+#[cfg(feature = "provider-hetzner")]
+use hetzner::HetznerProvider;
+#[cfg(feature = "provider-linode")]
+use linode::LinodeProvider;
+use reqwest::blocking::Response;
+use reqwest::header::RETRY_AFTER;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::time::Duration;
+
+/// The IDs of the DNS providers supported by this build of the program. The DNS providers that are implemented are only listed
+/// if their Cargo feature (e.g. `provider-desec`) is enabled, which is the case by default, while the other ones are accepted
+/// by the cache but fail when their backend is built. For more information on each provider, see the
+/// [GitHub repository](https://github.com/AmonRayfa/wapi).
+pub const SUPPORTED_PROVIDERS: &[&str] = &[
+    "alibabacloud",
+    "bluehost",
+    "cloudflare",
+    #[cfg(feature = "provider-desec")]
+    "desec",
+    "dnspod",
+    "dreamhost",
+    "dynadot",
+    #[cfg(feature = "provider-dyndns2")]
+    "dyndns2",
+    "enom",
+    "epik",
+    "gandi",
+    "godaddy",
+    #[cfg(feature = "provider-googleclouddns")]
+    "googleclouddns",
+    #[cfg(feature = "provider-hetzner")]
+    "hetzner",
+    "hover",
+    "ionos",
+    #[cfg(feature = "provider-linode")]
+    "linode",
+    "namecheap",
+    "namesilo",
+    "opensrs",
+    "ovh",
+    "porkbun",
+    "resellerclub",
+];
+
+/// Returns `true` if the DNS provider ID is supported by this build of the program. In the tests and with the `testing`
+/// feature, the IDs of the simulated DNS providers (starting with `mock:`) are supported as well.
+pub fn is_supported(id: &str) -> bool {
+    #[cfg(any(test, feature = "testing"))]
+    if id.starts_with(mock::MOCK_PREFIX) {
+        return true;
+    }
+
+    SUPPORTED_PROVIDERS.contains(&id)
+}
+
+/// The IDs of the DNS providers whose backend is compiled in by its own Cargo feature (named `provider-<id>`).
+const FEATURE_PROVIDERS: &[&str] = &["desec", "dyndns2", "googleclouddns", "hetzner", "linode"];
+
+/// Returns `true` if the DNS provider ID is known to the program, even if its backend is not compiled into this build. The
+/// cache keeps the DNS providers with a known ID, so that a build without some backends does not erase their credentials and
+/// records from the cache file.
+pub(crate) fn is_known(id: &str) -> bool {
+    is_supported(id) || FEATURE_PROVIDERS.contains(&id)
+}
+
+/// The outcome of a successful record update.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateStatus {
+    /// The record was changed to point to the new address.
+    Updated,
+    /// The record already pointed to the address, so nothing was changed.
+    Unchanged,
+}
+
+/// The struct describing how a DNS provider backend must be used.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    /// The minimum amount of time that must elapse between two updates sent to the DNS provider. Some DNS providers (e.g.
+    /// the DynDNS2 services) flag the clients that send updates too often as abusive, so this interval is respected even when
+    /// an update is forced. If `None`, updates can be sent at any time.
+    pub min_update_interval: Option<Duration>,
+}
+
+/// The trait implemented by every DNS provider backend. The backends must be thread-safe, since several DNS providers can be
+/// updated at the same time.
+pub trait Provider: Send + Sync {
+    /// Returns the ID of the DNS provider.
+    fn id(&self) -> &str;
+
+    /// Returns the capabilities of the DNS provider. By default, the DNS provider has no particular requirement.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::default()
+    }
+
+    /// Inspects the zone of the record before it is updated, and returns warnings about the configurations that the update
+    /// could interfere with (e.g. a CNAME record of the same name, or a DNSSEC-signed zone that must be re-signed manually),
+    /// which are only reported when the zone shows an actual problem. It is only called right before the record is sent, so the
+    /// DNS provider can reuse what it fetched for the update. This is only implemented by the DNS providers whose API exposes
+    /// this information. By default, no warning is returned.
+    fn safety_warnings(&self, record: &RecordConfig) -> Vec<String> {
+        let _ = record;
+        Vec::new()
+    }
+
+    /// Updates the record so that it points to the address, and returns whether anything was changed.
+    fn update_record(&self, record: &RecordConfig, address: IpAddr) -> std::result::Result<UpdateStatus, ProviderError>;
+
+    /// Updates the record so that it holds exactly the addresses (the detected one followed by its static ones, see
+    /// [`RecordConfig::static_values`](wapi::RecordConfig)), and returns whether anything was changed. By default, the DNS
+    /// provider cannot set several values for a record, so a single address is sent through
+    /// [`Provider::update_record`](wapi::api::provider::Provider) and an error is returned for several.
+    fn update_record_set(
+        &self,
+        record: &RecordConfig,
+        addresses: &[IpAddr],
+    ) -> std::result::Result<UpdateStatus, ProviderError> {
+        match addresses {
+            [address] => self.update_record(record, *address),
+            _ => Err(ProviderError::Api(format!(
+                "The DNS provider \"{}\" cannot set several addresses for the record \"{}\", which has static values.",
+                self.id(),
+                record.fqdn()
+            ))),
+        }
+    }
+
+    /// Updates a TXT or CNAME record so that it holds the value rendered from its template (see
+    /// [`RecordConfig::value_template`](wapi::RecordConfig)), and returns whether anything was changed. By default, the DNS
+    /// provider does not support these records and an error is returned.
+    fn update_value(&self, record: &RecordConfig, value: &str) -> std::result::Result<UpdateStatus, ProviderError> {
+        let _ = value;
+        Err(ProviderError::Api(format!(
+            "The DNS provider \"{}\" does not support updating the {} record \"{}\".",
+            self.id(),
+            record.record_type,
+            record.fqdn()
+        )))
+    }
+
+    /// Returns the zone IDs resolved by the backend during its updates, as `(domain, zone_id)` pairs, so that they are cached
+    /// in the [`RecordConfig::zone_id`](wapi::RecordConfig) of the records and reused on the next runs. A `None` zone ID
+    /// means that the DNS provider reported the cached one as unknown (e.g. with a 404 status), so it must be cleared. This is
+    /// only implemented by the DNS providers whose API identifies zones by ID. By default, no zone ID is returned.
+    fn zone_ids(&self) -> Vec<(String, Option<String>)> {
+        Vec::new()
+    }
+}
+
+/// Returns the credentials (`api_key` and/or `secret_api_key`) that must be set for a DNS provider. The credentials that are
+/// not listed may be left empty.
+pub fn required_credentials(id: &str) -> &'static [&'static str] {
+    match id {
+        "desec" => &["api_key"],
+        "dyndns2" => &["api_key", "secret_api_key"],
+        "googleclouddns" => &["api_key"],
+        "hetzner" => &["api_key"],
+        "linode" => &["api_key"],
+        _ if id.starts_with("mock:") => &[],
+        _ => &["api_key", "secret_api_key"],
+    }
+}
+
+/// Builds the backend of a DNS provider of the cache, using its stored credentials, parameters, and extra headers. The DNS
+/// provider is designated by its reference (see [`split_reference`](wapi::api::cache::split_reference)), and its backend is
+/// chosen by its ID, so that the accounts of a DNS provider share the same backend. An error is returned if the DNS provider
+/// does not exist in the cache, if one of its required credentials (see
+/// [`required_credentials`](wapi::api::provider::required_credentials)) is empty, if it has no implementation yet, or if its
+/// HTTP client cannot be built.
+pub fn build(cache: &Cache, reference: &str, http: &HttpConfig) -> Result<Box<dyn Provider>> {
+    build_with_origin(cache, reference, http, None)
+}
+
+/// Builds the backend of a DNS provider of the cache like [`build`], sending its requests to the origin (e.g. a replay
+/// server in the tests) instead of the servers of the DNS provider if it is set.
+#[allow(unused_variables)] // The origin is unused when no DNS provider backend is compiled in.
+pub(crate) fn build_with_origin(
+    cache: &Cache,
+    reference: &str,
+    http: &HttpConfig,
+    origin: Option<&str>,
+) -> Result<Box<dyn Provider>> {
+    let (api_key, secret_api_key) = cache.provider_credentials(reference).ok_or_else(|| {
+        Error::Provider(
+            String::from("build"),
+            format!("No DNS provider with the ID \"{}\" exists in the cache.", reference).into(),
+        )
+    })?;
+    let id = cache::split_reference(reference).0;
+    if !is_supported(id) && FEATURE_PROVIDERS.contains(&id) {
+        return Err(Error::Provider(
+            String::from("build"),
+            format!("The DNS provider \"{}\" is not compiled into this build (see the `provider-{}` feature).", reference, id)
+                .into(),
+        ));
+    }
+
+    // Ensures that the credentials required by the DNS provider are set.
+    for credential in required_credentials(id) {
+        let value = match *credential {
+            "api_key" => api_key,
+            _ => secret_api_key,
+        };
+        if value.trim().is_empty() {
+            return Err(Error::Provider(
+                String::from("missing_credential"),
+                format!("The DNS provider \"{}\" requires the `{}` credential, which is empty.", reference, credential).into(),
+            ));
+        }
+    }
+
+    // Merges the extra headers of the DNS provider into the HTTP configuration.
+    let mut http = http.clone();
+    http.headers.extend(cache.extra_headers(reference).unwrap_or_default().iter().cloned());
+
+    #[cfg(any(test, feature = "testing"))]
+    if id.starts_with(mock::MOCK_PREFIX) {
+        return Ok(Box::new(mock::build(id)?));
+    }
+
+    match id {
+        #[cfg(feature = "provider-desec")]
+        "desec" => {
+            Ok(Box::new(DesecProvider::new(api_key, cache.provider_param(reference, "mode"), &http)?.with_origin(origin)))
+        }
+        #[cfg(feature = "provider-dyndns2")]
+        "dyndns2" => {
+            let update_url = cache.provider_param(reference, "update_url");
+            Ok(Box::new(DynDns2Provider::new(api_key, secret_api_key, update_url, &http)?.with_origin(origin)))
+        }
+        #[cfg(feature = "provider-googleclouddns")]
+        "googleclouddns" => {
+            let (project, access_token) =
+                (cache.provider_param(reference, "project"), cache.provider_param(reference, "access_token"));
+            Ok(Box::new(GoogleCloudDnsProvider::new(api_key, project, access_token, &http)?.with_origin(origin)))
+        }
+        #[cfg(feature = "provider-hetzner")]
+        "hetzner" => Ok(Box::new(HetznerProvider::new(api_key, &http)?.with_origin(origin))),
+        #[cfg(feature = "provider-linode")]
+        "linode" => Ok(Box::new(LinodeProvider::new(api_key, &http)?.with_origin(origin))),
+        _ => Err(Error::Provider(String::from("build"), format!("The DNS provider \"{}\" is not implemented yet.", id).into())),
+    }
+}
+
+/// Reads the status and the body of a response of a DNS provider. If the DNS provider asked to be retried later (a throttling
+/// or server-side failure with a `Retry-After` header), the failure is returned instead, along with the delay it requested
+/// (see [`Error::retry_after`](wapi::Error)) and with the secrets scrubbed from it.
+#[allow(dead_code)] // The function is unused when no DNS provider backend is compiled in.
+pub(crate) fn read_response(
+    id: &str,
+    response: Response,
+    http: &HttpConfig,
+    secrets: &[&str],
+) -> std::result::Result<(u16, String), ProviderError> {
+    let status = response.status().as_u16();
+    let endpoint = response.url().to_string();
+    let retry_after = response.headers().get(RETRY_AFTER).and_then(|value| value.to_str().ok()).map(String::from);
+    let body = response.text().map_err(|err| ProviderError::Http(format!("{:?}", http.map_error(err))))?;
+
+    if let Some(value) = retry_after {
+        let err =
+            Error::provider_response(id, ErrorKind::Api, Some(status), &endpoint, &body, secrets).with_retry_after(&value);
+        if err.retry_after().is_some() {
+            return Err(ProviderError::Response(Box::new(err)));
+        }
+    }
+
+    Ok((status, body))
+}
+
+/// The struct describing a DNS provider listed in a remote provider manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProviderInfo {
+    /// The ID of the DNS provider.
+    pub id: String,
+    /// The human-readable name of the DNS provider.
+    #[serde(default)]
+    pub name: String,
+    /// Whether the DNS provider is supported by the running build of the program. This field is not read from the manifest,
+    /// it is computed by comparing the manifest against [`SUPPORTED_PROVIDERS`](wapi::api::provider::SUPPORTED_PROVIDERS).
+    #[serde(skip_deserializing, default)]
+    pub supported: bool,
+}
+
+/// Downloads a JSON manifest listing the known DNS providers, and returns its entries with the
+/// [`ProviderInfo::supported`](wapi::api::provider::ProviderInfo) field telling whether the running build supports them. The
+/// manifest must be a JSON array of objects with an `id` field and an optional `name` field. This function is purely
+/// informational (nothing but the manifest is downloaded) and is never called by the program on its own. An error is returned
+/// if the manifest cannot be downloaded or deserialized.
+pub fn fetch_manifest(url: &str) -> Result<Vec<ProviderInfo>> {
+    let http = HttpConfig::default();
+    let client = http.build_client()?;
+
+    // Downloads the manifest and returns an error if the server cannot be reached or does not answer with a success status.
+    let manifest = client
+        .get(url)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.text())
+        .map_err(|err| http.map_error(err))?;
+
+    // Deserializes the manifest and returns an error if it fails.
+    let providers = serde_json::from_str::<Vec<ProviderInfo>>(&manifest)
+        .map_err(|err| Error::Http(String::from("response"), err.into()))?;
+
+    Ok(compare_manifest(providers))
+}
+
+/// Flags each entry of a provider manifest with whether the running build supports it.
+fn compare_manifest(mut providers: Vec<ProviderInfo>) -> Vec<ProviderInfo> {
+    for provider in providers.iter_mut() {
+        provider.supported = is_supported(&provider.id);
+    }
+
+    providers
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_compare_manifest() {
+        let manifest = vec![
+            ProviderInfo { id: String::from("porkbun"), name: String::from("Porkbun"), supported: false },
+            ProviderInfo { id: String::from("some_future_provider"), name: String::new(), supported: true },
+        ];
+
+        let providers = compare_manifest(manifest);
+        assert_eq!(providers.len(), 2);
+        assert!(providers[0].supported);
+        assert!(!providers[1].supported);
+
+        assert!(is_supported("cloudflare"));
+        assert!(!is_supported("some_random_name"));
+    }
+
+    #[test]
+    fn test_registry() {
+        // Ensures that the implemented DNS providers are only accepted if they are compiled in, and that the other ones are
+        // rejected cleanly by the cache (e.g. with `cargo test --no-default-features --lib test_registry`).
+        let mut cache = Cache::new();
+        for (id, compiled) in [
+            ("desec", cfg!(feature = "provider-desec")),
+            ("dyndns2", cfg!(feature = "provider-dyndns2")),
+            ("googleclouddns", cfg!(feature = "provider-googleclouddns")),
+            ("hetzner", cfg!(feature = "provider-hetzner")),
+            ("linode", cfg!(feature = "provider-linode")),
+        ] {
+            assert_eq!(is_supported(id), compiled);
+            assert_eq!(SUPPORTED_PROVIDERS.contains(&id), compiled);
+            assert_eq!(cache.ensure_provider(id, "SOME_API_KEY", "SOME_SECRET_API_KEY").is_ok(), compiled);
+            assert_eq!(cache.provider(id).is_some(), compiled);
+
+            // Ensures that a DNS provider whose backend is not compiled in is kept by the cache (e.g. if it was loaded from the
+            // cache file of another build), and that building its backend fails explicitly.
+            cache.add_dns_provider(String::from(id), String::from("SOME_API_KEY"), String::from("SOME_SECRET_API_KEY"));
+            assert!(cache.provider(id).is_some());
+            if !compiled {
+                let err = build(&cache, id, &HttpConfig::default()).err().unwrap();
+                assert!(err.code() == "E_PROVIDER_BUILD" && format!("{:?}", err).contains("not compiled"));
+            }
+        }
+        assert!(cache.ensure_provider("some_random_name", "SOME_API_KEY", "SOME_SECRET_API_KEY").is_err());
+    }
+
+    #[test]
+    fn test_build() {
+        let mut cache = Cache::new();
+        let http = HttpConfig::default();
+        #[cfg(feature = "provider-dyndns2")]
+        {
+            assert!(build(&cache, "dyndns2", &http).is_err());
+
+            // Ensures that the missing credentials are named, and that a DNS provider that needs none accepts empty ones.
+            cache.add_dns_provider(String::from("dyndns2"), String::from("SOME_USERNAME"), String::new());
+            match build(&cache, "dyndns2", &http) {
+                Ok(_) => panic!("A DNS provider was built without its required credentials."),
+                Err(e) => {
+                    assert!(format!("{}", e).contains("missing_credential") && format!("{:?}", e).contains("secret_api_key"))
+                }
+            }
+            cache.add_dns_provider(String::from("dyndns2"), String::from("SOME_USERNAME"), String::from("SOME_PASSWORD"));
+            assert_eq!(build(&cache, "dyndns2", &http).unwrap().id(), "dyndns2");
+        }
+
+        #[cfg(feature = "provider-desec")]
+        {
+            cache.add_dns_provider(String::from("desec"), String::from("SOME_TOKEN"), String::new());
+            assert_eq!(build(&cache, "desec", &http).unwrap().id(), "desec");
+        }
+        #[cfg(feature = "provider-googleclouddns")]
+        {
+            let key =
+                r#"{"project_id": "some-project", "private_key": "SOME_PRIVATE_KEY", "client_email": "wapi@some-project.iam"}"#;
+            cache.add_dns_provider(String::from("googleclouddns"), String::from(key), String::new());
+            assert_eq!(build(&cache, "googleclouddns", &http).unwrap().id(), "googleclouddns");
+            cache.add_dns_provider(String::from("googleclouddns/invalid"), String::from("{}"), String::new());
+            assert!(build(&cache, "googleclouddns/invalid", &http).is_err());
+        }
+        #[cfg(feature = "provider-hetzner")]
+        {
+            cache.add_dns_provider(String::from("hetzner"), String::from("SOME_TOKEN"), String::new());
+            assert_eq!(build(&cache, "hetzner", &http).unwrap().id(), "hetzner");
+        }
+        #[cfg(feature = "provider-linode")]
+        {
+            cache.add_dns_provider(String::from("linode"), String::from("SOME_TOKEN"), String::new());
+            assert_eq!(build(&cache, "linode", &http).unwrap().id(), "linode");
+        }
+
+        cache.add_dns_provider(String::from("mock:success"), String::new(), String::new());
+        assert_eq!(build(&cache, "mock:success", &http).unwrap().id(), "mock:success");
+
+        cache.add_dns_provider(String::from("porkbun"), String::from("SOME_API_KEY"), String::from("SOME_SECRET_API_KEY"));
+        assert!(build(&cache, "porkbun", &http).is_err());
+    }
+}
@@ -0,0 +1,604 @@
+// Copyright 2025 Amon Rayfa.
+// SPDX-License-Identifier: Apache-2.0.
+
+//! This module contains the record-and-replay layer used to test the DNS provider backends against the exchanges of their
+//! real APIs, which is only available in the tests and with the `testing` feature. A [`Fixture`] describes the updates sent
+//! to a DNS provider and the HTTP exchanges they caused, and is replayed by a local [`ReplayServer`] that the backend is
+//! pointed to, which checks that every request matches the recorded one.
+//!
+//! The fixtures are recorded (or refreshed) with real credentials by running the tests with `WAPI_RECORD=1`, along with the
+//! `WAPI_RECORD_<ID>_API_KEY` and `WAPI_RECORD_<ID>_SECRET_API_KEY` variables of the DNS provider (e.g.
+//! `WAPI_RECORD_HETZNER_API_KEY`), and optionally `WAPI_RECORD_<ID>_DOMAIN` to update the records of one of your domains
+//! instead of the one of the fixture (its records must already exist). The requests are then forwarded to the real API, and
+//! the credentials and the domain are replaced with placeholders in the saved exchanges.
+
+use crate::api::cache::{Cache, RecordConfig, RecordType};
+use crate::api::provider::{self, UpdateStatus};
+use crate::api::request::HttpConfig;
+use crate::api::sync::lock;
+use crate::error::api::{Error, ErrorKind, ProviderError, Result, ResultExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+/// The environment variable enabling the record mode when it is set to `1`.
+pub const RECORD_VAR: &str = "WAPI_RECORD";
+
+/// The placeholder replacing the `api_key` of the DNS provider in the fixtures.
+pub const API_KEY_PLACEHOLDER: &str = "<api_key>";
+
+/// The placeholder replacing the `secret_api_key` of the DNS provider in the fixtures.
+pub const SECRET_API_KEY_PLACEHOLDER: &str = "<secret_api_key>";
+
+/// The updates sent to a DNS provider and the HTTP exchanges they caused, stored as a JSON file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Fixture {
+    /// The ID of the DNS provider.
+    pub provider: String,
+    /// The origin of the real API of the DNS provider (e.g. `https://dns.hetzner.com`), which the requests are forwarded to in
+    /// the record mode.
+    pub upstream: String,
+    /// The date (`YYYY-MM-DD`) on which the exchanges were recorded against the real API of the DNS provider, or `None` if they
+    /// were written by hand and are still pending a recording (in which case they can drift from the real API).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recorded: Option<String>,
+    /// The extra parameters of the DNS provider (e.g. the `mode` of deSEC).
+    #[serde(default)]
+    pub params: BTreeMap<String, String>,
+    /// The extra parameters of the DNS provider that are only set when the fixture is replayed, for the credentials that
+    /// cannot be replayed (e.g. the `access_token` of Google Cloud DNS, which is minted from the real key in the record mode).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub replay_params: BTreeMap<String, String>,
+    /// The updates sent to the DNS provider, in order.
+    pub updates: Vec<FixtureUpdate>,
+    /// The HTTP exchanges caused by the updates, in order.
+    pub interactions: Vec<Interaction>,
+}
+
+/// An update sent to the DNS provider of a [`Fixture`], along with its expected outcome.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FixtureUpdate {
+    /// The domain of the record.
+    pub domain: String,
+    /// The subdomain of the record (empty for the apex of the domain).
+    #[serde(default)]
+    pub subdomain: String,
+    /// The type of the record.
+    #[serde(rename = "type")]
+    pub record_type: RecordType,
+    /// The time to live of the record, in seconds.
+    pub ttl: u32,
+    /// The addresses the record is updated to. A single address is sent with `update_record`, and several ones with
+    /// `update_record_set`.
+    pub addresses: Vec<IpAddr>,
+    /// The outcome of the update.
+    pub outcome: FixtureOutcome,
+}
+
+/// The outcome of an update of a [`Fixture`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FixtureOutcome {
+    /// The record was updated.
+    Updated,
+    /// The record already pointed to the addresses.
+    Unchanged,
+    /// The DNS provider rejected the credentials.
+    Auth,
+    /// The DNS provider does not manage the hostname.
+    NoHost,
+    /// The DNS provider refused the update.
+    Api,
+    /// The DNS provider could not be reached.
+    Http,
+}
+
+impl From<&std::result::Result<UpdateStatus, ProviderError>> for FixtureOutcome {
+    fn from(result: &std::result::Result<UpdateStatus, ProviderError>) -> Self {
+        match result {
+            Ok(UpdateStatus::Updated) => FixtureOutcome::Updated,
+            Ok(UpdateStatus::Unchanged) => FixtureOutcome::Unchanged,
+            Err(err) => match err.kind() {
+                ErrorKind::Auth => FixtureOutcome::Auth,
+                ErrorKind::NoHost => FixtureOutcome::NoHost,
+                ErrorKind::Api => FixtureOutcome::Api,
+                ErrorKind::Http => FixtureOutcome::Http,
+            },
+        }
+    }
+}
+
+/// An HTTP exchange of a [`Fixture`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    /// The request sent by the DNS provider backend.
+    pub request: RecordedRequest,
+    /// The response of the API.
+    pub response: RecordedResponse,
+}
+
+/// A request of an [`Interaction`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    /// The method of the request (e.g. `GET`).
+    pub method: String,
+    /// The path of the request, along with its query.
+    pub path: String,
+    /// The significant part of the body of the request, which is matched against the body of the replayed request: the
+    /// fields of a JSON object must be present in the replayed one with the same values (the other fields are ignored), and
+    /// any other value must be equal to the replayed body (a string being compared to the raw body). If `None`, the body is
+    /// not matched.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<Value>,
+}
+
+/// A response of an [`Interaction`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    /// The status of the response.
+    pub status: u16,
+    /// The body of the response, as JSON, or as a string for the bodies that are not JSON. A `null` body is empty.
+    #[serde(default)]
+    pub body: Value,
+}
+
+/// The credentials and the domain used to record a [`Fixture`] against the real API of its DNS provider.
+#[derive(Clone)]
+pub struct RecordCredentials {
+    /// The `api_key` of the DNS provider.
+    pub api_key: String,
+    /// The `secret_api_key` of the DNS provider.
+    pub secret_api_key: String,
+    /// The domain whose records are updated instead of the ones of the fixture, if any.
+    pub domain: Option<String>,
+}
+
+impl RecordCredentials {
+    /// Returns the credentials of the DNS provider set in the environment, or `None` if the record mode is not enabled (see
+    /// [`RECORD_VAR`]) or if its `WAPI_RECORD_<ID>_API_KEY` variable is not set.
+    pub fn from_env(provider: &str) -> Option<RecordCredentials> {
+        if std::env::var(RECORD_VAR).ok().as_deref() != Some("1") {
+            return None;
+        }
+        let prefix = format!("WAPI_RECORD_{}", provider.to_uppercase());
+        Some(RecordCredentials {
+            api_key: std::env::var(format!("{}_API_KEY", prefix)).ok()?,
+            secret_api_key: std::env::var(format!("{}_SECRET_API_KEY", prefix)).unwrap_or_default(),
+            domain: std::env::var(format!("{}_DOMAIN", prefix)).ok(),
+        })
+    }
+}
+
+impl Fixture {
+    /// Loads a fixture from a JSON file. An `Error::Replay` with the `fixture` step is returned if the file cannot be read or
+    /// parsed.
+    pub fn load(path: &Path) -> Result<Fixture> {
+        std::fs::read_to_string(path)
+            .map_err(|e| Error::Replay(String::from("fixture"), e.into()))
+            .and_then(|content| serde_json::from_str(&content).map_err(|e| Error::Replay(String::from("fixture"), e.into())))
+            .with_context(|| format!("Could not load the fixture \"{}\".", path.display()))
+    }
+
+    /// Saves the fixture to a JSON file, replacing it. An `Error::Replay` with the `fixture` step is returned if the file
+    /// cannot be written.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| Error::Replay(String::from("fixture"), e.into()))
+            .and_then(|content| {
+                std::fs::write(path, content + "\n").map_err(|e| Error::Replay(String::from("fixture"), e.into()))
+            })
+            .with_context(|| format!("Could not save the fixture \"{}\".", path.display()))
+    }
+
+    /// Replays the updates of the fixture against its recorded exchanges, with placeholder credentials. An error is returned
+    /// if a request does not match the recorded one, if a recorded exchange is left unused, or if an update does not have the
+    /// expected outcome.
+    pub fn replay(&self) -> Result<()> {
+        let server = ReplayServer::replay(self.interactions.clone())?;
+        let outcomes = self.run(&server, API_KEY_PLACEHOLDER, SECRET_API_KEY_PLACEHOLDER, None, &self.replay_params);
+        server.finish()?;
+
+        for (index, (update, outcome)) in self.updates.iter().zip(outcomes?).enumerate() {
+            if update.outcome != outcome {
+                return Err(Error::Replay(
+                    String::from("outcome"),
+                    format!("The update #{} ended with {:?} instead of {:?}.", index + 1, outcome, update.outcome).into(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends the updates of the fixture to the real API of its DNS provider with the credentials, and returns the fixture
+    /// holding the exchanges and the outcomes observed, with the credentials and the domain replaced with placeholders, and
+    /// the date of the recording. An error is returned if the API cannot be reached.
+    pub fn record(&self, credentials: &RecordCredentials) -> Result<Fixture> {
+        let mut secrets = vec![
+            (credentials.api_key.clone(), String::from(API_KEY_PLACEHOLDER)),
+            (credentials.secret_api_key.clone(), String::from(SECRET_API_KEY_PLACEHOLDER)),
+        ];
+        if let (Some(domain), Some(update)) = (&credentials.domain, self.updates.first()) {
+            secrets.push((domain.clone(), update.domain.clone()));
+        }
+        let server = ReplayServer::record(&self.upstream, secrets)?;
+        let (api_key, secret_api_key) = (&credentials.api_key, &credentials.secret_api_key);
+        let outcomes = self.run(&server, api_key, secret_api_key, credentials.domain.as_deref(), &BTreeMap::new());
+        let interactions = server.finish()?;
+
+        let mut fixture = self.clone();
+        for (update, outcome) in fixture.updates.iter_mut().zip(outcomes?) {
+            update.outcome = outcome;
+        }
+        fixture.interactions = interactions;
+        fixture.recorded = Some(chrono::Utc::now().format("%Y-%m-%d").to_string());
+        Ok(fixture)
+    }
+
+    /// Sends the updates of the fixture to the server with the credentials and the extra parameters (on top of the ones of
+    /// the fixture), and returns their outcomes. The records are updated on the domain if it is set, or on the ones of the
+    /// updates otherwise.
+    fn run(
+        &self,
+        server: &ReplayServer,
+        api_key: &str,
+        secret: &str,
+        domain: Option<&str>,
+        params: &BTreeMap<String, String>,
+    ) -> Result<Vec<FixtureOutcome>> {
+        let mut cache = Cache::new();
+        cache.add_dns_provider(self.provider.clone(), api_key.to_string(), secret.to_string());
+        for (key, value) in self.params.iter().chain(params.iter()) {
+            cache.set_provider_param(&self.provider, key, Some(value.clone()));
+        }
+        let backend = provider::build_with_origin(&cache, &self.provider, &HttpConfig::default(), Some(server.origin()))?;
+
+        let mut outcomes = Vec::new();
+        for update in self.updates.iter() {
+            let record = RecordConfig::new(domain.unwrap_or(&update.domain), &update.subdomain, update.record_type, update.ttl);
+            let result = match update.addresses.as_slice() {
+                [address] => backend.update_record(&record, *address),
+                addresses => backend.update_record_set(&record, addresses),
+            };
+            outcomes.push(FixtureOutcome::from(&result));
+        }
+        Ok(outcomes)
+    }
+}
+
+/// The local HTTP server a DNS provider backend is pointed to in the tests, which either replays recorded exchanges or
+/// records the exchanges with a real API. The requests are handled one at a time, in the order they are received.
+pub struct ReplayServer {
+    address: SocketAddr,
+    origin: String,
+    shared: Arc<Mutex<Shared>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// The state shared between a [`ReplayServer`] and its thread.
+struct Shared {
+    mode: Mode,
+    pending: VecDeque<Interaction>,
+    served: Vec<Interaction>,
+    failures: Vec<String>,
+}
+
+/// The mode of a [`ReplayServer`].
+enum Mode {
+    /// The requests are answered with the pending exchanges, in order.
+    Replay,
+    /// The requests are forwarded to the upstream, and the exchanges are stored with the secrets replaced by their
+    /// placeholders.
+    Record { upstream: String, secrets: Vec<(String, String)>, client: reqwest::blocking::Client },
+}
+
+impl ReplayServer {
+    /// Starts a server replaying the exchanges, in order. An error is returned if the server cannot be started.
+    pub fn replay(interactions: Vec<Interaction>) -> Result<ReplayServer> {
+        ReplayServer::start(Mode::Replay, interactions.into())
+    }
+
+    /// Starts a server forwarding the requests to the upstream (e.g. `https://dns.hetzner.com`), and recording the exchanges
+    /// with the secrets replaced by their placeholders (as `(secret, placeholder)` pairs, the secrets shorter than 4
+    /// characters being ignored). An error is returned if the server cannot be started.
+    pub fn record(upstream: &str, secrets: Vec<(String, String)>) -> Result<ReplayServer> {
+        let client = HttpConfig::default().build_client()?;
+        let secrets = secrets.into_iter().filter(|(secret, _)| secret.len() >= 4).collect();
+        ReplayServer::start(Mode::Record { upstream: upstream.trim_end_matches('/').to_string(), secrets, client }, [].into())
+    }
+
+    /// Starts the server on a local port.
+    fn start(mode: Mode, pending: VecDeque<Interaction>) -> Result<ReplayServer> {
+        let map_err = |e: std::io::Error| Error::Replay(String::from("server"), e.into());
+        let listener = TcpListener::bind("127.0.0.1:0").map_err(map_err)?;
+        let address = listener.local_addr().map_err(map_err)?;
+        let shared = Arc::new(Mutex::new(Shared { mode, pending, served: Vec::new(), failures: Vec::new() }));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let (thread_shared, thread_stop) = (shared.clone(), stop.clone());
+        let thread = std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                if thread_stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Err(e) = stream.and_then(|stream| handle(&thread_shared, stream)) {
+                    lock(&thread_shared).failures.push(format!("The connection failed ({}).", e));
+                }
+            }
+        });
+
+        Ok(ReplayServer { address, origin: format!("http://{}", address), shared, stop, thread: Some(thread) })
+    }
+
+    /// Returns the origin of the server (e.g. `http://127.0.0.1:8080`), which the backend must send its requests to.
+    pub fn origin(&self) -> &str {
+        &self.origin
+    }
+
+    /// Stops the server, and returns the exchanges it served (the recorded ones in the record mode). An error is returned if
+    /// a request did not match the recorded one, if a recorded exchange was left unused, or if a request could not be
+    /// forwarded.
+    pub fn finish(mut self) -> Result<Vec<Interaction>> {
+        self.stop();
+        let mut shared = lock(&self.shared);
+        let mut failures = std::mem::take(&mut shared.failures);
+        failures.extend(
+            shared.pending.iter().map(|i| format!("The request {} {} was never sent.", i.request.method, i.request.path)),
+        );
+
+        match failures.is_empty() {
+            true => Ok(std::mem::take(&mut shared.served)),
+            false => Err(Error::Replay(String::from("exchange"), failures.join(" ").into())),
+        }
+    }
+
+    /// Stops the thread of the server, if it is still running.
+    fn stop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            self.stop.store(true, Ordering::SeqCst);
+            // Wakes the thread up, which is waiting for a connection.
+            let _ = TcpStream::connect(self.address);
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for ReplayServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Reads a request from the stream, and writes the response of the server to it.
+fn handle(shared: &Mutex<Shared>, mut stream: TcpStream) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or_default().to_string(), parts.next().unwrap_or_default().to_string());
+
+    // Reads the headers, and then the body according to its length.
+    let mut headers = Vec::new();
+    let mut length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.trim_end().split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().unwrap_or(0);
+            }
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    let body = String::from_utf8_lossy(&body).to_string();
+
+    let (status, response) = respond(&mut lock(shared), &method, &path, &headers, &body);
+    let head = format!("HTTP/1.1 {} Replayed\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", status, response.len());
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(response.as_bytes())
+}
+
+/// Returns the status and the body of the response to the request, according to the mode of the server.
+fn respond(shared: &mut Shared, method: &str, path: &str, headers: &[(String, String)], body: &str) -> (u16, String) {
+    match &shared.mode {
+        Mode::Replay => {
+            let Some(expected) = shared.pending.pop_front() else {
+                shared.failures.push(format!("The request {} {} was not expected.", method, path));
+                return (500, String::new());
+            };
+            if let Err(reason) = matches(&expected.request, method, path, body) {
+                shared.failures.push(format!("The request {} {} does not match the recorded one: {}", method, path, reason));
+                return (500, String::new());
+            }
+            let response = (expected.response.status, body_text(&expected.response.body));
+            shared.served.push(expected);
+            response
+        }
+        Mode::Record { upstream, secrets, client } => {
+            let Ok(http_method) = reqwest::Method::from_bytes(method.as_bytes()) else {
+                shared.failures.push(format!("The method {} is invalid.", method));
+                return (500, String::new());
+            };
+            let mut request = client.request(http_method, format!("{}{}", upstream, path));
+            for (name, value) in headers.iter() {
+                if !["host", "content-length", "connection"].iter().any(|h| name.eq_ignore_ascii_case(h)) {
+                    request = request.header(name.as_str(), value.as_str());
+                }
+            }
+            if !body.is_empty() {
+                request = request.body(body.to_string());
+            }
+            let response = match request.send() {
+                Ok(response) => response,
+                Err(e) => {
+                    shared.failures.push(format!("The request {} {} could not be forwarded ({}).", method, path, e));
+                    return (502, String::new());
+                }
+            };
+            let status = response.status().as_u16();
+            let text = response.text().unwrap_or_default();
+
+            let scrub = |text: &str| {
+                secrets.iter().fold(text.to_string(), |text, (secret, placeholder)| text.replace(secret, placeholder))
+            };
+            let interaction = Interaction {
+                request: RecordedRequest {
+                    method: method.to_string(),
+                    path: scrub(path),
+                    body: (!body.is_empty()).then(|| parse_body(&scrub(body))),
+                },
+                response: RecordedResponse { status, body: parse_body(&scrub(&text)) },
+            };
+            shared.served.push(interaction);
+            (status, text)
+        }
+    }
+}
+
+/// Checks that the request matches the recorded one (see [`RecordedRequest::body`]), and returns the reason why it does not
+/// otherwise.
+fn matches(expected: &RecordedRequest, method: &str, path: &str, body: &str) -> std::result::Result<(), String> {
+    if !expected.method.eq_ignore_ascii_case(method) || expected.path != path {
+        return Err(format!("{} {} was expected.", expected.method, expected.path));
+    }
+
+    match &expected.body {
+        None => Ok(()),
+        Some(Value::String(text)) if text == body => Ok(()),
+        Some(Value::String(text)) => Err(format!("the body \"{}\" was expected.", text)),
+        Some(Value::Object(fields)) => {
+            let actual: Value = serde_json::from_str(body).map_err(|_| String::from("a JSON body was expected."))?;
+            match fields.iter().find(|(key, value)| actual.get(key.as_str()) != Some(value)) {
+                Some((key, value)) => Err(format!("the field `{}` was expected to be {}.", key, value)),
+                None => Ok(()),
+            }
+        }
+        Some(value) => match serde_json::from_str::<Value>(body) {
+            Ok(actual) if actual == *value => Ok(()),
+            _ => Err(format!("the body {} was expected.", value)),
+        },
+    }
+}
+
+/// Parses a body as JSON, or keeps it as a string if it is not JSON.
+fn parse_body(body: &str) -> Value {
+    match body.trim() {
+        "" => Value::Null,
+        trimmed => serde_json::from_str(trimmed).unwrap_or_else(|_| Value::String(body.to_string())),
+    }
+}
+
+/// Returns the text of a recorded body (see [`RecordedResponse::body`]).
+fn body_text(body: &Value) -> String {
+    match body {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_replay_server() {
+        let request = |method: &str, path: &str, body: Option<Value>| RecordedRequest {
+            method: method.to_string(),
+            path: path.to_string(),
+            body,
+        };
+        let fields = serde_json::json!({ "ttl": 300, "value": "203.0.113.8" });
+        assert!(matches(&request("GET", "/zones?name=example.com", None), "GET", "/zones?name=example.com", "").is_ok());
+        assert!(matches(&request("GET", "/zones?name=example.com", None), "GET", "/zones?name=example.org", "").is_err());
+        assert!(matches(&request("GET", "/records", None), "PUT", "/records", "").is_err());
+        let body = r#"{"name": "home", "ttl": 300, "value": "203.0.113.8"}"#;
+        assert!(matches(&request("PUT", "/records/1", Some(fields.clone())), "PUT", "/records/1", body).is_ok());
+        let body = r#"{"name": "home", "ttl": 600, "value": "203.0.113.8"}"#;
+        assert!(
+            matches(&request("PUT", "/records/1", Some(fields)), "PUT", "/records/1", body).is_err_and(|e| e.contains("ttl"))
+        );
+        let text = Some(Value::String(String::from("hostname=home")));
+        assert!(matches(&request("POST", "/", text.clone()), "POST", "/", "hostname=home").is_ok());
+        assert!(matches(&request("POST", "/", text), "POST", "/", "hostname=nas").is_err());
+
+        assert_eq!(parse_body(""), Value::Null);
+        assert_eq!(parse_body("good 203.0.113.8"), Value::String(String::from("good 203.0.113.8")));
+        assert_eq!(body_text(&parse_body(r#"{"id": 7}"#)), r#"{"id":7}"#);
+        assert_eq!(body_text(&Value::Null), "");
+
+        // Ensures that the exchanges are replayed in order, and that the missing and unexpected requests are reported.
+        let exchange = |path: &str, status: u16, body: &str| Interaction {
+            request: request("GET", path, None),
+            response: RecordedResponse { status, body: Value::String(body.to_string()) },
+        };
+        let server = ReplayServer::replay(vec![exchange("/a", 200, "first"), exchange("/b", 404, "second")]).unwrap();
+        let client = HttpConfig::default().build_client().unwrap();
+        let response = client.get(format!("{}/a", server.origin())).send().unwrap();
+        assert_eq!((response.status().as_u16(), response.text().unwrap()), (200, String::from("first")));
+        assert!(server.finish().is_err_and(|e| format!("{:?}", e).contains("GET /b was never sent")));
+        let server = ReplayServer::replay(vec![exchange("/a", 200, "first")]).unwrap();
+        assert_eq!(client.get(format!("{}/b", server.origin())).send().unwrap().status().as_u16(), 500);
+        assert!(server.finish().is_err_and(|e| format!("{:?}", e).contains("GET /a was expected")));
+    }
+
+    #[test]
+    fn test_fixture_errors() {
+        // Ensures that the failures to read and parse the fixtures are told apart from the failures of the cache.
+        let dir = std::env::temp_dir().join(format!("wapi-test-fixture-errors-{}", std::process::id()));
+        let err = Fixture::load(&dir.join("missing.json")).unwrap_err();
+        assert_eq!(err.code(), "E_REPLAY_FIXTURE");
+        assert!(err.to_string().contains("missing.json"));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("invalid.json"), "{").unwrap();
+        assert_eq!(Fixture::load(&dir.join("invalid.json")).unwrap_err().code(), "E_REPLAY_FIXTURE");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_provider_fixtures() {
+        // Replays (or records, see the module documentation) every fixture of the DNS providers compiled in, and ensures that
+        // each of them has at least one.
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures").join("providers");
+        let mut paths: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().path()).collect();
+        paths.sort();
+        let readme = std::fs::read_to_string(dir.join("README.md")).unwrap();
+        let mut covered = Vec::new();
+        for path in paths.iter().filter(|p| p.extension().is_some_and(|e| e == "json")) {
+            let fixture = Fixture::load(path).unwrap();
+
+            // Ensures that the README tells the recorded fixtures apart from the ones that are pending a recording.
+            let name = path.file_name().unwrap().to_string_lossy();
+            let row = readme.lines().find(|line| line.starts_with(&format!("| `{}`", name)));
+            let origin = match &fixture.recorded {
+                Some(date) => format!("Recorded on {}", date),
+                None => String::from("Hand-written (pending)"),
+            };
+            assert!(row.is_some_and(|row| row.contains(&origin)), "The README does not list {:?} as \"{}\".", name, origin);
+
+            if !provider::is_supported(&fixture.provider) {
+                continue;
+            }
+            match RecordCredentials::from_env(&fixture.provider) {
+                Some(credentials) => fixture.record(&credentials).and_then(|recorded| recorded.save(path)),
+                None => fixture.replay(),
+            }
+            .unwrap_or_else(|e| panic!("The fixture {:?} failed: {} {:?}", path, e, e));
+            covered.push(fixture.provider);
+        }
+
+        for id in ["desec", "dyndns2", "googleclouddns", "hetzner", "linode"] {
+            assert_eq!(covered.iter().any(|c| c == id), provider::is_supported(id), "No fixture covers \"{}\".", id);
+        }
+    }
+}
@@ -1,2 +1,220 @@
 // Copyright 2025 Amon Rayfa.
 // SPDX-License-Identifier: Apache-2.0.
+
+//! This module contains the struct and functions used to configure and build the HTTP client shared by the program.
+
+use crate::error::api::{Error, Result};
+use reqwest::blocking::{Client, RequestBuilder};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::time::Duration;
+
+/// A pre-configured HTTP client, used instead of the one built from an [`HttpConfig`](wapi::api::request::HttpConfig) (e.g.
+/// to share the connection pool, the proxy, or the DNS resolver of an application). It is created from a blocking
+/// `reqwest` client.
+#[derive(Debug, Clone)]
+pub struct HttpClient(Client);
+
+impl From<Client> for HttpClient {
+    fn from(client: Client) -> Self {
+        HttpClient(client)
+    }
+}
+
+/// The struct used to configure the HTTP client shared by the program.
+#[derive(Clone)]
+pub struct HttpConfig {
+    /// The maximum amount of time allowed to establish a connection with a server. Defaults to 10 seconds.
+    pub connect_timeout: Duration,
+    /// The maximum amount of time allowed for a whole request, from the moment it is sent until its response has been fully
+    /// read. Defaults to 30 seconds.
+    pub request_timeout: Duration,
+    /// The extra headers sent with every request (e.g. the headers required by an authentication gateway placed in front of
+    /// an API), including the ones sent with a pre-configured HTTP client. Their values are treated as sensitive, so they are
+    /// never printed. Defaults to no header.
+    pub headers: Vec<(String, String)>,
+    /// The pre-configured HTTP client returned by [`HttpConfig::build_client`](wapi::api::request::HttpConfig) instead of
+    /// building one, in which case the timeouts of the configuration are not applied (the ones of the client are), and the
+    /// extra headers are added to each request instead of being set on the client. The requests sent to the local network
+    /// (UPnP gateway, cloud metadata service) do not use it. Defaults to `None`.
+    pub client: Option<HttpClient>,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        HttpConfig {
+            connect_timeout: Duration::from_secs(10),
+            request_timeout: Duration::from_secs(30),
+            headers: Vec::new(),
+            client: None,
+        }
+    }
+}
+
+impl std::fmt::Debug for HttpConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Redacts the values of the extra headers, which may hold credentials.
+        let headers: Vec<(&str, &str)> = self.headers.iter().map(|(name, _)| (name.as_str(), "<redacted>")).collect();
+        f.debug_struct("HttpConfig")
+            .field("connect_timeout", &self.connect_timeout)
+            .field("request_timeout", &self.request_timeout)
+            .field("headers", &headers)
+            .field("client", &self.client)
+            .finish()
+    }
+}
+
+/// Returns `true` if the name is a well-formed HTTP header name (a non-empty token, as defined by RFC 9110).
+pub fn is_valid_header_name(name: &str) -> bool {
+    !name.is_empty() && name.bytes().all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+/// Returns `true` if the value is a well-formed HTTP header value (visible ASCII characters, spaces, and tabs only).
+pub fn is_valid_header_value(value: &str) -> bool {
+    value.bytes().all(|b| b == b'\t' || (b' '..=b'~').contains(&b))
+}
+
+impl HttpConfig {
+    /// Builds an HTTP client that applies the connect timeout, the request timeout, and the extra headers of the configuration,
+    /// or returns the pre-configured one if it is set (which does not send the extra headers on its own, see
+    /// [`HttpConfig::apply_headers`](wapi::api::request::HttpConfig)). An error is returned if a header is malformed, or if the
+    /// client cannot be initialized (e.g. the TLS backend cannot be loaded).
+    pub fn build_client(&self) -> Result<Client> {
+        let headers = self.header_map()?;
+        if let Some(HttpClient(client)) = &self.client {
+            return Ok(client.clone());
+        }
+
+        Client::builder()
+            .user_agent(concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")))
+            .default_headers(headers)
+            .connect_timeout(self.connect_timeout)
+            .timeout(self.request_timeout)
+            .build()
+            .map_err(|err| self.map_error(err))
+    }
+
+    /// Ensures that an HTTP client can be built from the configuration, without building it (a blocking client cannot be built
+    /// nor dropped within an asynchronous runtime). An error is returned if a header is malformed.
+    pub fn validate(&self) -> Result<()> {
+        self.header_map().map(|_| ())
+    }
+
+    /// Adds the extra headers of the configuration to the request, so that they are sent whether the client was built from the
+    /// configuration or pre-configured. The malformed headers are skipped, since they prevent the client from being built (see
+    /// [`HttpConfig::build_client`](wapi::api::request::HttpConfig)).
+    pub fn apply_headers(&self, request: RequestBuilder) -> RequestBuilder {
+        match self.headers.is_empty() {
+            true => request,
+            false => request.headers(self.header_map().unwrap_or_default()),
+        }
+    }
+
+    /// Converts the extra headers, marking their values as sensitive so that they are redacted from the debug output. An error
+    /// is returned if a header is malformed.
+    fn header_map(&self) -> Result<HeaderMap> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in self.headers.iter() {
+            let header_name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| Error::Http(String::from("build"), format!("The header name \"{}\" is invalid.", name).into()))?;
+            let mut header_value = HeaderValue::from_str(value).map_err(|_| {
+                Error::Http(String::from("build"), format!("The value of the header \"{}\" is invalid.", name).into())
+            })?;
+            header_value.set_sensitive(true);
+            headers.insert(header_name, header_value);
+        }
+        Ok(headers)
+    }
+
+    /// Sets the pre-configured HTTP client returned by [`HttpConfig::build_client`](wapi::api::request::HttpConfig) instead of
+    /// building one.
+    pub fn with_client(mut self, client: impl Into<HttpClient>) -> HttpConfig {
+        self.client = Some(client.into());
+        self
+    }
+
+    /// Converts an error returned by the HTTP client into an `Error::Http`, telling apart a connection that could not be
+    /// established in time from a request that was too slow to complete.
+    pub(crate) fn map_error(&self, err: reqwest::Error) -> Error {
+        if err.is_builder() {
+            Error::Http(String::from("build"), err.into())
+        } else if err.is_connect() && err.is_timeout() {
+            Error::Http(
+                String::from("connect"),
+                format!("The connection could not be established within {:?} (connect timeout).", self.connect_timeout).into(),
+            )
+        } else if err.is_connect() {
+            Error::Http(String::from("connect"), err.into())
+        } else if err.is_timeout() {
+            Error::Http(
+                String::from("request"),
+                format!("The request did not complete within {:?} (request timeout).", self.request_timeout).into(),
+            )
+        } else {
+            Error::Http(String::from("request"), err.into())
+        }
+    }
+}
+
+/// Returns the URL with its scheme and host replaced by the origin (e.g. `http://127.0.0.1:8080`), keeping its path and
+/// query. It is used to send the requests of a DNS provider backend to another server (e.g. a replay server in the tests).
+#[cfg(any(
+    test,
+    feature = "provider-desec",
+    feature = "provider-dyndns2",
+    feature = "provider-googleclouddns",
+    feature = "provider-hetzner",
+    feature = "provider-linode"
+))]
+pub(crate) fn rebase(url: &str, origin: &str) -> String {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let path = rest.find('/').map_or("", |start| &rest[start..]);
+    format!("{}{}", origin.trim_end_matches('/'), path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_http_config() {
+        let config = HttpConfig::default();
+        assert_eq!(config.connect_timeout, Duration::from_secs(10));
+        assert_eq!(config.request_timeout, Duration::from_secs(30));
+
+        let config = HttpConfig {
+            connect_timeout: Duration::from_secs(2),
+            request_timeout: Duration::from_secs(60),
+            headers: vec![(String::from("CF-Access-Client-Id"), String::from("SOME_CLIENT_ID"))],
+            client: None,
+        };
+        assert!(config.validate().is_ok() && config.build_client().is_ok());
+        assert!(format!("{:?}", config).contains("CF-Access-Client-Id"));
+        let invalid = HttpConfig { headers: vec![(String::from("X Api Key"), String::new())], ..HttpConfig::default() };
+        assert!(matches!(invalid.validate(), Err(Error::Http(stage, _)) if stage == "build"));
+        assert!(!format!("{:?}", config).contains("SOME_CLIENT_ID"));
+
+        // Ensures that a pre-configured client is used as is, and that the extra headers are added to its requests.
+        let client = HttpClient::from(Client::builder().build().unwrap());
+        let config = config.with_client(client);
+        assert!(config.validate().is_ok());
+        let request = config.apply_headers(config.build_client().unwrap().get("http://127.0.0.1/")).build().unwrap();
+        assert_eq!(request.headers().get("CF-Access-Client-Id").unwrap(), "SOME_CLIENT_ID");
+        assert!(request.headers().get("CF-Access-Client-Id").unwrap().is_sensitive());
+        let request = HttpConfig::default().apply_headers(Client::new().get("http://127.0.0.1/")).build().unwrap();
+        assert!(request.headers().is_empty());
+
+        assert!(is_valid_header_name("X-Api-Key"));
+        assert!(!is_valid_header_name("X Api Key"));
+        assert!(!is_valid_header_name(""));
+        assert!(is_valid_header_value("Bearer abc\tdef"));
+        assert!(!is_valid_header_value("abc\r\nX-Injected: 1"));
+
+        assert_eq!(rebase("https://desec.io/api/v1", "http://127.0.0.1:8080/"), "http://127.0.0.1:8080/api/v1");
+        assert_eq!(rebase("https://update.dedyn.io/", "http://127.0.0.1:8080"), "http://127.0.0.1:8080/");
+        assert_eq!(
+            rebase("https://members.dyndns.org/nic/update?hostname={hostname}", "http://[::1]:80"),
+            "http://[::1]:80/nic/update?hostname={hostname}"
+        );
+        assert_eq!(rebase("https://example.com", "http://127.0.0.1:8080"), "http://127.0.0.1:8080");
+    }
+}
@@ -0,0 +1,11 @@
+// Copyright 2025 Amon Rayfa.
+// SPDX-License-Identifier: Apache-2.0.
+
+//! This module contains the synchronization helpers shared by the modules of the program.
+
+use std::sync::{Mutex, MutexGuard};
+
+/// Locks the mutex, recovering its content if a thread panicked while holding it.
+pub(crate) fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
@@ -0,0 +1,134 @@
+// Copyright 2025 Amon Rayfa.
+// SPDX-License-Identifier: Apache-2.0.
+
+//! This module contains the helpers used to test the applications embedding the library (and the library itself) without
+//! touching the cache file of the program. Along with the scriptable DNS providers of the
+//! [`mock`](wapi::api::provider::mock) module, it is only available in the tests and with the `testing` feature.
+
+use crate::api::cache::Cache;
+use crate::api::client::{CacheStore, FileStore};
+use crate::error::api::{Error, Result};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The number of temporary caches created by the process, which makes their directories unique.
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A cache kept in a file of its own temporary directory, which is removed when it is dropped. It is a
+/// [`CacheStore`](wapi::api::client::CacheStore), so it can be given to
+/// [`ClientBuilder::cache_store`](wapi::api::client::ClientBuilder) (see [`TempCache::store`]).
+pub struct TempCache {
+    dir: PathBuf,
+    store: FileStore,
+}
+
+impl TempCache {
+    /// Creates a temporary directory holding no cache yet. An error is returned if the directory cannot be created.
+    pub fn new() -> Result<TempCache> {
+        let id = COUNTER.fetch_add(1, Ordering::SeqCst);
+        let dir = std::env::temp_dir().join(format!("wapi-test-{}-{}", std::process::id(), id));
+        std::fs::create_dir_all(&dir).map_err(|e| Error::cache_write(&dir, e))?;
+        let store = FileStore::new(&dir.join("cache.json"));
+        Ok(TempCache { dir, store })
+    }
+
+    /// Creates a temporary directory holding the cache. An error is returned if the directory cannot be created or if the
+    /// cache cannot be written.
+    pub fn with_cache(mut cache: Cache) -> Result<TempCache> {
+        let temp = TempCache::new()?;
+        temp.store.save(&mut cache)?;
+        Ok(temp)
+    }
+
+    /// Returns the path of the cache file, which does not exist until the cache is saved.
+    pub fn path(&self) -> PathBuf {
+        self.dir.join("cache.json")
+    }
+
+    /// Returns a store keeping the cache in the temporary file, which remains usable as long as the temporary cache is not
+    /// dropped.
+    pub fn store(&self) -> FileStore {
+        FileStore::new(&self.path())
+    }
+
+    /// Returns the temporary directory, e.g. to write other files that are removed along with the cache.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+impl CacheStore for TempCache {
+    fn load(&self) -> Result<Cache> {
+        self.store.load()
+    }
+
+    fn save(&self, cache: &mut Cache) -> Result<()> {
+        self.store.save(cache)
+    }
+}
+
+impl Drop for TempCache {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.dir) {
+            tracing::warn!(dir = %self.dir.display(), "The temporary cache could not be removed: {}", e);
+        }
+    }
+}
+
+/// Serves the responses of the handler on a local port, and returns the URL of the server. The handler is given each request
+/// received (its request line, its headers, and the beginning of its body), and returns the status of the response (which may
+/// be followed by extra header lines) along with its body.
+#[cfg(test)]
+pub(crate) fn serve<F>(handler: F) -> String
+where
+    F: Fn(&str) -> (&'static str, &'static str) + Send + 'static,
+{
+    use std::io::{Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut buffer = [0; 4096];
+            let size = stream.read(&mut buffer).unwrap_or(0);
+            let (status, body) = handler(&String::from_utf8_lossy(&buffer[..size]));
+            let response = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", status, body.len(), body);
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+    url
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_temp_cache() {
+        let temp = TempCache::new().unwrap();
+        let other = TempCache::new().unwrap();
+        assert_ne!(temp.dir(), other.dir());
+        assert!(temp.dir().is_dir() && !temp.path().exists());
+        assert!(matches!(temp.load(), Err(Error::CacheRead { .. })));
+
+        // Ensures that the directory is removed along with the cache file once the temporary cache is dropped.
+        let mut cache = Cache::new();
+        temp.save(&mut cache).unwrap();
+        assert!(temp.path().is_file());
+        let dir = temp.dir().to_path_buf();
+        drop(temp);
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn test_serve() {
+        let url = serve(|request| match request.starts_with("GET /ip ") {
+            true => ("200 OK\r\nRetry-After: 120", "203.0.113.7"),
+            false => ("404 Not Found", ""),
+        });
+        let response = reqwest::blocking::get(format!("{}/ip", url)).unwrap();
+        assert_eq!(response.headers()["Retry-After"], "120");
+        assert_eq!(response.text().unwrap(), "203.0.113.7");
+        assert_eq!(reqwest::blocking::get(&url).unwrap().status(), 404);
+    }
+}
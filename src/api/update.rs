@@ -0,0 +1,910 @@
+// Copyright 2025 Amon Rayfa.
+// SPDX-License-Identifier: Apache-2.0.
+
+//! This module contains the structs and functions used to update the records of the cache's DNS providers so that they point
+//! to the detected IP addresses.
+
+use crate::api::cache::{Cache, ProviderView, RecordConfig, RecordType};
+use crate::api::cancel::{self, CancellationToken};
+use crate::api::provider::{self, Provider, UpdateStatus};
+use crate::api::request::HttpConfig;
+use crate::api::sync::lock;
+use crate::error::api::{Error, ProviderError, Result, ResultExt};
+use chrono::{DateTime, TimeDelta, Utc};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, ToSocketAddrs};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The struct used to configure an update run.
+#[derive(Debug, Clone)]
+pub struct UpdateOptions {
+    /// If `true`, the records are updated even if the detected IP addresses are the ones stored in the cache. The minimum
+    /// update interval of the DNS providers (see [`Capabilities`](wapi::api::provider::Capabilities)) is still respected.
+    pub force: bool,
+    /// The IDs of the DNS providers to update, which select all their accounts, or the references of the accounts to update
+    /// (see [`split_reference`](wapi::api::cache::split_reference)). If empty, the DNS providers are not filtered by ID.
+    pub providers: Vec<String>,
+    /// The labels selecting the DNS providers to update (a DNS provider is selected if it has at least one of them). If empty,
+    /// the DNS providers are not filtered by label.
+    pub labels: Vec<String>,
+    /// If set, the updated records are resolved after the run (with a growing backoff) until they point to their new address
+    /// or until this timeout elapses, and the outcome is stored in
+    /// [`RecordReport::propagation`](wapi::api::update::RecordReport). The verification never fails the run. Defaults to
+    /// `None`, which disables it.
+    pub verify_timeout: Option<Duration>,
+    /// If `true`, the records for which the DNS provider reported safety warnings (see
+    /// [`Provider::safety_warnings`](wapi::api::provider::Provider)) are skipped instead of being updated. Defaults to `false`,
+    /// in which case the warnings are only added to the report.
+    pub block_on_warnings: bool,
+    /// The maximum number of DNS providers updated at the same time (the records of a single DNS provider are always updated
+    /// one after the other). Setting it to 1 updates the DNS providers sequentially, and 0 is treated as 1. Defaults to 4.
+    pub max_concurrency: usize,
+    /// The maximum number of records that can change in a single run. If more records would be sent to their DNS provider
+    /// (e.g. because a detection glitch returned a wrong address), the run is aborted before anything is sent, unless it is
+    /// forced. Defaults to `None`, which disables the limit.
+    pub max_changes: Option<usize>,
+    /// The token cancelling the run, checked before each record is sent and between the attempts of the verification of the
+    /// propagation. The records that were not sent yet are skipped, the outcome of the other ones is still stored in the cache,
+    /// and the run is reported as cancelled (see [`UpdateReport::cancelled`](wapi::api::update::UpdateReport)). Defaults to
+    /// `None`.
+    pub cancel: Option<CancellationToken>,
+}
+
+impl Default for UpdateOptions {
+    fn default() -> Self {
+        UpdateOptions {
+            force: false,
+            providers: Vec::new(),
+            labels: Vec::new(),
+            verify_timeout: None,
+            block_on_warnings: false,
+            max_concurrency: 4,
+            max_changes: None,
+            cancel: None,
+        }
+    }
+}
+
+impl UpdateOptions {
+    /// Returns `true` if the DNS provider is selected by both the ID filter and the label selector of the options.
+    fn selects(&self, provider: &ProviderView) -> bool {
+        (self.providers.is_empty() || self.providers.iter().any(|id| id == provider.id || *id == provider.reference()))
+            && (self.labels.is_empty() || self.labels.iter().any(|l| provider.labels.contains(&l.trim().to_lowercase())))
+    }
+}
+
+/// The outcome of the update of a single record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordOutcome {
+    /// The record was changed to point to the new address.
+    Updated,
+    /// The record already pointed to the address, either according to the cache (in which case no request was sent) or
+    /// according to the DNS provider.
+    Unchanged,
+    /// The record was not updated, for the given reason (e.g. no address of its family was detected).
+    Skipped(String),
+    /// The record could not be updated, because of the given error.
+    Failed(String),
+    /// The record was not updated because its DNS provider is disabled (see
+    /// [`Cache::set_provider_enabled`](wapi::Cache)).
+    Disabled,
+}
+
+/// The struct holding the outcome of the update of a single record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordReport {
+    /// The ID of the DNS provider managing the record, followed by its alias if it has one (see
+    /// [`split_reference`](wapi::api::cache::split_reference)).
+    pub provider: String,
+    /// The fully qualified domain name of the record.
+    pub fqdn: String,
+    /// The type of the record.
+    pub record_type: RecordType,
+    /// The outcome of the update.
+    pub outcome: RecordOutcome,
+    /// The state of the propagation of the new address, if the record was updated and the verification is enabled (see
+    /// [`UpdateOptions::verify_timeout`](wapi::api::update::UpdateOptions)).
+    pub propagation: Option<Propagation>,
+    /// The safety warnings reported by the DNS provider before the update (e.g. about CAA records or DNSSEC).
+    pub warnings: Vec<String>,
+}
+
+impl RecordOutcome {
+    /// Returns the name of the outcome recorded in the `provider.update` spans, without its reason or error.
+    fn label(&self) -> &'static str {
+        match self {
+            RecordOutcome::Updated => "updated",
+            RecordOutcome::Unchanged => "unchanged",
+            RecordOutcome::Skipped(_) => "skipped",
+            RecordOutcome::Failed(_) => "failed",
+            RecordOutcome::Disabled => "disabled",
+        }
+    }
+}
+
+/// The state of the propagation of an updated record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Propagation {
+    /// The record resolves to its new address.
+    Confirmed,
+    /// The record did not resolve to its new address before the timeout elapsed (it may still propagate later).
+    Pending,
+}
+
+/// The struct summarizing an update run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UpdateReport {
+    /// The IPv4 address the A records were updated to, if one was detected.
+    pub ipv4: Option<Ipv4Addr>,
+    /// The IPv6 address the AAAA records were updated to, if one was detected.
+    pub ipv6: Option<Ipv6Addr>,
+    /// The outcome of each record, in the order of the DNS providers and their records in the cache.
+    pub records: Vec<RecordReport>,
+    /// Whether the run was cancelled with the token of its options before it completed (see
+    /// [`UpdateOptions::cancel`](wapi::api::update::UpdateOptions)).
+    pub cancelled: bool,
+}
+
+impl UpdateReport {
+    /// Returns the number of records that were updated.
+    pub fn updated(&self) -> usize {
+        self.records.iter().filter(|r| r.outcome == RecordOutcome::Updated).count()
+    }
+
+    /// Returns the number of records that were already up to date.
+    pub fn unchanged(&self) -> usize {
+        self.records.iter().filter(|r| r.outcome == RecordOutcome::Unchanged).count()
+    }
+
+    /// Returns the number of records that were skipped.
+    pub fn skipped(&self) -> usize {
+        self.records.iter().filter(|r| matches!(r.outcome, RecordOutcome::Skipped(_))).count()
+    }
+
+    /// Returns the number of records that could not be updated.
+    pub fn failed(&self) -> usize {
+        self.records.iter().filter(|r| matches!(r.outcome, RecordOutcome::Failed(_))).count()
+    }
+
+    /// Returns the number of records whose DNS provider is disabled.
+    pub fn disabled(&self) -> usize {
+        self.records.iter().filter(|r| r.outcome == RecordOutcome::Disabled).count()
+    }
+}
+
+/// Updates the records of the cache's DNS providers so that they point to the detected IP addresses, and returns a report of
+/// the run. A record is only sent to its DNS provider if the address of its family changed since the last run (or if the
+/// update is forced), so that the DNS providers never receive redundant updates. The addresses are stored in the cache once
+/// every record of their family is up to date, the DNS providers that succeeded are marked as such, the zone IDs resolved by
+/// the DNS providers are cached, and the run is appended to the run history. The cache is not saved. An error is returned
+/// (and the cache is left untouched) if more records would change than
+/// [`UpdateOptions::max_changes`](wapi::api::update::UpdateOptions) allows and the run is not forced.
+pub fn run(
+    cache: &mut Cache,
+    ipv4: Option<Ipv4Addr>,
+    ipv6: Option<Ipv6Addr>,
+    options: &UpdateOptions,
+    http: &HttpConfig,
+) -> Result<UpdateReport> {
+    let detected = Detected {
+        ipv4,
+        ipv6,
+        ipv4_changed: ipv4.is_some_and(|address| cache.ipv4_changed(address)),
+        ipv6_changed: ipv6.is_some_and(|address| cache.ipv6_changed(address)),
+    };
+    let mut report = UpdateReport { ipv4, ipv6, records: Vec::new(), cancelled: false };
+
+    // Collects the selected DNS providers along with their records and backends, so that they can be updated concurrently
+    // without accessing the cache.
+    let targets: Vec<Target> = cache
+        .providers()
+        .filter(|p| options.selects(p))
+        .map(|p| {
+            // Refers to the DNS provider by its reference, which tells its accounts apart.
+            let id = p.reference();
+            Target {
+                // Keys the minimum update interval on the last attempt, so that a failing record is not re-sent on every run.
+                last_attempt: [p.last_success, cache.last_error_for(&id).map(|(t, _)| t)]
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|t| cache.timestamp_config().parse(t))
+                    .max(),
+                records: cache.records(&id).unwrap_or_default().to_vec(),
+                enabled: p.enabled,
+                backend: match p.enabled {
+                    true => provider::build(cache, &id, http)
+                        .with_context(|| format!("Could not prepare the DNS provider \"{}\".", id)),
+                    false => Err(Error::Provider(String::from("build"), "The DNS provider is disabled.".into())),
+                },
+                id,
+            }
+        })
+        .collect();
+
+    // Aborts the run if too many records would change at once, since it most likely comes from a wrong detection.
+    if let (Some(max_changes), false) = (options.max_changes, options.force) {
+        let changes = targets
+            .iter()
+            .filter(|t| t.enabled)
+            .flat_map(|t| t.records.iter())
+            .filter(|r| matches!(pending(r, &detected), (Some(_), true)))
+            .count();
+        if changes > max_changes {
+            return Err(Error::Provider(
+                String::from("guardrail"),
+                format!(
+                    "{} records would change, exceeding the limit of {} changes per run (force the run to apply them).",
+                    changes, max_changes
+                )
+                .into(),
+            ));
+        }
+    }
+
+    // Updates the DNS providers with a bounded pool of workers, and collects the results in the order of the DNS providers.
+    let results = run_pool(&targets, options.max_concurrency, |target| update_provider(target, &detected, options));
+    for (target, (records, succeeded, failure)) in targets.iter().zip(results) {
+        // Marks the DNS provider as successful if one of its requests was accepted, and then records its last error if any.
+        if succeeded {
+            cache.record_provider_success(&target.id);
+        }
+        if let Some(message) = failure {
+            cache.record_provider_failure(&target.id, &message);
+        }
+        // Caches the zone IDs resolved by the backend (and clears the ones it reported as unknown), so that the zones are not
+        // looked up again on the next runs.
+        if let Ok(backend) = &target.backend {
+            for (domain, zone_id) in backend.zone_ids() {
+                if cache.zone_id(&target.id, &domain) != zone_id.as_deref() {
+                    cache.set_zone_id(&target.id, &domain, zone_id);
+                }
+            }
+        }
+        report.records.extend(records);
+    }
+
+    // Stores the addresses whose records are all up to date (ignoring the disabled ones, like the DNS providers that are not
+    // selected), so that the failed ones are retried on the next run.
+    let up_to_date = |uses: fn(&RecordConfig) -> bool| {
+        targets
+            .iter()
+            .flat_map(|t| t.records.iter())
+            .zip(report.records.iter())
+            .filter(|(record, _)| uses(record))
+            .all(|(_, r)| matches!(r.outcome, RecordOutcome::Updated | RecordOutcome::Unchanged | RecordOutcome::Disabled))
+    };
+    if let (Some(address), true) = (ipv4, up_to_date(RecordConfig::uses_ipv4)) {
+        cache.set_ipv4(address);
+    }
+    if let (Some(address), true) = (ipv6, up_to_date(RecordConfig::uses_ipv6)) {
+        cache.set_ipv6(address);
+    }
+
+    // Verifies that the updated records propagated, if enabled. Only the A and AAAA records are verified, since the values of
+    // the TXT and CNAME records do not resolve to an address.
+    if let Some(timeout) = options.verify_timeout {
+        let updated: Vec<&mut RecordReport> = report
+            .records
+            .iter_mut()
+            .filter(|r| r.outcome == RecordOutcome::Updated && matches!(r.record_type, RecordType::A | RecordType::Aaaa))
+            .collect();
+        let targets: Vec<(String, IpAddr)> = updated
+            .iter()
+            .filter_map(|r| match r.record_type {
+                RecordType::A => ipv4.map(|a| (r.fqdn.clone(), IpAddr::V4(a))),
+                RecordType::Aaaa => ipv6.map(|a| (r.fqdn.clone(), IpAddr::V6(a))),
+                RecordType::Txt | RecordType::Cname => None,
+            })
+            .collect();
+        let confirmed =
+            await_propagation(&targets, timeout, Duration::from_secs(1), options.cancel.as_ref(), verify_propagation);
+        for (record, confirmed) in updated.into_iter().zip(confirmed) {
+            record.propagation = Some(if confirmed { Propagation::Confirmed } else { Propagation::Pending });
+        }
+    }
+
+    report.cancelled = cancel::is_cancelled(options.cancel.as_ref());
+    cache.record_run(report.updated(), report.unchanged(), report.failed());
+    Ok(report)
+}
+
+/// The detected IP addresses of an update run, and whether they changed since the last run.
+struct Detected {
+    ipv4: Option<Ipv4Addr>,
+    ipv6: Option<Ipv6Addr>,
+    ipv4_changed: bool,
+    ipv6_changed: bool,
+}
+
+/// What is pushed to a record: the address of its family for the A and AAAA records, or the value rendered from its template
+/// for the TXT and CNAME records.
+enum Payload {
+    Address(IpAddr),
+    Value(String),
+}
+
+impl Payload {
+    /// Sends the payload to the DNS provider.
+    fn send(&self, backend: &dyn Provider, record: &RecordConfig) -> std::result::Result<UpdateStatus, ProviderError> {
+        match self {
+            Payload::Address(address) if record.static_values.is_empty() => backend.update_record(record, *address),
+            Payload::Address(address) => backend.update_record_set(record, &record.addresses(*address)),
+            Payload::Value(value) => backend.update_value(record, value),
+        }
+    }
+}
+
+/// Returns what would be pushed to the record (if the addresses it needs were detected), and whether it changed since the last
+/// run.
+fn pending(record: &RecordConfig, detected: &Detected) -> (Option<Payload>, bool) {
+    match record.record_type {
+        RecordType::A => (detected.ipv4.map(|a| Payload::Address(IpAddr::V4(a))), detected.ipv4_changed),
+        RecordType::Aaaa => (detected.ipv6.map(|a| Payload::Address(IpAddr::V6(a))), detected.ipv6_changed),
+        RecordType::Txt | RecordType::Cname => (
+            record.render_value(detected.ipv4, detected.ipv6).map(Payload::Value),
+            (record.uses_ipv4() && detected.ipv4_changed) || (record.uses_ipv6() && detected.ipv6_changed),
+        ),
+    }
+}
+
+/// A DNS provider of an update run, along with everything needed to update its records.
+struct Target {
+    id: String,
+    last_attempt: Option<DateTime<Utc>>,
+    records: Vec<RecordConfig>,
+    enabled: bool,
+    backend: Result<Box<dyn Provider>>,
+}
+
+/// Updates the records of a DNS provider one after the other, and returns their reports along with whether at least one
+/// request was accepted by the DNS provider and the last error that occurred (if any). Each record is traced in a
+/// `provider.update` span, which records its duration and outcome (but never the credentials of the DNS provider).
+fn update_provider(target: &Target, detected: &Detected, options: &UpdateOptions) -> (Vec<RecordReport>, bool, Option<String>) {
+    let id = &target.id;
+    let mut reports = Vec::new();
+    let mut succeeded = false;
+    let mut failure = None;
+
+    for record in target.records.iter() {
+        let span = tracing::info_span!(
+            "provider.update",
+            provider = %id,
+            record = %record.fqdn(),
+            record_type = %record.record_type,
+            duration_ms = tracing::field::Empty,
+            outcome = tracing::field::Empty,
+        )
+        .entered();
+        let start = Instant::now();
+        let (payload, changed) = pending(record, detected);
+        let mut warnings = Vec::new();
+
+        let outcome = match (&target.backend, &payload) {
+            _ if !target.enabled => RecordOutcome::Disabled,
+            (_, None) => match record.record_type {
+                RecordType::A | RecordType::Aaaa => {
+                    RecordOutcome::Skipped(format!("No {} address was detected.", record.record_type))
+                }
+                RecordType::Txt | RecordType::Cname => {
+                    RecordOutcome::Skipped(String::from("An address used by the value template was not detected."))
+                }
+            },
+            (_, Some(_)) if !changed && !options.force => RecordOutcome::Unchanged,
+            _ if cancel::is_cancelled(options.cancel.as_ref()) => {
+                RecordOutcome::Skipped(String::from("The run was cancelled."))
+            }
+            (Err(e), _) => RecordOutcome::Failed(format!("{:?}", e)),
+            (Ok(backend), Some(payload)) => match backend.capabilities().min_update_interval {
+                // Respects the abuse-prevention window of the DNS provider, even if the update is forced.
+                Some(interval) if within_interval(target.last_attempt, interval, Utc::now()) => RecordOutcome::Skipped(
+                    format!(
+                        "An update was sent to the DNS provider less than {:?} ago, which is its minimum update interval.",
+                        interval
+                    ),
+                ),
+                _ => {
+                    // Checks whether the update could interfere with the configuration of the zone, only once the record is
+                    // known to be sent so that the skipped records cost no request.
+                    warnings = backend.safety_warnings(record);
+                    match options.block_on_warnings && !warnings.is_empty() {
+                        true => {
+                            RecordOutcome::Skipped(String::from("The DNS provider reported safety warnings about the update."))
+                        }
+                        false => match payload.send(backend.as_ref(), record) {
+                            Ok(status) => {
+                                succeeded = true;
+                                match status {
+                                    UpdateStatus::Updated => RecordOutcome::Updated,
+                                    UpdateStatus::Unchanged => RecordOutcome::Unchanged,
+                                }
+                            }
+                            Err(e) => RecordOutcome::Failed(format!(
+                                "{:?}",
+                                Error::from(e).context(format!("Could not update the record \"{}\".", record.fqdn()))
+                            )),
+                        },
+                    }
+                }
+            },
+        };
+
+        span.record("duration_ms", u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX));
+        span.record("outcome", outcome.label());
+        if let RecordOutcome::Failed(e) = &outcome {
+            failure = Some(format!("{}: {}", record.fqdn(), e));
+        }
+        for warning in warnings.iter() {
+            tracing::warn!(provider = %id, record = %record.fqdn(), warning = %warning, "The update may not be safe.");
+        }
+        match &outcome {
+            RecordOutcome::Updated => tracing::info!(provider = %id, record = %record.fqdn(), "The record was updated."),
+            RecordOutcome::Failed(e) => {
+                tracing::warn!(provider = %id, record = %record.fqdn(), error = %e, "The record could not be updated.")
+            }
+            RecordOutcome::Skipped(reason) => {
+                tracing::debug!(provider = %id, record = %record.fqdn(), reason = %reason, "The record was skipped.")
+            }
+            RecordOutcome::Unchanged => tracing::debug!(provider = %id, record = %record.fqdn(), "The record is up to date."),
+            RecordOutcome::Disabled => {
+                tracing::debug!(provider = %id, record = %record.fqdn(), "The DNS provider of the record is disabled.")
+            }
+        }
+        reports.push(RecordReport {
+            provider: id.clone(),
+            fqdn: record.fqdn(),
+            record_type: record.record_type,
+            outcome,
+            propagation: None,
+            warnings,
+        });
+    }
+
+    (reports, succeeded, failure)
+}
+
+/// Applies the work to every item with at most `max_concurrency` workers running at the same time (at least one), and returns
+/// the results in the order of the items regardless of the order in which they completed.
+fn run_pool<T, R, F>(items: &[T], max_concurrency: usize, work: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new(items.iter().map(|_| None).collect());
+
+    // Runs the workers with the subscriber and the span of the caller, which are not inherited by the threads.
+    let (dispatch, span) = (tracing::dispatcher::get_default(|d| d.clone()), tracing::Span::current());
+    std::thread::scope(|scope| {
+        for _ in 0..max_concurrency.clamp(1, items.len().max(1)) {
+            scope.spawn(|| {
+                // Takes the next item until there is none left.
+                tracing::dispatcher::with_default(&dispatch, || {
+                    span.in_scope(|| loop {
+                        let index = next.fetch_add(1, Ordering::SeqCst);
+                        let item = match items.get(index) {
+                            Some(item) => item,
+                            None => break,
+                        };
+                        let result = work(item);
+                        lock(&results)[index] = Some(result);
+                    })
+                })
+            });
+        }
+    });
+
+    results.into_inner().unwrap_or_else(|err| err.into_inner()).into_iter().flatten().collect()
+}
+
+/// Returns `true` if the fully qualified domain name resolves (through the resolver of the operating system) to the address.
+pub fn verify_propagation(fqdn: &str, address: IpAddr) -> bool {
+    (fqdn, 0).to_socket_addrs().is_ok_and(|mut addresses| addresses.any(|a| a.ip() == address))
+}
+
+/// Resolves the targets with the resolver until each of them points to its address or until the timeout elapses, waiting a
+/// doubling backoff between the attempts, or until the token (if any) is cancelled. Returns whether each target was confirmed,
+/// in the order of the targets.
+fn await_propagation<F>(
+    targets: &[(String, IpAddr)],
+    timeout: Duration,
+    backoff: Duration,
+    cancel: Option<&CancellationToken>,
+    resolve: F,
+) -> Vec<bool>
+where
+    F: Fn(&str, IpAddr) -> bool,
+{
+    let deadline = Instant::now() + timeout;
+    let mut backoff = backoff;
+    let mut confirmed = vec![false; targets.len()];
+
+    loop {
+        for (i, (fqdn, address)) in targets.iter().enumerate() {
+            if !confirmed[i] {
+                confirmed[i] = resolve(fqdn, *address);
+            }
+        }
+
+        // Stops once every target is confirmed, if the next attempt would happen after the deadline, or if cancelled.
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if confirmed.iter().all(|c| *c) || remaining < backoff || cancel::is_cancelled(cancel) {
+            return confirmed;
+        }
+        cancel::sleep(backoff, cancel);
+        if cancel::is_cancelled(cancel) {
+            return confirmed;
+        }
+        backoff = backoff.saturating_mul(2);
+    }
+}
+
+/// Returns `true` if less than the interval has elapsed between the last attempt (successful or not) and now. `false` is
+/// returned if there is no last attempt (or if its timestamp could not be parsed).
+fn within_interval(last_attempt: Option<DateTime<Utc>>, interval: Duration, now: DateTime<Utc>) -> bool {
+    let Some(last_attempt) = last_attempt else {
+        return false;
+    };
+
+    match TimeDelta::from_std(interval) {
+        Ok(interval) => now - last_attempt < interval,
+        Err(_) => true,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::cache::{TimestampConfig, TimestampZone};
+
+    /// Serves the body to every request received on a local port, and returns the URL of the server.
+    #[cfg(feature = "provider-dyndns2")]
+    fn serve(body: &'static str) -> String {
+        crate::api::testing::serve(move |_| ("200 OK", body))
+    }
+
+    #[test]
+    #[cfg(feature = "provider-dyndns2")]
+    fn test_run() {
+        let mut cache = Cache::new();
+        let http = HttpConfig::default();
+        let ipv4 = Ipv4Addr::new(203, 0, 113, 7);
+        cache.add_dns_provider("dyndns2".to_string(), "SOME_USERNAME".to_string(), "SOME_PASSWORD".to_string());
+        cache.set_provider_param("dyndns2", "update_url", Some(format!("{}/nic/update?hostname={{hostname}}", serve("good"))));
+        cache.add_record("dyndns2", RecordConfig::new("example.com", "", RecordType::A, 300)).unwrap();
+        cache.add_record("dyndns2", RecordConfig::new("example.com", "", RecordType::Aaaa, 300)).unwrap();
+
+        let report = run(&mut cache, Some(ipv4), None, &UpdateOptions::default(), &http).unwrap();
+        assert_eq!(report.records.len(), 2);
+        assert_eq!(report.records[0].fqdn, "example.com");
+        assert_eq!(report.records[0].outcome, RecordOutcome::Updated);
+        assert!(matches!(report.records[1].outcome, RecordOutcome::Skipped(_)));
+        assert!(report.records[0].warnings.is_empty());
+        assert!(!cache.ipv4_changed(ipv4));
+        assert!(cache.provider("dyndns2").unwrap().last_success.is_some());
+        assert_eq!(cache.last_error_for("dyndns2"), None);
+        assert_eq!(cache.run_history().last().unwrap().updated, 1);
+
+        // Ensures that no update is sent when the address did not change.
+        let report = run(&mut cache, Some(ipv4), None, &UpdateOptions::default(), &http).unwrap();
+        assert_eq!(report.unchanged(), 1);
+
+        // Ensures that a forced update still respects the minimum update interval of the DNS provider.
+        let options = UpdateOptions { force: true, ..UpdateOptions::default() };
+        let report = run(&mut cache, Some(ipv4), None, &options, &http).unwrap();
+        assert!(matches!(report.records[0].outcome, RecordOutcome::Skipped(_)));
+        assert_eq!(report.updated(), 0);
+
+        // Ensures that the DNS providers that are not selected are left out of the run.
+        let options = UpdateOptions { providers: vec![String::from("porkbun")], ..UpdateOptions::default() };
+        assert!(run(&mut cache, Some(ipv4), None, &options, &http).unwrap().records.is_empty());
+        let options = UpdateOptions { labels: vec![String::from("prod")], ..UpdateOptions::default() };
+        assert!(run(&mut cache, Some(ipv4), None, &options, &http).unwrap().records.is_empty());
+        cache.set_provider_labels("dyndns2", vec![String::from("Prod")]);
+        assert_eq!(run(&mut cache, Some(ipv4), None, &options, &http).unwrap().records.len(), 2);
+    }
+
+    #[test]
+    #[cfg(feature = "provider-dyndns2")]
+    fn test_run_failure_interval() {
+        let mut cache = Cache::new();
+        let http = HttpConfig::default();
+        let ipv4 = Ipv4Addr::new(203, 0, 113, 7);
+        cache.add_dns_provider("dyndns2".to_string(), "SOME_USERNAME".to_string(), "SOME_PASSWORD".to_string());
+        let url = format!("{}/nic/update?hostname={{hostname}}", serve("badauth"));
+        cache.set_provider_param("dyndns2", "update_url", Some(url));
+        cache.add_record("dyndns2", RecordConfig::new("example.com", "", RecordType::A, 300)).unwrap();
+
+        let report = run(&mut cache, Some(ipv4), None, &UpdateOptions::default(), &http).unwrap();
+        assert!(matches!(report.records[0].outcome, RecordOutcome::Failed(_)));
+        assert!(cache.provider("dyndns2").unwrap().last_success.is_none());
+        assert!(cache.last_error_for("dyndns2").is_some());
+
+        // Ensures that a failing record is not re-sent within the minimum update interval of the DNS provider.
+        let report = run(&mut cache, Some(ipv4), None, &UpdateOptions::default(), &http).unwrap();
+        assert!(matches!(report.records[0].outcome, RecordOutcome::Skipped(_)));
+        assert_eq!(report.failed(), 0);
+    }
+
+    #[test]
+    fn test_run_mock() {
+        let mut cache = Cache::new();
+        let http = HttpConfig::default();
+        let ipv4 = Ipv4Addr::new(203, 0, 113, 7);
+        let ipv6 = "2001:db8::1".parse::<Ipv6Addr>().unwrap();
+        for id in ["mock:success", "mock:fail", "mock:unchanged"] {
+            cache.add_dns_provider(id.to_string(), "SOME_API_KEY".to_string(), "SOME_SECRET_API_KEY".to_string());
+        }
+        cache.add_record("mock:success", RecordConfig::new("example.com", "", RecordType::A, 300)).unwrap();
+        cache.add_record("mock:success", RecordConfig::new("example.com", "", RecordType::Aaaa, 300)).unwrap();
+        cache.add_record("mock:fail", RecordConfig::new("example.org", "", RecordType::A, 300)).unwrap();
+        cache.add_record("mock:unchanged", RecordConfig::new("example.net", "", RecordType::Aaaa, 300)).unwrap();
+
+        let report = run(&mut cache, Some(ipv4), Some(ipv6), &UpdateOptions::default(), &http).unwrap();
+        let outcomes: Vec<(&str, &str, &RecordOutcome)> =
+            report.records.iter().map(|r| (r.provider.as_str(), r.fqdn.as_str(), &r.outcome)).collect();
+        assert_eq!(outcomes.len(), 4);
+        assert_eq!(outcomes[0], ("mock:success", "example.com", &RecordOutcome::Updated));
+        assert_eq!(outcomes[1], ("mock:success", "example.com", &RecordOutcome::Updated));
+        assert_eq!(outcomes[2].0, "mock:fail");
+        assert!(matches!(outcomes[2].2, RecordOutcome::Failed(_)));
+        assert_eq!(outcomes[3], ("mock:unchanged", "example.net", &RecordOutcome::Unchanged));
+        assert_eq!((report.updated(), report.unchanged(), report.failed()), (2, 1, 1));
+
+        // Ensures that the failed family is retried on the next run while the other one is stored.
+        assert!(cache.ipv4_changed(ipv4));
+        assert!(!cache.ipv6_changed(ipv6));
+        assert!(cache.last_error_for("mock:fail").is_some());
+        assert!(cache.last_error_for("mock:success").is_none());
+
+        // Ensures that only the records of the changed family are sent again.
+        let options = UpdateOptions { max_concurrency: 1, ..UpdateOptions::default() };
+        let report = run(&mut cache, Some(ipv4), Some(ipv6), &options, &http).unwrap();
+        assert_eq!((report.updated(), report.unchanged(), report.failed()), (1, 2, 1));
+
+        // Ensures that the records of a disabled DNS provider are skipped, without preventing the address from being stored.
+        cache.set_provider_enabled("mock:fail", false);
+        assert!(!cache.provider("mock:fail").unwrap().enabled);
+        let report = run(&mut cache, Some(ipv4), Some(ipv6), &options, &http).unwrap();
+        assert_eq!(report.records[2].outcome, RecordOutcome::Disabled);
+        assert_eq!((report.updated(), report.unchanged(), report.failed(), report.disabled()), (1, 2, 0, 1));
+        assert!(!cache.ipv4_changed(ipv4));
+    }
+
+    #[test]
+    fn test_await_propagation() {
+        let targets = vec![
+            (String::from("a.example.com"), "203.0.113.7".parse().unwrap()),
+            (String::from("b.example.com"), "203.0.113.7".parse().unwrap()),
+        ];
+
+        // Ensures that a target is confirmed once it resolves to its address, and stays pending otherwise.
+        let attempts = std::cell::Cell::new(0);
+        let resolve = |fqdn: &str, _: IpAddr| {
+            attempts.set(attempts.get() + 1);
+            fqdn == "a.example.com" && attempts.get() > 2
+        };
+        let confirmed = await_propagation(&targets, Duration::from_millis(50), Duration::from_millis(5), None, resolve);
+        assert_eq!(confirmed, [true, false]);
+
+        let confirmed = await_propagation(&targets, Duration::ZERO, Duration::from_millis(5), None, |_, _| true);
+        assert_eq!(confirmed, [true, true]);
+        assert!(await_propagation(&[], Duration::from_secs(60), Duration::from_secs(1), None, |_, _| false).is_empty());
+
+        // Ensures that the verification stops waiting once it is cancelled.
+        let token = CancellationToken::new();
+        let canceller = token.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            canceller.cancel();
+        });
+        let start = Instant::now();
+        let confirmed =
+            await_propagation(&targets, Duration::from_secs(60), Duration::from_secs(30), Some(&token), |_, _| false);
+        assert_eq!(confirmed, [false, false]);
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert!(verify_propagation("localhost", "127.0.0.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_run_pool() {
+        let items: Vec<usize> = (0..20).collect();
+        let running = AtomicUsize::new(0);
+        let max_running = AtomicUsize::new(0);
+        let work = |item: &usize| {
+            let current = running.fetch_add(1, Ordering::SeqCst) + 1;
+            max_running.fetch_max(current, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(((20 - item) % 3) as u64));
+            running.fetch_sub(1, Ordering::SeqCst);
+            item * 2
+        };
+
+        // Ensures that the results are in the order of the items, and that the concurrency is bounded.
+        let results = run_pool(&items, 3, work);
+        assert_eq!(results, items.iter().map(|i| i * 2).collect::<Vec<_>>());
+        assert!(max_running.load(Ordering::SeqCst) <= 3);
+
+        max_running.store(0, Ordering::SeqCst);
+        assert_eq!(run_pool(&items, 0, work).len(), 20);
+        assert_eq!(max_running.load(Ordering::SeqCst), 1);
+        assert!(run_pool(&[] as &[usize], 4, work).is_empty());
+    }
+
+    #[test]
+    fn test_within_interval() {
+        let timestamps = TimestampConfig { zone: TimestampZone::Utc, ..TimestampConfig::default() };
+        let now = timestamps.parse("2025-01-01 12:00:00.000").unwrap();
+        let interval = Duration::from_secs(600);
+        assert!(within_interval(timestamps.parse("2025-01-01 11:55:00.000"), interval, now));
+        assert!(!within_interval(timestamps.parse("2025-01-01 11:50:00.000"), interval, now));
+        assert!(within_interval(timestamps.parse("2025-01-01T12:55:00.000+01:00"), interval, now));
+        assert!(!within_interval(timestamps.parse("not a timestamp"), interval, now));
+        assert!(!within_interval(None, interval, now));
+    }
+
+    #[test]
+    fn test_run_templates() {
+        let mut cache = Cache::new();
+        let http = HttpConfig::default();
+        let ipv4 = Ipv4Addr::new(203, 0, 113, 7);
+        let ipv6 = "2001:db8::1".parse::<Ipv6Addr>().unwrap();
+        for id in ["mock:success", "mock:fail"] {
+            cache.add_dns_provider(id.to_string(), "SOME_API_KEY".to_string(), "SOME_SECRET_API_KEY".to_string());
+        }
+        let spf = RecordConfig::new("example.com", "", RecordType::Txt, 300).with_value_template("v=spf1 ip4:{ipv4} -all");
+        cache.add_record("mock:success", spf).unwrap();
+        let alias = RecordConfig::new("example.org", "home", RecordType::Cname, 300).with_value_template("{ipv6}.example.net");
+        cache.add_record("mock:fail", alias).unwrap();
+
+        // Ensures that a failed templated record prevents the address it uses from being stored, but not the other one.
+        let report = run(&mut cache, Some(ipv4), Some(ipv6), &UpdateOptions::default(), &http).unwrap();
+        assert_eq!(report.records[0].outcome, RecordOutcome::Updated);
+        assert!(matches!(report.records[1].outcome, RecordOutcome::Failed(_)));
+        assert!(!cache.ipv4_changed(ipv4));
+        assert!(cache.ipv6_changed(ipv6));
+
+        // Ensures that the templated records are skipped if an address they use was not detected.
+        let report = run(&mut cache, None, None, &UpdateOptions { force: true, ..UpdateOptions::default() }, &http).unwrap();
+        assert_eq!(report.skipped(), 2);
+    }
+
+    #[test]
+    fn test_max_changes() {
+        let mut cache = Cache::new();
+        let http = HttpConfig::default();
+        let ipv4 = Ipv4Addr::new(203, 0, 113, 7);
+        cache.add_dns_provider(String::from("mock:success"), String::new(), String::new());
+        for subdomain in ["a", "b", "c"] {
+            cache.add_record("mock:success", RecordConfig::new("example.com", subdomain, RecordType::A, 300)).unwrap();
+        }
+
+        // Ensures that the run is aborted without touching the cache if too many records would change.
+        let options = UpdateOptions { max_changes: Some(2), ..UpdateOptions::default() };
+        let err = run(&mut cache, Some(ipv4), None, &options, &http).unwrap_err();
+        assert_eq!(err.code(), "E_PROVIDER_GUARDRAIL");
+        assert!(cache.ipv4_changed(ipv4));
+        assert!(cache.run_history().is_empty());
+
+        // Ensures that the limit is bypassed when the run is forced, and that the unchanged records are not counted.
+        let options = UpdateOptions { force: true, ..options };
+        assert_eq!(run(&mut cache, Some(ipv4), None, &options, &http).unwrap().updated(), 3);
+        let options = UpdateOptions { max_changes: Some(0), ..UpdateOptions::default() };
+        assert_eq!(run(&mut cache, Some(ipv4), None, &options, &http).unwrap().unchanged(), 3);
+    }
+
+    #[test]
+    #[cfg(feature = "provider-desec")]
+    fn test_run_static_values() {
+        let mut cache = Cache::new();
+        let http = HttpConfig::default();
+        let ipv4 = Ipv4Addr::new(203, 0, 113, 7);
+        let statics = ["198.51.100.1".parse().unwrap()];
+        cache.add_dns_provider(String::from("mock:success"), String::new(), String::new());
+        cache.add_dns_provider(String::from("desec"), String::from("SOME_TOKEN"), String::new());
+        let record = RecordConfig::new("example.com", "www", RecordType::A, 300).with_static_values(&statics);
+        cache.add_record("mock:success", record.clone()).unwrap();
+        cache.add_record("desec", record).unwrap();
+
+        // Ensures that the DNS providers that cannot set several addresses fail clearly, before sending anything.
+        let report = run(&mut cache, Some(ipv4), None, &UpdateOptions::default(), &http).unwrap();
+        assert_eq!(report.records[0].outcome, RecordOutcome::Updated);
+        assert!(matches!(&report.records[1].outcome, RecordOutcome::Failed(e) if e.contains("several addresses")));
+    }
+
+    #[test]
+    fn test_run_zone_ids() {
+        let mut cache = Cache::new();
+        let http = HttpConfig::default();
+        let ipv4 = Ipv4Addr::new(203, 0, 113, 7);
+        let resolved = provider::mock::MockProvider::scripted("mock:zones").unwrap().with_zone_ids([
+            (String::from("example.com"), Some(String::from("SOME_ZONE_ID"))),
+            (String::from("example.org"), None),
+        ]);
+        resolved.register();
+        cache.add_dns_provider(String::from("mock:zones"), String::new(), String::new());
+        cache.add_record("mock:zones", RecordConfig::new("example.com", "www", RecordType::A, 300)).unwrap();
+        let stale = RecordConfig {
+            zone_id: Some(String::from("SOME_STALE_ZONE_ID")),
+            ..RecordConfig::new("example.org", "www", RecordType::A, 300)
+        };
+        cache.add_record("mock:zones", stale).unwrap();
+
+        // Ensures that the zone IDs resolved by the DNS provider are cached, and that the ones it reported as unknown are
+        // cleared.
+        run(&mut cache, Some(ipv4), None, &UpdateOptions::default(), &http).unwrap();
+        assert_eq!(cache.zone_id("mock:zones", "example.com"), Some("SOME_ZONE_ID"));
+        assert_eq!(cache.zone_id("mock:zones", "example.org"), None);
+        provider::mock::MockProvider::unregister("mock:zones");
+    }
+
+    #[test]
+    fn test_run_warnings() {
+        use provider::mock::{MockMethod, MockProvider, MockResponse};
+
+        let mut cache = Cache::new();
+        let http = HttpConfig::default();
+        let ipv4 = Ipv4Addr::new(203, 0, 113, 7);
+        let warning = "The zone \"example.com\" is signed with DNSSEC.";
+        let provider = MockProvider::scripted("mock:warnings")
+            .unwrap()
+            .respond_with(MockMethod::SafetyWarnings, [MockResponse::Warn(warning); 3]);
+        provider.register();
+        let record = RecordConfig::new("example.com", "www", RecordType::A, 300);
+        cache.add_dns_provider(String::from("mock:warnings"), String::new(), String::new());
+        cache.add_record("mock:warnings", record.clone()).unwrap();
+
+        // Ensures that the warnings are added to the report of the record, which is still updated by default.
+        let report = run(&mut cache, Some(ipv4), None, &UpdateOptions::default(), &http).unwrap();
+        assert_eq!(report.records[0].outcome, RecordOutcome::Updated);
+        assert_eq!(report.records[0].warnings, vec![String::from(warning)]);
+
+        // Ensures that the record is skipped without being sent when the warnings block the updates.
+        let options = UpdateOptions { force: true, block_on_warnings: true, ..UpdateOptions::default() };
+        let report = run(&mut cache, Some(ipv4), None, &options, &http).unwrap();
+        assert!(matches!(&report.records[0].outcome, RecordOutcome::Skipped(r) if r.contains("safety warnings")));
+        assert_eq!(report.records[0].warnings, vec![String::from(warning)]);
+        assert_eq!(provider.calls().len(), 1);
+
+        // Ensures that the zone is not inspected for the records that are not sent.
+        let report = run(&mut cache, Some(ipv4), None, &UpdateOptions::default(), &http).unwrap();
+        assert_eq!(report.records[0].outcome, RecordOutcome::Unchanged);
+        assert!(report.records[0].warnings.is_empty());
+        assert_eq!(provider.safety_warnings(&record), vec![String::from(warning)]);
+        MockProvider::unregister("mock:warnings");
+    }
+
+    #[test]
+    fn test_run_cancel() {
+        let mut cache = Cache::new();
+        let http = HttpConfig::default();
+        let ipv4 = Ipv4Addr::new(203, 0, 113, 7);
+        let delay = Duration::from_millis(200);
+        let slow = provider::mock::MockProvider::scripted("mock:slow").unwrap().with_delay(delay);
+        slow.register();
+        cache.add_dns_provider(String::from("mock:slow"), String::new(), String::new());
+        for subdomain in ["a", "b", "c"] {
+            cache.add_record("mock:slow", RecordConfig::new("example.com", subdomain, RecordType::A, 300)).unwrap();
+        }
+
+        // Cancels the run while the second record is being sent, which is completed before the run stops.
+        let token = CancellationToken::new();
+        let canceller = token.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(delay * 3 / 2);
+            canceller.cancel();
+        });
+        let options = UpdateOptions { cancel: Some(token), ..UpdateOptions::default() };
+        let report = run(&mut cache, Some(ipv4), None, &options, &http).unwrap();
+        assert_eq!(slow.calls().len(), 2);
+        assert!(report.cancelled);
+        assert_eq!((report.updated(), report.skipped()), (2, 1));
+        assert_eq!(report.records[2].outcome, RecordOutcome::Skipped(String::from("The run was cancelled.")));
+
+        // Ensures that the completed work is stored, and that the address is kept pending so that the next run retries.
+        assert!(cache.ipv4_changed(ipv4));
+        assert_eq!(cache.run_history().last().map(|r| r.updated), Some(2));
+        assert!(cache.providers().next().is_some_and(|p| p.last_success.is_some()));
+        assert!(!run(&mut cache, Some(ipv4), None, &UpdateOptions::default(), &http).unwrap().cancelled);
+    }
+}
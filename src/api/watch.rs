@@ -0,0 +1,267 @@
+// Copyright 2025 Amon Rayfa.
+// SPDX-License-Identifier: Apache-2.0.
+
+//! This module contains the stream of the changes of the public IP addresses returned by
+//! [`Client::watch`](wapi::Client), which lets the asynchronous applications react to them (e.g. by reloading the
+//! configuration of a reverse proxy).
+
+use crate::api::ip::DetectionResult;
+use crate::api::sync::lock;
+use futures_core::Stream;
+use std::collections::VecDeque;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::{Interval, MissedTickBehavior};
+
+/// The maximum number of events kept for a consumer that does not read them fast enough. Once it is reached, the oldest event
+/// is dropped for each new one (see [`IpWatch::dropped`]), so that a slow consumer never stalls the detection.
+pub const CAPACITY: usize = 64;
+
+/// A change of a public IP address observed by a detection cycle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IpChangeEvent {
+    /// The previous address of the family, or `None` if none was known yet (neither detected nor stored in the cache).
+    pub old: Option<IpAddr>,
+    /// The newly detected address.
+    pub new: IpAddr,
+    /// The endpoint that returned the new address, if known.
+    pub source: Option<String>,
+}
+
+/// The stream of the changes of the public IP addresses, in the order they were observed. The detection cycles run on a
+/// background task of tokio, which is stopped when the stream is dropped. The stream ends once its ticks end (see
+/// [`Client::watch_with`](wapi::Client)) and the remaining events were read.
+pub struct IpWatch {
+    shared: Arc<Mutex<Shared>>,
+    task: JoinHandle<()>,
+}
+
+/// The state shared between an [`IpWatch`] and its background task.
+#[derive(Default)]
+struct Shared {
+    events: VecDeque<IpChangeEvent>,
+    dropped: u64,
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+impl IpWatch {
+    /// Returns the next event, or `None` once the stream ended. This is a shortcut for `StreamExt::next`, so that the stream
+    /// can be read without depending on a stream utility crate.
+    pub async fn next(&mut self) -> Option<IpChangeEvent> {
+        std::future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+
+    /// Returns the number of events that were dropped because the consumer did not read them fast enough (see
+    /// [`CAPACITY`]).
+    pub fn dropped(&self) -> u64 {
+        lock(&self.shared).dropped
+    }
+}
+
+impl Stream for IpWatch {
+    type Item = IpChangeEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<IpChangeEvent>> {
+        let mut shared = lock(&self.shared);
+        match shared.events.pop_front() {
+            Some(event) => Poll::Ready(Some(event)),
+            None if shared.closed => Poll::Ready(None),
+            None => {
+                shared.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for IpWatch {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// The ticks of the internal scheduler of [`Client::watch`](wapi::Client). The first tick is immediate, and the next ones are
+/// delayed if a detection cycle takes longer than the interval.
+pub(crate) struct IntervalTicks(Interval);
+
+impl IntervalTicks {
+    /// Creates the ticks of the interval. It must be called within a tokio runtime.
+    pub(crate) fn new(period: Duration) -> IntervalTicks {
+        let mut interval = tokio::time::interval(period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        IntervalTicks(interval)
+    }
+}
+
+impl Stream for IntervalTicks {
+    type Item = ();
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<()>> {
+        self.0.poll_tick(cx).map(|_| Some(()))
+    }
+}
+
+/// Spawns the background task running a detection cycle with the detector on each tick, and returns the stream of the
+/// changes it observes relative to the initial addresses. It must be called within a tokio runtime.
+pub(crate) fn spawn<D, S>(detector: D, ipv4: Option<Ipv4Addr>, ipv6: Option<Ipv6Addr>, ticks: S) -> IpWatch
+where
+    D: Fn() -> DetectionResult + Send + Sync + 'static,
+    S: Stream + Send + Unpin + 'static,
+{
+    let shared = Arc::new(Mutex::new(Shared::default()));
+    let task = tokio::spawn(drive(shared.clone(), Arc::new(detector), (ipv4, ipv6), ticks));
+    IpWatch { shared, task }
+}
+
+/// Runs a detection cycle on each tick, and publishes the changes of the addresses until the ticks end.
+async fn drive<D, S>(
+    shared: Arc<Mutex<Shared>>,
+    detector: Arc<D>,
+    mut addresses: (Option<Ipv4Addr>, Option<Ipv6Addr>),
+    mut ticks: S,
+) where
+    D: Fn() -> DetectionResult + Send + Sync + 'static,
+    S: Stream + Send + Unpin + 'static,
+{
+    while std::future::poll_fn(|cx| Pin::new(&mut ticks).poll_next(cx)).await.is_some() {
+        // Runs the blocking detection on the blocking thread pool, so that the runtime is never stalled.
+        let detector = detector.clone();
+        let Ok(detected) = tokio::task::spawn_blocking(move || detector()).await else {
+            tracing::warn!("The detection thread of the IP watch panicked.");
+            break;
+        };
+        for event in changes(&mut addresses, &detected) {
+            publish(&shared, event);
+        }
+    }
+
+    let mut shared = lock(&shared);
+    shared.closed = true;
+    if let Some(waker) = shared.waker.take() {
+        waker.wake();
+    }
+}
+
+/// Returns the changes between the known addresses and the detected ones, and updates the known addresses. A family whose
+/// detection failed is left untouched.
+fn changes(addresses: &mut (Option<Ipv4Addr>, Option<Ipv6Addr>), detected: &DetectionResult) -> Vec<IpChangeEvent> {
+    let mut events = Vec::new();
+    if let Some(new) = detected.ipv4.as_ref().ok().copied() {
+        if addresses.0 != Some(new) {
+            let old = addresses.0.replace(new).map(IpAddr::V4);
+            events.push(IpChangeEvent { old, new: IpAddr::V4(new), source: detected.ipv4_source.clone() });
+        }
+    }
+    if let Some(new) = detected.ipv6.as_ref().ok().copied() {
+        if addresses.1 != Some(new) {
+            let old = addresses.1.replace(new).map(IpAddr::V6);
+            events.push(IpChangeEvent { old, new: IpAddr::V6(new), source: detected.ipv6_source.clone() });
+        }
+    }
+    events
+}
+
+/// Queues the event for the consumer, dropping the oldest one if the queue is full, and wakes the consumer up.
+fn publish(shared: &Mutex<Shared>, event: IpChangeEvent) {
+    let mut shared = lock(shared);
+    if shared.events.len() >= CAPACITY {
+        shared.events.pop_front();
+        shared.dropped += 1;
+        tracing::debug!(dropped = shared.dropped, "The IP watch dropped an event, since its consumer is too slow.");
+    }
+    shared.events.push_back(event);
+    if let Some(waker) = shared.waker.take() {
+        waker.wake();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::api::Error;
+
+    /// Ticks a fixed number of times, and then ends.
+    struct Ticks(usize);
+
+    impl Stream for Ticks {
+        type Item = ();
+
+        fn poll_next(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<()>> {
+            match self.0 {
+                0 => Poll::Ready(None),
+                _ => {
+                    self.0 -= 1;
+                    Poll::Ready(Some(()))
+                }
+            }
+        }
+    }
+
+    /// Returns a detector replaying the scripted detections (as IPv4 and IPv6 addresses, `None` meaning a failed detection)
+    /// in order, and then failing.
+    fn scripted(script: Vec<(Option<&str>, Option<&str>)>) -> impl Fn() -> DetectionResult + Send + Sync + 'static {
+        let script: Vec<(Option<String>, Option<String>)> =
+            script.into_iter().map(|(a, b)| (a.map(String::from), b.map(String::from))).collect();
+        let script = Mutex::new(VecDeque::from(script));
+        move || {
+            let (ipv4, ipv6) = lock(&script).pop_front().unwrap_or((None, None));
+            let failure = || Error::Ip(String::from("http"), "The detection failed.".into());
+            DetectionResult {
+                ipv4_source: ipv4.as_ref().map(|_| String::from("https://ipv4.example.com")),
+                ipv6_source: ipv6.as_ref().map(|_| String::from("https://ipv6.example.com")),
+                ipv4: ipv4.map(|a| a.parse().unwrap()).ok_or_else(failure),
+                ipv6: ipv6.map(|a| a.parse().unwrap()).ok_or_else(failure),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_watch() {
+        // Ensures that only the changes are yielded, and that the failed detections keep the known addresses.
+        let script = vec![
+            (Some("203.0.113.7"), None),
+            (Some("203.0.113.7"), None),
+            (None, Some("2001:db8::7")),
+            (Some("203.0.113.8"), Some("2001:db8::8")),
+        ];
+        let mut watch = spawn(scripted(script), Some("203.0.113.6".parse().unwrap()), None, Ticks(4));
+        let event = watch.next().await.unwrap();
+        assert_eq!((event.old, event.new), (Some("203.0.113.6".parse().unwrap()), "203.0.113.7".parse().unwrap()));
+        assert_eq!(event.source.as_deref(), Some("https://ipv4.example.com"));
+        let event = watch.next().await.unwrap();
+        assert_eq!((event.old, event.new), (None, "2001:db8::7".parse().unwrap()));
+        assert_eq!(event.source.as_deref(), Some("https://ipv6.example.com"));
+        let event = watch.next().await.unwrap();
+        assert_eq!((event.old, event.new), (Some("203.0.113.7".parse().unwrap()), "203.0.113.8".parse().unwrap()));
+        let event = watch.next().await.unwrap();
+        assert_eq!((event.old, event.new), (Some("2001:db8::7".parse().unwrap()), "2001:db8::8".parse().unwrap()));
+        assert_eq!(watch.next().await, None);
+        assert_eq!(watch.dropped(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_watch_backpressure() {
+        // Ensures that the oldest events are dropped (and counted) when the consumer does not read them, without stalling
+        // the detection cycles.
+        let addresses: Vec<String> = (0..CAPACITY + 10).map(|i| format!("10.0.{}.{}", i / 256, i % 256)).collect();
+        let script = addresses.iter().map(|a| (Some(a.as_str()), None)).collect();
+        let mut watch = spawn(scripted(script), None, None, Ticks(CAPACITY + 10));
+        while !lock(&watch.shared).closed {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(watch.dropped(), 10);
+        let event = watch.next().await.unwrap();
+        assert_eq!(event.old, Some(addresses[9].parse().unwrap()));
+        assert_eq!(event.new, addresses[10].parse::<IpAddr>().unwrap());
+        let mut count = 1;
+        while watch.next().await.is_some() {
+            count += 1;
+        }
+        assert_eq!(count, CAPACITY);
+    }
+}
@@ -4,6 +4,3 @@
 //! The Wapi error enums and result types.
 
 pub(crate) mod api;
-
-#[cfg(debug_assertions)]
-pub(crate) mod utils;
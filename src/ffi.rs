@@ -0,0 +1,453 @@
+// Copyright 2025 Amon Rayfa.
+// SPDX-License-Identifier: Apache-2.0.
+
+//! This module contains the C API of the library, which lets the programs written in other languages (e.g. the management
+//! daemon of a router firmware) drive the client without shelling out to the program. It is only available with the `ffi`
+//! feature, and its C header (`include/wapi.h`) is generated from it with [cbindgen](https://github.com/mozilla/cbindgen)
+//! (see `cbindgen.toml`). The dynamic library exporting it is not built by default, and is built with
+//! `cargo rustc --lib --release --features ffi --crate-type cdylib`.
+//!
+//! The functions never unwind into the caller: a panic is caught at the boundary and reported like an error. When a function
+//! fails, it returns `NULL` (or `0`) and the failure can be retrieved with `wapi_last_error_status`, `wapi_last_error_code`,
+//! and `wapi_last_error_message`. The objects returned by the API must be released with their `_free` function.
+
+use crate::api::client::{Client, FileStore};
+use crate::api::update::{RecordOutcome, UpdateOptions, UpdateReport};
+use crate::Error;
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::ptr;
+
+/// The flag of `wapi_update` updating the records even if the detected IP addresses are the ones stored in the cache (see
+/// [`UpdateOptions::force`](wapi::api::update::UpdateOptions)).
+pub const WAPI_UPDATE_FORCE: u32 = 1 << 0;
+
+/// The flag of `wapi_update` skipping the records whose DNS provider reports safety warnings (see
+/// [`UpdateOptions::block_on_warnings`](wapi::api::update::UpdateOptions)).
+pub const WAPI_UPDATE_BLOCK_ON_WARNINGS: u32 = 1 << 1;
+
+/// The status of the last call made to the C API on the current thread.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WapiStatus {
+    /// The call succeeded.
+    Ok = 0,
+    /// The operation failed, and its stable code is returned by `wapi_last_error_code` (see
+    /// [`Error::code`](wapi::Error)).
+    Error = 1,
+    /// An argument is invalid (e.g. a `NULL` pointer or a string that is not valid UTF-8).
+    InvalidArgument = 2,
+    /// The library panicked, which was caught at the boundary of the C API.
+    Panic = 3,
+}
+
+/// The outcome of the update of a record, as returned by `wapi_report_record_outcome` (see
+/// [`RecordOutcome`](wapi::api::update::RecordOutcome)).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WapiOutcome {
+    /// The record was changed to point to the new address.
+    Updated = 0,
+    /// The record already pointed to the address.
+    Unchanged = 1,
+    /// The record was not updated, for the reason returned by `wapi_report_record_detail`.
+    Skipped = 2,
+    /// The record could not be updated, because of the error returned by `wapi_report_record_detail`.
+    Failed = 3,
+    /// The record was not updated because its DNS provider is disabled.
+    Disabled = 4,
+}
+
+/// The client driven through the C API, created with `wapi_client_new` and released with `wapi_client_free`.
+pub struct WapiClient {
+    client: Client,
+}
+
+/// The report of an update run, returned by `wapi_update` and released with `wapi_report_free`. Its strings are kept alive
+/// until it is released.
+pub struct WapiReport {
+    ipv4: Option<CString>,
+    ipv6: Option<CString>,
+    records: Vec<WapiRecord>,
+    report: UpdateReport,
+}
+
+/// A record of a [`WapiReport`], with its strings converted for the C API.
+struct WapiRecord {
+    provider: CString,
+    fqdn: CString,
+    detail: Option<CString>,
+    outcome: WapiOutcome,
+}
+
+impl WapiReport {
+    fn new(report: UpdateReport) -> WapiReport {
+        let records = report
+            .records
+            .iter()
+            .map(|r| {
+                let (outcome, detail) = match &r.outcome {
+                    RecordOutcome::Updated => (WapiOutcome::Updated, None),
+                    RecordOutcome::Unchanged => (WapiOutcome::Unchanged, None),
+                    RecordOutcome::Skipped(reason) => (WapiOutcome::Skipped, Some(c_string(reason))),
+                    RecordOutcome::Failed(error) => (WapiOutcome::Failed, Some(c_string(error))),
+                    RecordOutcome::Disabled => (WapiOutcome::Disabled, None),
+                };
+                WapiRecord { provider: c_string(&r.provider), fqdn: c_string(&r.fqdn), detail, outcome }
+            })
+            .collect();
+
+        WapiReport {
+            ipv4: report.ipv4.map(|a| c_string(&a.to_string())),
+            ipv6: report.ipv6.map(|a| c_string(&a.to_string())),
+            records,
+            report,
+        }
+    }
+}
+
+/// The failure of the last call made to the C API on the current thread.
+struct LastError {
+    status: WapiStatus,
+    code: Option<CString>,
+    message: CString,
+}
+
+thread_local! {
+    /// The failure of the last call made to the C API on the current thread, if it failed.
+    static LAST_ERROR: RefCell<Option<LastError>> = const { RefCell::new(None) };
+}
+
+/// Converts a string for the C API, dropping the interior NUL bytes (which C strings cannot hold).
+fn c_string(value: &str) -> CString {
+    CString::new(value.replace('\0', "")).unwrap_or_default()
+}
+
+/// Runs the body of a function of the C API, and returns its value. If it fails or panics, the failure is stored as the last
+/// error of the thread and the fallback value is returned instead.
+fn guard<T>(fallback: T, body: impl FnOnce() -> std::result::Result<T, LastError>) -> T {
+    let result = panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|payload| {
+        let reason = match (payload.downcast_ref::<&str>(), payload.downcast_ref::<String>()) {
+            (Some(reason), _) => reason.to_string(),
+            (_, Some(reason)) => reason.clone(),
+            _ => String::from("Unknown cause."),
+        };
+        Err(LastError {
+            status: WapiStatus::Panic,
+            code: None,
+            message: c_string(&format!("The library panicked: {}", reason)),
+        })
+    });
+
+    match result {
+        Ok(value) => {
+            LAST_ERROR.with(|e| *e.borrow_mut() = None);
+            value
+        }
+        Err(error) => {
+            LAST_ERROR.with(|e| *e.borrow_mut() = Some(error));
+            fallback
+        }
+    }
+}
+
+/// Returns the failure of an operation of the library.
+fn failure(error: Error) -> LastError {
+    LastError {
+        status: WapiStatus::Error,
+        code: Some(c_string(error.code())),
+        message: c_string(&format!("{} ({:?})", error, error)),
+    }
+}
+
+/// Returns the failure of an invalid argument.
+fn invalid_argument(message: &str) -> LastError {
+    LastError { status: WapiStatus::InvalidArgument, code: None, message: c_string(message) }
+}
+
+/// Creates a client keeping its cache in the cache file at the given path, or in the cache file of the program if the path is
+/// `NULL`. The client uses the default configurations, and must be released with `wapi_client_free`. `NULL` is returned if the
+/// path is not valid UTF-8 or if the client cannot be built.
+///
+/// # Safety
+///
+/// The path must be `NULL` or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn wapi_client_new(cache_path: *const c_char) -> *mut WapiClient {
+    guard(ptr::null_mut(), || {
+        let builder = match cache_path.is_null() {
+            true => Client::builder(),
+            false => {
+                let path =
+                    CStr::from_ptr(cache_path).to_str().map_err(|_| invalid_argument("The cache path is not valid UTF-8."))?;
+                Client::builder().cache_store(FileStore::new(Path::new(path)))
+            }
+        };
+        let client = builder.build().map_err(failure)?;
+        Ok(Box::into_raw(Box::new(WapiClient { client })))
+    })
+}
+
+/// Releases a client created with `wapi_client_new`. Nothing happens if the client is `NULL`.
+///
+/// # Safety
+///
+/// The client must be `NULL` or a pointer returned by `wapi_client_new` that was not released yet.
+#[no_mangle]
+pub unsafe extern "C" fn wapi_client_free(client: *mut WapiClient) {
+    if !client.is_null() {
+        drop(Box::from_raw(client));
+    }
+}
+
+/// Runs an update (see [`Client::update`](wapi::Client)) with the flags (`WAPI_UPDATE_*`, combined with `|`), and returns its
+/// report, which must be released with `wapi_report_free`. `NULL` is returned if the client is `NULL` or if the update fails.
+///
+/// # Safety
+///
+/// The client must be `NULL` or a valid pointer returned by `wapi_client_new`.
+#[no_mangle]
+pub unsafe extern "C" fn wapi_update(client: *const WapiClient, flags: u32) -> *mut WapiReport {
+    guard(ptr::null_mut(), || {
+        let client = client.as_ref().ok_or_else(|| invalid_argument("The client is NULL."))?;
+        let options = UpdateOptions {
+            force: flags & WAPI_UPDATE_FORCE != 0,
+            block_on_warnings: flags & WAPI_UPDATE_BLOCK_ON_WARNINGS != 0,
+            ..UpdateOptions::default()
+        };
+        let report = client.client.update(&options).map_err(failure)?;
+        Ok(Box::into_raw(Box::new(WapiReport::new(report))))
+    })
+}
+
+/// Releases a report returned by `wapi_update`, along with its strings. Nothing happens if the report is `NULL`.
+///
+/// # Safety
+///
+/// The report must be `NULL` or a pointer returned by `wapi_update` that was not released yet.
+#[no_mangle]
+pub unsafe extern "C" fn wapi_report_free(report: *mut WapiReport) {
+    if !report.is_null() {
+        drop(Box::from_raw(report));
+    }
+}
+
+/// Returns the IPv4 address the A records were updated to, or `NULL` if none was detected (or if the report is `NULL`). The
+/// string belongs to the report.
+///
+/// # Safety
+///
+/// The report must be `NULL` or a valid pointer returned by `wapi_update`.
+#[no_mangle]
+pub unsafe extern "C" fn wapi_report_ipv4(report: *const WapiReport) -> *const c_char {
+    report.as_ref().and_then(|r| r.ipv4.as_ref()).map_or(ptr::null(), |a| a.as_ptr())
+}
+
+/// Returns the IPv6 address the AAAA records were updated to, or `NULL` if none was detected (or if the report is `NULL`).
+/// The string belongs to the report.
+///
+/// # Safety
+///
+/// The report must be `NULL` or a valid pointer returned by `wapi_update`.
+#[no_mangle]
+pub unsafe extern "C" fn wapi_report_ipv6(report: *const WapiReport) -> *const c_char {
+    report.as_ref().and_then(|r| r.ipv6.as_ref()).map_or(ptr::null(), |a| a.as_ptr())
+}
+
+/// Returns the number of records that were updated (`0` if the report is `NULL`).
+///
+/// # Safety
+///
+/// The report must be `NULL` or a valid pointer returned by `wapi_update`.
+#[no_mangle]
+pub unsafe extern "C" fn wapi_report_updated(report: *const WapiReport) -> usize {
+    report.as_ref().map_or(0, |r| r.report.updated())
+}
+
+/// Returns the number of records that were already up to date (`0` if the report is `NULL`).
+///
+/// # Safety
+///
+/// The report must be `NULL` or a valid pointer returned by `wapi_update`.
+#[no_mangle]
+pub unsafe extern "C" fn wapi_report_unchanged(report: *const WapiReport) -> usize {
+    report.as_ref().map_or(0, |r| r.report.unchanged())
+}
+
+/// Returns the number of records that were skipped (`0` if the report is `NULL`).
+///
+/// # Safety
+///
+/// The report must be `NULL` or a valid pointer returned by `wapi_update`.
+#[no_mangle]
+pub unsafe extern "C" fn wapi_report_skipped(report: *const WapiReport) -> usize {
+    report.as_ref().map_or(0, |r| r.report.skipped())
+}
+
+/// Returns the number of records that could not be updated (`0` if the report is `NULL`).
+///
+/// # Safety
+///
+/// The report must be `NULL` or a valid pointer returned by `wapi_update`.
+#[no_mangle]
+pub unsafe extern "C" fn wapi_report_failed(report: *const WapiReport) -> usize {
+    report.as_ref().map_or(0, |r| r.report.failed())
+}
+
+/// Returns the number of records that were not updated because their DNS provider is disabled (`0` if the report is
+/// `NULL`).
+///
+/// # Safety
+///
+/// The report must be `NULL` or a valid pointer returned by `wapi_update`.
+#[no_mangle]
+pub unsafe extern "C" fn wapi_report_disabled(report: *const WapiReport) -> usize {
+    report.as_ref().map_or(0, |r| r.report.disabled())
+}
+
+/// Returns the number of records of the report, which are indexed from `0` by the `wapi_report_record_*` functions (`0` if
+/// the report is `NULL`).
+///
+/// # Safety
+///
+/// The report must be `NULL` or a valid pointer returned by `wapi_update`.
+#[no_mangle]
+pub unsafe extern "C" fn wapi_report_record_count(report: *const WapiReport) -> usize {
+    report.as_ref().map_or(0, |r| r.records.len())
+}
+
+/// Returns the DNS provider of a record of the report (followed by its alias if it has one), or `NULL` if the index is out of
+/// bounds (or if the report is `NULL`). The string belongs to the report.
+///
+/// # Safety
+///
+/// The report must be `NULL` or a valid pointer returned by `wapi_update`.
+#[no_mangle]
+pub unsafe extern "C" fn wapi_report_record_provider(report: *const WapiReport, index: usize) -> *const c_char {
+    report.as_ref().and_then(|r| r.records.get(index)).map_or(ptr::null(), |r| r.provider.as_ptr())
+}
+
+/// Returns the fully qualified domain name of a record of the report, or `NULL` if the index is out of bounds (or if the
+/// report is `NULL`). The string belongs to the report.
+///
+/// # Safety
+///
+/// The report must be `NULL` or a valid pointer returned by `wapi_update`.
+#[no_mangle]
+pub unsafe extern "C" fn wapi_report_record_fqdn(report: *const WapiReport, index: usize) -> *const c_char {
+    report.as_ref().and_then(|r| r.records.get(index)).map_or(ptr::null(), |r| r.fqdn.as_ptr())
+}
+
+/// Returns the outcome of a record of the report. `WAPI_OUTCOME_FAILED` is returned if the index is out of bounds (or if the
+/// report is `NULL`).
+///
+/// # Safety
+///
+/// The report must be `NULL` or a valid pointer returned by `wapi_update`.
+#[no_mangle]
+pub unsafe extern "C" fn wapi_report_record_outcome(report: *const WapiReport, index: usize) -> WapiOutcome {
+    report.as_ref().and_then(|r| r.records.get(index)).map_or(WapiOutcome::Failed, |r| r.outcome)
+}
+
+/// Returns the reason a record of the report was skipped or the error that prevented its update, or `NULL` if it has none
+/// (or if the index is out of bounds, or if the report is `NULL`). The string belongs to the report.
+///
+/// # Safety
+///
+/// The report must be `NULL` or a valid pointer returned by `wapi_update`.
+#[no_mangle]
+pub unsafe extern "C" fn wapi_report_record_detail(report: *const WapiReport, index: usize) -> *const c_char {
+    report.as_ref().and_then(|r| r.records.get(index)).and_then(|r| r.detail.as_ref()).map_or(ptr::null(), |d| d.as_ptr())
+}
+
+/// Returns the status of the last call made to the C API on the current thread.
+#[no_mangle]
+pub extern "C" fn wapi_last_error_status() -> WapiStatus {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(WapiStatus::Ok, |e| e.status))
+}
+
+/// Returns the stable code of the last error of the current thread (one of [`ERROR_CODES`](wapi::ERROR_CODES)), or `NULL` if
+/// the last call succeeded or did not fail with `WAPI_STATUS_ERROR`. The string is valid until the next call made to the C
+/// API on the current thread.
+#[no_mangle]
+pub extern "C" fn wapi_last_error_code() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().and_then(|e| e.code.as_ref()).map_or(ptr::null(), |c| c.as_ptr()))
+}
+
+/// Returns the message of the last error of the current thread, or `NULL` if the last call succeeded. The string is valid
+/// until the next call made to the C API on the current thread.
+#[no_mangle]
+pub extern "C" fn wapi_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |e| e.message.as_ptr()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api::cache::{Cache, RecordConfig, RecordType};
+    use crate::api::client::MemoryStore;
+    use crate::api::ip::IpConfig;
+
+    /// Returns the string of the C API as a Rust string, or `None` if it is `NULL`.
+    fn string(value: *const c_char) -> Option<String> {
+        (!value.is_null()).then(|| unsafe { CStr::from_ptr(value) }.to_string_lossy().into_owned())
+    }
+
+    #[test]
+    fn test_guard() {
+        // Ensures that the panics are caught and reported, and that a successful call clears the last error.
+        assert!(!guard(false, || panic!("Something went wrong.")));
+        assert_eq!(wapi_last_error_status(), WapiStatus::Panic);
+        assert_eq!(string(wapi_last_error_message()).unwrap(), "The library panicked: Something went wrong.");
+        assert!(wapi_last_error_code().is_null());
+        assert!(guard(false, || Ok(true)));
+        assert_eq!(wapi_last_error_status(), WapiStatus::Ok);
+        assert!(wapi_last_error_message().is_null());
+
+        // Ensures that the errors of the library are reported with their stable code.
+        let error = Error::Cancelled(String::from("update"), "The run was cancelled.".into());
+        assert!(guard(ptr::null_mut::<WapiClient>(), || Err(failure(error))).is_null());
+        assert_eq!(wapi_last_error_status(), WapiStatus::Error);
+        assert_eq!(string(wapi_last_error_code()).unwrap(), "E_CANCELLED");
+    }
+
+    #[test]
+    fn test_update() {
+        // Ensures that the NULL pointers are rejected, and that the accessors of a NULL report return empty values.
+        unsafe {
+            assert!(wapi_update(ptr::null(), 0).is_null());
+            assert_eq!(wapi_last_error_status(), WapiStatus::InvalidArgument);
+            assert_eq!(wapi_report_record_count(ptr::null()), 0);
+            assert!(wapi_report_record_fqdn(ptr::null(), 0).is_null());
+            wapi_report_free(ptr::null_mut());
+            wapi_client_free(ptr::null_mut());
+        }
+
+        // Runs an update with a client that detects no IP address, so that its record is skipped.
+        let mut cache = Cache::new();
+        cache.add_dns_provider(String::from("mock:success"), String::new(), String::new());
+        cache.add_record("mock:success", RecordConfig::new("example.com", "home", RecordType::A, 600)).unwrap();
+        let config = IpConfig { ipv4_endpoints: Vec::new(), ipv6_endpoints: Vec::new(), ..IpConfig::default() };
+        let client = Client::builder().cache_store(MemoryStore::with_cache(cache)).config(config).build().unwrap();
+        let client = Box::into_raw(Box::new(WapiClient { client }));
+
+        unsafe {
+            let report = wapi_update(client, WAPI_UPDATE_FORCE);
+            assert!(!report.is_null());
+            assert_eq!(wapi_last_error_status(), WapiStatus::Ok);
+            assert!(wapi_report_ipv4(report).is_null() && wapi_report_ipv6(report).is_null());
+            assert_eq!((wapi_report_updated(report), wapi_report_skipped(report), wapi_report_failed(report)), (0, 1, 0));
+            assert_eq!(wapi_report_disabled(report), 0);
+            assert_eq!(wapi_report_record_count(report), 1);
+            assert_eq!(string(wapi_report_record_provider(report, 0)).unwrap(), "mock:success");
+            assert_eq!(string(wapi_report_record_fqdn(report, 0)).unwrap(), "home.example.com");
+            assert_eq!(wapi_report_record_outcome(report, 0), WapiOutcome::Skipped);
+            assert!(string(wapi_report_record_detail(report, 0)).is_some());
+            assert!(wapi_report_record_provider(report, 1).is_null());
+            wapi_report_free(report);
+            wapi_client_free(client);
+        }
+    }
+}
@@ -16,12 +16,66 @@
 //!
 //! # Cargo Features
 //!
-//! This crate has no public
-//! [Cargo features](https://doc.rust-lang.org/stable/cargo/reference/features.html#the-features-section).
+//! This crate has the following
+//! [Cargo features](https://doc.rust-lang.org/stable/cargo/reference/features.html#the-features-section):
+//!
+//! - `async`: Enables the asynchronous variants of the [`Client`](wapi::Client) operations (e.g. `Client::update_async`),
+//!   which run the blocking implementation on the blocking thread pool of [tokio](https://tokio.rs), so that it can be
+//!   embedded in an asynchronous service, and the stream of the changes of the public IP addresses (`Client::watch`). The
+//!   detection and the DNS provider backends remain blocking, so the asynchronous API wraps the blocking one (and not the
+//!   other way around).
+//! - `ffi`: Enables the C API of the [`ffi`](wapi::ffi) module, declared in the `include/wapi.h` header. The dynamic library
+//!   exporting it is built with `cargo rustc --lib --release --features ffi --crate-type cdylib`.
+//! - `providers-all` (default): Enables every DNS provider backend.
+//! - `provider-desec`, `provider-dyndns2`, `provider-googleclouddns`, `provider-hetzner`, `provider-linode`: Enable a single
+//!   DNS provider backend. The DNS providers that are not compiled in are not listed in
+//!   [`SUPPORTED_PROVIDERS`](wapi::api::provider::SUPPORTED_PROVIDERS), so they cannot be added to the cache and their
+//!   backend fails to build, but the ones already stored in the cache file are kept.
+//! - `testing`: Enables the testing harness for the programs embedding the crate: the simulated and scriptable DNS providers
+//!   (`api::provider::mock::MockProvider`, with IDs starting with `mock:`), the temporary caches
+//!   (`api::testing::TempCache`), and the record-and-replay layer of the DNS provider backends (`api::replay`).
 
-mod api;
+pub mod api;
 mod error;
-pub use api::cache::Cache;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub use api::cache::{
+    Cache, ConflictPolicy, ProviderView, RecordConfig, RecordType, RunRecord, TimestampConfig, TimestampFormat, TimestampZone,
+    UpsertOutcome,
+};
+pub use api::client::Client;
+pub use error::api::{Cause, Error, ErrorKind, ProviderError, ResultExt, ERROR_CODES};
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    /// Returns the attributes of the Rust files of the directory (and of its subdirectories) that compile an item depending on
+    /// the build profile, along with their location.
+    fn profile_gates(dir: &Path) -> Vec<String> {
+        let mut gates = Vec::new();
+        for entry in std::fs::read_dir(dir).unwrap().flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                gates.extend(profile_gates(&path));
+            } else if path.extension().is_some_and(|e| e == "rs") {
+                let source = std::fs::read_to_string(&path).unwrap();
+                gates.extend(
+                    source
+                        .lines()
+                        .enumerate()
+                        .filter(|(_, l)| l.trim_start().starts_with("#[cfg") && l.contains("debug_assertions"))
+                        .map(|(i, l)| format!("{}:{}: {}", path.display(), i + 1, l.trim())),
+                );
+            }
+        }
+        gates
+    }
 
-#[cfg(debug_assertions)]
-mod utils;
+    #[test]
+    fn test_profile_parity() {
+        // Ensures that the debug and release builds expose the same API, since no item is compiled for only one of them.
+        let gates = profile_gates(&Path::new(env!("CARGO_MANIFEST_DIR")).join("src"));
+        assert!(gates.is_empty(), "Some items depend on the build profile:\n{}", gates.join("\n"));
+    }
+}
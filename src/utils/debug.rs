@@ -1,2 +0,0 @@
-// Copyright 2025 Amon Rayfa.
-// SPDX-License-Identifier: Apache-2.0.
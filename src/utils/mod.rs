@@ -1,4 +0,0 @@
-// Copyright 2025 Amon Rayfa.
-// SPDX-License-Identifier: Apache-2.0.
-
-pub(crate) mod debug;
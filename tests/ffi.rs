@@ -0,0 +1,54 @@
+// Copyright 2025 Amon Rayfa.
+// SPDX-License-Identifier: Apache-2.0.
+
+//! This module contains the test of the C API, which builds the dynamic library of the crate, compiles the C program of
+//! `tests/ffi/main.c` against it and the header of the crate, and runs it.
+
+#![cfg(all(feature = "ffi", unix))]
+
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+
+#[test]
+fn test_c_program() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    // The dynamic library of the crate is not built by `cargo test`, so it is built in an `ffi` directory next to the `debug`
+    // one of the target directory (the test binary being in its `deps` directory), which keeps it apart from the running
+    // build. It is built offline, since its dependencies were already fetched to build the test.
+    let target_dir = env::current_exe().unwrap().ancestors().nth(3).unwrap().join("ffi");
+    let status = Command::new(env!("CARGO"))
+        .args(["rustc", "--offline", "--lib", "--features", "ffi", "--crate-type", "cdylib", "--manifest-path"])
+        .arg(root.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .status()
+        .expect("Cargo could not be run.");
+    assert!(status.success(), "The dynamic library could not be built.");
+    let lib_dir = target_dir.join("debug");
+    let temp_dir = env::temp_dir().join(format!("wapi-ffi-{}", std::process::id()));
+    std::fs::create_dir_all(&temp_dir).unwrap();
+    let program = temp_dir.join("main");
+
+    let status = Command::new(env::var("CC").unwrap_or_else(|_| String::from("cc")))
+        .arg(root.join("tests/ffi/main.c"))
+        .arg("-I")
+        .arg(root.join("include"))
+        .arg("-L")
+        .arg(&lib_dir)
+        .arg("-lwapi")
+        .arg("-o")
+        .arg(&program)
+        .status()
+        .expect("The C compiler could not be run.");
+    assert!(status.success(), "The C program could not be compiled.");
+
+    let output = Command::new(&program)
+        .arg(temp_dir.join("missing.json"))
+        .env("LD_LIBRARY_PATH", &lib_dir)
+        .env("DYLD_LIBRARY_PATH", &lib_dir)
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(&temp_dir).unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+}
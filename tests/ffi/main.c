@@ -0,0 +1,44 @@
+/* Copyright 2025 Amon Rayfa.
+ * SPDX-License-Identifier: Apache-2.0. */
+
+/* The C program linked against the dynamic library of the crate by `tests/ffi.rs`, which checks that the C API can be used
+ * from C. It takes the path of a cache file that does not exist, so that the update fails before any network request. */
+
+#include <stdio.h>
+#include <string.h>
+
+#include "wapi.h"
+
+#define CHECK(condition)                                                                                                       \
+    if (!(condition)) {                                                                                                        \
+        fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, #condition);                                         \
+        return 1;                                                                                                              \
+    }
+
+int main(int argc, char **argv) {
+    CHECK(argc == 2);
+
+    // Ensures that the NULL arguments are rejected, and that the functions accept them without crashing.
+    CHECK(wapi_update(NULL, 0) == NULL);
+    CHECK(wapi_last_error_status() == WAPI_STATUS_INVALID_ARGUMENT);
+    CHECK(wapi_last_error_code() == NULL);
+    CHECK(wapi_last_error_message() != NULL);
+    CHECK(wapi_report_record_count(NULL) == 0);
+    CHECK(wapi_report_record_fqdn(NULL, 0) == NULL);
+    CHECK(wapi_report_record_outcome(NULL, 0) == WAPI_OUTCOME_FAILED);
+    wapi_report_free(NULL);
+    wapi_client_free(NULL);
+
+    // Ensures that the client can be created, and that the failure of an update is reported with its stable code.
+    WapiClient *client = wapi_client_new(argv[1]);
+    CHECK(client != NULL);
+    CHECK(wapi_last_error_status() == WAPI_STATUS_OK);
+    WapiReport *report = wapi_update(client, WAPI_UPDATE_FORCE | WAPI_UPDATE_BLOCK_ON_WARNINGS);
+    CHECK(report == NULL);
+    CHECK(wapi_last_error_status() == WAPI_STATUS_ERROR);
+    CHECK(wapi_last_error_code() != NULL && strcmp(wapi_last_error_code(), "E_CACHE_READ") == 0);
+    CHECK(wapi_last_error_message() != NULL && strlen(wapi_last_error_message()) > 0);
+    wapi_client_free(client);
+
+    return 0;
+}